@@ -0,0 +1,73 @@
+#!/bin/sh
+# Downloads the logs snapshot into the database of a hoprd node, verifying its age and checksum.
+# The outcome is written to the termination log of the container, from where the operator reports it in the node status.
+#
+# SNAPSHOT_URL       URL of the tar.xz snapshot
+# SNAPSHOT_SHA256    Expected sha256 checksum of the snapshot. Not verified if empty
+# SNAPSHOT_MAX_AGE   Maximum age in seconds of the snapshot, read from its Last-Modified header. Not verified if empty
+# SNAPSHOT_REQUIRED  Whether the node must not start without the snapshot
+# SNAPSHOT_RETRIES   Download attempts before giving up
+
+set -u
+DB_DIR=${HOPRD_DB_DIR:-/app/hoprd-db/db}
+TERMINATION_LOG=${TERMINATION_LOG:-/dev/termination-log}
+ARCHIVE="${DB_DIR}/snapshot.tar.xz"
+EXTRACT_DIR="${DB_DIR}/.snapshot"
+
+finish() {
+  echo "$1: $2" | tee "${TERMINATION_LOG}"
+  exit "$3"
+}
+
+give_up() {
+  rm -rf "${ARCHIVE}" "${EXTRACT_DIR}"
+  if [ "${SNAPSHOT_REQUIRED:-false}" = "true" ]; then
+    finish Failed "$1" 1
+  else
+    finish Skipped "$1" 0
+  fi
+}
+
+if ls "${DB_DIR}"/hopr_logs.db* > /dev/null 2>&1; then
+  finish Completed "The database already contains the logs" 0
+fi
+mkdir -p "${DB_DIR}"
+
+if [ -n "${SNAPSHOT_MAX_AGE:-}" ]; then
+  last_modified=$(curl -sfIL "${SNAPSHOT_URL}" | grep -i '^last-modified:' | tail -1 | cut -d' ' -f2- | tr -d '\r')
+  if [ -z "${last_modified}" ]; then
+    give_up "The age of the snapshot cannot be read from ${SNAPSHOT_URL}"
+  fi
+  age=$(( $(date +%s) - $(date -d "${last_modified}" +%s) ))
+  if [ "${age}" -gt "${SNAPSHOT_MAX_AGE}" ]; then
+    give_up "The snapshot is ${age} seconds old, older than the maximum of ${SNAPSHOT_MAX_AGE}"
+  fi
+fi
+
+attempt=1
+while true; do
+  if curl -sfL -o "${ARCHIVE}" "${SNAPSHOT_URL}"; then
+    if [ -z "${SNAPSHOT_SHA256:-}" ] || echo "${SNAPSHOT_SHA256}  ${ARCHIVE}" | sha256sum -c --status 2> /dev/null; then
+      break
+    fi
+    error="The sha256 checksum of the snapshot does not match ${SNAPSHOT_SHA256}"
+  else
+    error="Could not download ${SNAPSHOT_URL}"
+  fi
+  rm -f "${ARCHIVE}"
+  if [ "${attempt}" -ge "${SNAPSHOT_RETRIES:-3}" ]; then
+    give_up "${error} after ${attempt} attempts"
+  fi
+  echo "${error}, retrying (attempt ${attempt})"
+  sleep $(( attempt * 10 ))
+  attempt=$(( attempt + 1 ))
+done
+
+# The snapshot is extracted aside, so an interrupted extraction does not leave a partial database behind
+rm -rf "${EXTRACT_DIR}" && mkdir -p "${EXTRACT_DIR}"
+if ! tar xJf "${ARCHIVE}" -C "${EXTRACT_DIR}"; then
+  give_up "Could not extract the snapshot"
+fi
+mv "${EXTRACT_DIR}"/* "${DB_DIR}/"
+rm -rf "${ARCHIVE}" "${EXTRACT_DIR}"
+finish Completed "Downloaded the snapshot in ${attempt} attempts" 0
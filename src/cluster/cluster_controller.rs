@@ -29,6 +29,8 @@ enum ClusterHoprdAction {
     Rescale,
     /// Delete all subresources created in the `Create` phase
     Delete,
+    /// Refresh the count of unhealthy nodes of a cluster in the desired state
+    SyncStatus,
     /// This `ClusterHoprd` resource is in desired state and requires no actions to be taken
     NoOp,
 }
@@ -54,7 +56,7 @@ fn determine_action(cluster_hoprd: &ClusterHoprd) -> ClusterHoprdAction {
         if observed_generation < current_generation {
             ClusterHoprdAction::Modify
         } else {
-            ClusterHoprdAction::NoOp
+            ClusterHoprdAction::SyncStatus
         }
     }
 }
@@ -66,6 +68,7 @@ async fn reconciler(cluster_hoprd: Arc<ClusterHoprd>, context: Arc<ContextData>)
         ClusterHoprdAction::Modify => cluster_hoprd.modify(context.clone()).await,
         ClusterHoprdAction::Delete => cluster_hoprd.delete(context.clone()).await,
         ClusterHoprdAction::Rescale => cluster_hoprd.rescale(context.clone()).await,
+        ClusterHoprdAction::SyncStatus => cluster_hoprd.sync_status(context.clone()).await,
         // The resource is already in desired state, do nothing and re-check after 10 seconds
         ClusterHoprdAction::NoOp => Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))),
     }
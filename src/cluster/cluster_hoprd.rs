@@ -1,7 +1,10 @@
 use crate::events::ClusterHoprdEventEnum;
 use crate::hoprd::{
     hoprd_deployment_spec::HoprdDeploymentSpec,
-    hoprd_resource::{Hoprd, HoprdSpec},
+    hoprd_config::HoprdConfigRef,
+    hoprd_remediation::HoprdRemediationSpec,
+    hoprd_snapshot::HoprdSnapshotSpec,
+    hoprd_resource::{Hoprd, HoprdPhaseEnum, HoprdSpec},
     hoprd_service::HoprdServiceSpec,
 };
 use crate::model::Error;
@@ -38,14 +41,19 @@ use tracing::{debug, error, info, warn};
 pub struct ClusterHoprdSpec {
     pub identity_pool_name: String,
     pub replicas: i32,
+    #[serde(default)]
     pub config: String,
+    pub config_ref: Option<HoprdConfigRef>,
     pub version: String,
+    pub image: Option<String>,
     pub enabled: Option<bool>,
     pub service: HoprdServiceSpec,
     pub deployment: Option<HoprdDeploymentSpec>,
     pub profiling_enabled: Option<bool>,
     pub source_node_logs: Option<String>,
     pub dns_name: Option<String>,
+    pub remediation: Option<HoprdRemediationSpec>,
+    pub snapshot: Option<HoprdSnapshotSpec>,
 }
 
 /// The status object of `Hoprd`
@@ -55,6 +63,8 @@ pub struct ClusterHoprdStatus {
     pub phase: ClusterHoprdPhaseEnum,
     pub observed_generation: i64,
     pub current_nodes: i32,
+    /// Nodes of the cluster in a Degraded or Failed phase
+    pub unhealthy_nodes: Option<i32>,
 }
 
 impl Default for ClusterHoprdStatus {
@@ -63,6 +73,7 @@ impl Default for ClusterHoprdStatus {
             phase: ClusterHoprdPhaseEnum::Initialized,
             observed_generation: 0,
             current_nodes: 0,
+            unhealthy_nodes: None,
         }
     }
 }
@@ -308,6 +319,23 @@ impl ClusterHoprd {
         }
     }
 
+    /// Counts the nodes of the cluster that are not healthy, keeping the status in sync while the cluster is in the desired state
+    pub async fn sync_status(&self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        let api: Api<Hoprd> = Api::namespaced(context_data.client.clone(), &self.namespace().unwrap());
+        let unhealthy_nodes = count_unhealthy_nodes(&self.get_hoprd_nodes_from_cluster(api).await?);
+        if self.status.as_ref().and_then(|status| status.unhealthy_nodes) != Some(unhealthy_nodes) {
+            if unhealthy_nodes > 0 {
+                warn!("ClusterHoprd {} has {} unhealthy nodes", self.name_any(), unhealthy_nodes);
+            }
+            let cluster_api: Api<ClusterHoprd> = Api::namespaced(context_data.client.clone(), &self.namespace().unwrap());
+            let patch = Patch::Merge(json!({ "status": { "unhealthyNodes": unhealthy_nodes } }));
+            if let Err(error) = cluster_api.patch_status(&self.name_any(), &PatchParams::default(), &patch).await {
+                error!("Could not update the unhealthy nodes on cluster {}: {:?}", self.name_any(), error);
+            }
+        }
+        Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+    }
+
     // Finds the next free node in the cluster. We use this function, because a node might be missing in the middle of the list of nodes
     async fn get_next_free_node(&self, client: Client) -> i32 {
         let api: Api<Hoprd> = Api::namespaced(client, &self.namespace().unwrap());
@@ -366,7 +394,8 @@ impl ClusterHoprd {
             config: self.spec.config.to_owned(),
             enabled: self.spec.enabled,
             version: self.spec.version.to_owned(),
-            deployment: self.spec.deployment.to_owned(),
+            image: self.spec.image.to_owned(),
+            deployment: Some(HoprdDeploymentSpec::for_cluster_nodes(self.spec.deployment.clone(), &self.name_any())?),
             profiling_enabled: self.spec.profiling_enabled,
             identity_pool_name: self.spec.identity_pool_name.to_owned(),
             delete_database: Some(false),
@@ -377,6 +406,9 @@ impl ClusterHoprd {
             identity_name,
             source_node_logs,
             dns_name: self.spec.dns_name.to_owned(),
+            remediation: self.spec.remediation.to_owned(),
+            config_ref: self.spec.config_ref.to_owned(),
+            snapshot: self.spec.snapshot.to_owned(),
         };
         match self.create_hoprd_resource(context_data.clone(), node_name.to_owned(), hoprd_spec).await {
             Ok(_) => {
@@ -412,7 +444,8 @@ impl ClusterHoprd {
 
     /// Creates a hoprd resource
     async fn create_hoprd_resource(&self, context_data: Arc<ContextData>, name: String, hoprd_spec: HoprdSpec) -> Result<Hoprd, Error> {
-        let mut labels: BTreeMap<String, String> = utils::common_lables(hoprd_spec.identity_pool_name.to_owned(), Some(name.to_owned()), Some("node".to_owned()));
+        let mut labels: BTreeMap<String, String> = utils::user_labels(self.labels());
+        labels.extend(utils::common_lables(hoprd_spec.identity_pool_name.to_owned(), Some(name.to_owned()), Some("node".to_owned())));
         labels.insert(constants::LABEL_NODE_CLUSTER.to_owned(), self.name_any());
         let api: Api<Hoprd> = Api::namespaced(context_data.client.clone(), &self.namespace().unwrap());
         let owner_references: Option<Vec<OwnerReference>> = Some(vec![self.controller_owner_ref(&()).unwrap()]);
@@ -444,7 +477,8 @@ impl ClusterHoprd {
             config: self.spec.config.to_owned(),
             enabled: self.spec.enabled,
             version: self.spec.version.to_owned(),
-            deployment: self.spec.deployment.to_owned(),
+            image: self.spec.image.to_owned(),
+            deployment: Some(HoprdDeploymentSpec::for_cluster_nodes(self.spec.deployment.clone(), &self.name_any())?),
             profiling_enabled: self.spec.profiling_enabled,
             delete_database: Some(false),
             identity_pool_name: self.spec.identity_pool_name.to_owned(),
@@ -455,6 +489,9 @@ impl ClusterHoprd {
             identity_name: "temp".to_string(), // Will be overwritten in the loop
             source_node_logs: Some(false),
             dns_name: self.spec.dns_name.to_owned(),
+            remediation: self.spec.remediation.to_owned(),
+            config_ref: self.spec.config_ref.to_owned(),
+            snapshot: self.spec.snapshot.to_owned(),
         };
 
         for hoprd_node in self.get_hoprd_nodes_from_cluster(api.clone()).await.unwrap() {
@@ -501,3 +538,35 @@ impl ClusterHoprd {
         Ok(())
     }
 }
+
+/// Nodes that are degraded or failed
+fn count_unhealthy_nodes(nodes: &[Hoprd]) -> i32 {
+    nodes
+        .iter()
+        .filter(|node| node.status.as_ref().is_some_and(|status| status.phase.eq(&HoprdPhaseEnum::Degraded) || status.phase.eq(&HoprdPhaseEnum::Failed)))
+        .count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hoprd::hoprd_resource::HoprdStatus;
+
+    #[test]
+    fn test_count_unhealthy_nodes() {
+        let node = |phase: Option<HoprdPhaseEnum>| Hoprd {
+            status: phase.map(|phase| HoprdStatus { phase, ..HoprdStatus::default() }),
+            ..Hoprd::default()
+        };
+        assert_eq!(count_unhealthy_nodes(&[]), 0);
+        let nodes = [
+            node(Some(HoprdPhaseEnum::Running)),
+            node(Some(HoprdPhaseEnum::Degraded)),
+            node(Some(HoprdPhaseEnum::Failed)),
+            node(Some(HoprdPhaseEnum::Stopped)),
+            node(Some(HoprdPhaseEnum::Initializing)),
+            node(None),
+        ];
+        assert_eq!(count_unhealthy_nodes(&nodes), 2);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::cluster::{cluster_hoprd, cluster_hoprd::ClusterHoprdStatus};
+use crate::hoprd::{hoprd_config::HoprdConfigRef, hoprd_deployment_spec, hoprd_remediation::HoprdRemediationSpec, hoprd_snapshot::HoprdSnapshotSpec, hoprd_service::HoprdServiceSpec, hoprd_v1beta1::HoprdDeploymentSpec};
+use crate::model::Error;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Struct corresponding to the Specification (`spec`) part of the `v1beta1` version of the `ClusterHoprd` resource.
+/// This version is only served, the objects are stored as `v1alpha3` and translated by the conversion webhook.
+#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[kube(group = "hoprnet.org", version = "v1beta1", kind = "ClusterHoprd", plural = "clusterhoprds", derive = "PartialEq", namespaced)]
+#[kube(status = "ClusterHoprdStatus", shortname = "clusterhoprd")]
+#[serde(rename_all = "camelCase")]
+pub struct ClusterHoprdSpec {
+    pub identity_pool_name: String,
+    pub replicas: i32,
+    #[serde(default)]
+    pub config: String,
+    pub config_ref: Option<HoprdConfigRef>,
+    pub version: String,
+    pub image: Option<String>,
+    pub enabled: Option<bool>,
+    pub service: HoprdServiceSpec,
+    pub deployment: Option<HoprdDeploymentSpec>,
+    pub profiling_enabled: Option<bool>,
+    pub source_node_logs: Option<String>,
+    pub dns_name: Option<String>,
+    pub remediation: Option<HoprdRemediationSpec>,
+    pub snapshot: Option<HoprdSnapshotSpec>,
+}
+
+impl TryFrom<cluster_hoprd::ClusterHoprdSpec> for ClusterHoprdSpec {
+    type Error = Error;
+
+    fn try_from(spec: cluster_hoprd::ClusterHoprdSpec) -> Result<Self, Error> {
+        Ok(Self {
+            identity_pool_name: spec.identity_pool_name,
+            replicas: spec.replicas,
+            config: spec.config,
+            version: spec.version,
+            image: spec.image,
+            enabled: spec.enabled,
+            service: spec.service,
+            deployment: spec.deployment.map(HoprdDeploymentSpec::try_from).transpose()?,
+            profiling_enabled: spec.profiling_enabled,
+            source_node_logs: spec.source_node_logs,
+            dns_name: spec.dns_name,
+            remediation: spec.remediation,
+            config_ref: spec.config_ref,
+            snapshot: spec.snapshot,
+        })
+    }
+}
+
+impl TryFrom<ClusterHoprdSpec> for cluster_hoprd::ClusterHoprdSpec {
+    type Error = Error;
+
+    fn try_from(spec: ClusterHoprdSpec) -> Result<Self, Error> {
+        Ok(Self {
+            identity_pool_name: spec.identity_pool_name,
+            replicas: spec.replicas,
+            config: spec.config,
+            version: spec.version,
+            image: spec.image,
+            enabled: spec.enabled,
+            service: spec.service,
+            deployment: spec.deployment.map(hoprd_deployment_spec::HoprdDeploymentSpec::try_from).transpose()?,
+            profiling_enabled: spec.profiling_enabled,
+            source_node_logs: spec.source_node_logs,
+            dns_name: spec.dns_name,
+            remediation: spec.remediation,
+            config_ref: spec.config_ref,
+            snapshot: spec.snapshot,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+    use kube::CustomResourceExt;
+
+    #[test]
+    fn test_chart_schema_matches_generated_schema() {
+        let generated_schema = serde_json::to_value(&ClusterHoprd::crd().spec.versions[0].schema).unwrap();
+        assert_eq!(utils::crd_schema_from_chart("charts/hoprd-crds/templates/cluster-hoprd.yaml", "v1beta1"), generated_schema);
+    }
+}
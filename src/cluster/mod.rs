@@ -1,2 +1,3 @@
 pub mod cluster_controller;
 pub mod cluster_hoprd;
+pub mod cluster_hoprd_v1beta1;
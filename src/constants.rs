@@ -4,20 +4,40 @@ pub const RECONCILE_LONG_FREQUENCY: u64 = 30;
 pub const OPERATOR_ENVIRONMENT: &str = "OPERATOR_ENVIRONMENT";
 pub const OPERATOR_FINALIZER: &str = "hoprds.hoprnet.org/finalizer";
 pub const OPERATOR_METRICS_CONTAINER_TAG: &str = "edinburgh";
+// This value `OPERATOR_SNAPSHOT_CONTAINER_TAG` should match the version in snapshot-container/VERSION
+pub const OPERATOR_SNAPSHOT_CONTAINER_TAG: &str = "1.0.0";
 pub const OPERATOR_JOB_TIMEOUT: u64 = 300;
+pub const OPERATOR_SHUTDOWN_TIMEOUT: u64 = 10;
 // This value `OPERATOR_NODE_SYNC_TIMEOUT` should be lower than 295
 pub const OPERATOR_NODE_SYNC_TIMEOUT: u32 = 290;
+pub const OPERATOR_RPC_TIMEOUT: u64 = 10;
 pub const IDENTITY_POOL_WALLET_DEPLOYER_PRIVATE_KEY_REF_KEY: &str = "DEPLOYER_PRIVATE_KEY";
 pub const IDENTITY_POOL_WALLET_PRIVATE_KEY_REF_KEY: &str = "PRIVATE_KEY";
+pub const IDENTITY_POOL_IDENTITY_PASSWORD_REF_KEY: &str = "IDENTITY_PASSWORD";
+pub const IDENTITY_POOL_PROVISIONING_HISTORY_SIZE: usize = 10;
+pub const IDENTITY_POOL_FUNDING_HISTORY_SIZE: usize = 20;
+pub const IDENTITY_HOPRD_SECRET_KEY: &str = ".hopr-id";
 
 // Annotations
 pub const ANNOTATION_LAST_CONFIGURATION: &str = "kubectl.kubernetes.io/last-applied-configuration";
 pub const ANNOTATION_EXTERNAL_DNS_HOSTNAME: &str = "external-dns.alpha.kubernetes.io/hostname";
+pub const ANNOTATION_REFRESH_IMAGE: &str = "hoprds.hoprnet.org/refresh-image";
+pub const ANNOTATION_CHECKSUM: &str = "hoprds.hoprnet.org/checksum";
+pub const ANNOTATION_CONVERSION_PRESERVED_FIELDS: &str = "hoprds.hoprnet.org/conversion-preserved-fields";
+pub const ANNOTATION_SKIP_DRAIN: &str = "hoprds.hoprnet.org/skip-drain";
+
+// Conditions
+pub const CONDITION_API_REACHABLE: &str = "ApiReachable";
+pub const CONDITION_NETWORK_HEALTHY: &str = "NetworkHealthy";
+pub const CONDITION_CONFIG_VALID: &str = "ConfigValid";
 
 // Labels
+// Prefixes of the labels managed by the operator, which are never taken from the user defined labels
+pub const LABEL_RESERVED_PREFIXES: [&str; 2] = ["app.kubernetes.io/", "hoprds.hoprnet.org/"];
 pub const LABEL_KUBERNETES_NAME: &str = "app.kubernetes.io/name";
 pub const LABEL_KUBERNETES_INSTANCE: &str = "app.kubernetes.io/instance";
 pub const LABEL_KUBERNETES_COMPONENT: &str = "app.kubernetes.io/component";
+pub const LABEL_KUBERNETES_TOPOLOGY_ZONE: &str = "topology.kubernetes.io/zone";
 
 pub const LABEL_KUBERNETES_IDENTITY_POOL: &str = "hoprds.hoprnet.org/identitypool";
 pub const LABEL_NODE_ADDRESS: &str = "hoprds.hoprnet.org/nodeAddress";
@@ -30,13 +50,21 @@ pub const LABEL_NODE_CLUSTER: &str = "hoprds.hoprnet.org/cluster";
 pub const HOPR_DOCKER_REGISTRY: &str = "europe-west3-docker.pkg.dev";
 pub const HOPR_DOCKER_IMAGE_NAME: &str = "hoprassociation/docker-images/hoprd";
 pub const HOPR_DOCKER_METRICS_IMAGE_NAME: &str = "hoprassociation/docker-images/hoprd-operator-metrics";
+pub const HOPR_DOCKER_SNAPSHOT_IMAGE_NAME: &str = "hoprassociation/docker-images/hoprd-operator-snapshot";
+pub const HOPRD_POD_USER_ID: i64 = 1000;
+pub const HOPRD_API_PORT: u16 = 3001;
+pub const HOPRD_API_TIMEOUT: u64 = 5;
+pub const HOPRD_REMEDIATION_HISTORY_SIZE: usize = 10;
+// Ports allocated to a node that does not set them, by the mutating webhook and when converting between versions
+pub const HOPRD_PORTS_ALLOCATION: u16 = 4;
 
 // HOPRD Arguments
-pub const HOPRD_IDENTITY_FILE: &str = "HOPRD_IDENTITY_FILE";
 pub const HOPRD_CONFIGURATION: &str = "HOPRD_CONFIGURATION";
 pub const HOPRD_SAFE_ADDRESS: &str = "HOPRD_SAFE_ADDRESS";
 pub const HOPRD_MODULE_ADDRESS: &str = "HOPRD_MODULE_ADDRESS";
 pub const HOPRD_HOST: &str = "HOPRD_HOST";
 pub const HOPRD_API: &str = "HOPRD_API";
 pub const HOPRD_SESSION_PORT_RANGE: &str = "HOPRD_SESSION_PORT_RANGE";
+pub const HOPRD_LOGS_SNAPSHOT_URL: &str = "HOPRD_LOGS_SNAPSHOT_URL";
+pub const HOPRD_API_TOKEN: &str = "HOPRD_API_TOKEN";
 pub const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
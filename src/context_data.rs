@@ -9,7 +9,7 @@ use kube::{
 };
 
 use crate::{ events::ResourceEvent, hoprd::hoprd_resource::Hoprd, identity_hoprd::identity_hoprd_resource::IdentityHoprd, identity_pool::identity_pool_resource::{IdentityPool, IdentityPoolPhaseEnum},
-    operator_config::OperatorConfig,
+    image_registry::ImageRegistryClient, operator_config::OperatorConfig,
 };
 
 #[derive(Clone)]
@@ -20,6 +20,8 @@ pub struct ContextData {
     pub state: Arc<RwLock<State>>,
 
     pub config: OperatorConfig,
+
+    pub image_registry: ImageRegistryClient,
 }
 
 /// State wrapper around the controller outputs for the web server
@@ -38,6 +40,7 @@ impl ContextData {
         ContextData {
             client,
             state: Arc::new(RwLock::new(State::new(pools))),
+            image_registry: ImageRegistryClient::new(config.images.insecure_registries.clone()),
             config,
         }
     }
@@ -10,6 +10,12 @@ pub enum HoprdEventEnum {
     Stopped,
     Failed,
     Modified,
+    Degraded,
+    Remediating,
+    DatabaseResetFailed,
+    InvalidConfig,
+    UnknownConfigKeys,
+    ImageNotResolved,
     Deleting,
     Deleted,
 }
@@ -19,7 +25,7 @@ fn unwrap_attribute(attr: &Option<String>) -> &str {
 }
 
 impl ResourceEvent for HoprdEventEnum {
-    fn to_event(&self, _: Option<String>) -> Event {
+    fn to_event(&self, attribute: Option<String>) -> Event {
         match self {
             HoprdEventEnum::Initializing => Event {
                 type_: EventType::Normal,
@@ -49,6 +55,48 @@ impl ResourceEvent for HoprdEventEnum {
                 action: "Node reconfigured".to_string(),
                 secondary: None,
             },
+            HoprdEventEnum::Degraded => Event {
+                type_: EventType::Warning,
+                reason: "Degraded".to_string(),
+                note: Some(format!("Hoprd node reports a {} network health", unwrap_attribute(&attribute))),
+                action: "Node network health is below the threshold".to_string(),
+                secondary: None,
+            },
+            HoprdEventEnum::Remediating => Event {
+                type_: EventType::Warning,
+                reason: "Remediating".to_string(),
+                note: Some(format!("Hoprd node is being remediated: {}", unwrap_attribute(&attribute))),
+                action: "Node remediation started".to_string(),
+                secondary: None,
+            },
+            HoprdEventEnum::DatabaseResetFailed => Event {
+                type_: EventType::Warning,
+                reason: "DatabaseResetFailed".to_string(),
+                note: Some(format!("Job {} deleting the database of the Hoprd node failed", unwrap_attribute(&attribute))),
+                action: "Node started with the database left by the job".to_string(),
+                secondary: None,
+            },
+            HoprdEventEnum::InvalidConfig => Event {
+                type_: EventType::Warning,
+                reason: "InvalidConfig".to_string(),
+                note: Some(format!("Hoprd node configuration is invalid at {}", unwrap_attribute(&attribute))),
+                action: "Node configuration was not rolled out".to_string(),
+                secondary: None,
+            },
+            HoprdEventEnum::UnknownConfigKeys => Event {
+                type_: EventType::Warning,
+                reason: "UnknownConfigKeys".to_string(),
+                note: Some(format!("Hoprd node configuration has keys ignored by hoprd: {}", unwrap_attribute(&attribute))),
+                action: "Node configuration was rolled out without the unknown keys".to_string(),
+                secondary: None,
+            },
+            HoprdEventEnum::ImageNotResolved => Event {
+                type_: EventType::Warning,
+                reason: "ImageNotResolved".to_string(),
+                note: Some(format!("Could not resolve the digest of the Hoprd node image: {}", unwrap_attribute(&attribute))),
+                action: "Node rolled out with the image tag".to_string(),
+                secondary: None,
+            },
             HoprdEventEnum::Deleting => Event {
                 type_: EventType::Normal,
                 reason: "Deleting".to_string(),
@@ -179,9 +227,13 @@ impl ResourceEvent for ClusterHoprdEventEnum {
 pub enum IdentityHoprdEventEnum {
     Initialized,
     Failed,
+    Validating,
+    Invalid,
     Ready,
     InUse,
     Deleting,
+    Draining,
+    DrainFailed,
 }
 
 impl ResourceEvent for IdentityHoprdEventEnum {
@@ -202,6 +254,20 @@ impl ResourceEvent for IdentityHoprdEventEnum {
                 action: "Identity bootstrapping failed".to_string(),
                 secondary: None,
             },
+            IdentityHoprdEventEnum::Validating => Event {
+                type_: EventType::Normal,
+                reason: "Validating".to_string(),
+                note: Some("Validating the identity on-chain".to_string()),
+                action: "Identity validation started".to_owned(),
+                secondary: None,
+            },
+            IdentityHoprdEventEnum::Invalid => Event {
+                type_: EventType::Warning,
+                reason: "Invalid".to_string(),
+                note: Some(format!("Identity did not pass the on-chain validation: {}", parsed_attribute)),
+                action: "Identity validation failed".to_owned(),
+                secondary: None,
+            },
             IdentityHoprdEventEnum::Ready => Event {
                 type_: EventType::Normal,
                 reason: "Ready".to_string(),
@@ -223,6 +289,20 @@ impl ResourceEvent for IdentityHoprdEventEnum {
                 action: "Identity deletion started".to_string(),
                 secondary: None,
             },
+            IdentityHoprdEventEnum::Draining => Event {
+                type_: EventType::Normal,
+                reason: "Draining".to_string(),
+                note: Some(format!("Draining the funds of the identity to {}", parsed_attribute)),
+                action: "Identity drain started".to_string(),
+                secondary: None,
+            },
+            IdentityHoprdEventEnum::DrainFailed => Event {
+                type_: EventType::Warning,
+                reason: "DrainFailed".to_string(),
+                note: Some(format!("Failed to drain the funds of the identity: {}", parsed_attribute)),
+                action: "Identity drain failed".to_string(),
+                secondary: None,
+            },
         }
     }
 }
@@ -236,6 +316,10 @@ pub enum IdentityPoolEventEnum {
     Unlocked,
     IdentityCreated,
     IdentityDeleted,
+    ProvisioningStarted,
+    ProvisioningFailed,
+    FundingStarted,
+    FundingFailed,
 }
 
 impl ResourceEvent for IdentityPoolEventEnum {
@@ -298,6 +382,34 @@ impl ResourceEvent for IdentityPoolEventEnum {
                 action: "Identity pool deregistered identity".to_string(),
                 secondary: None,
             },
+            IdentityPoolEventEnum::ProvisioningStarted => Event {
+                type_: EventType::Normal,
+                reason: "ProvisioningStarted".to_string(),
+                note: Some(format!("Provisioning identity {}", parsed_attribute)),
+                action: "Identity pool started a job provisioning an identity".to_string(),
+                secondary: None,
+            },
+            IdentityPoolEventEnum::ProvisioningFailed => Event {
+                type_: EventType::Warning,
+                reason: "ProvisioningFailed".to_string(),
+                note: Some(format!("Failed to provision identity: {}", parsed_attribute)),
+                action: "Identity pool failed to provision an identity".to_string(),
+                secondary: None,
+            },
+            IdentityPoolEventEnum::FundingStarted => Event {
+                type_: EventType::Normal,
+                reason: "FundingStarted".to_string(),
+                note: Some(format!("Funding addresses below threshold: {}", parsed_attribute)),
+                action: "Identity pool started a job funding its identities".to_string(),
+                secondary: None,
+            },
+            IdentityPoolEventEnum::FundingFailed => Event {
+                type_: EventType::Warning,
+                reason: "FundingFailed".to_string(),
+                note: Some(format!("Failed to fund identities: {}", parsed_attribute)),
+                action: "Identity pool failed to fund its identities".to_string(),
+                secondary: None,
+            },
         }
     }
 }
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{constants, model::Error};
+
+/// Subset of the node information returned by `/api/v4/node/info`
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    /// Network health reported by the node: `Unknown`, `Red`, `Orange`, `Yellow` or `Green`
+    pub connectivity_status: String,
+    pub network: Option<String>,
+    pub hopr_node_safe: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct NodePeers {
+    pub connected: Vec<Value>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct NodeChannels {
+    pub incoming: Vec<Value>,
+    pub outgoing: Vec<Value>,
+}
+
+/// Balances of the node and its safe, as formatted by the node (e.g. `1000 wxHOPR`)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdBalances {
+    pub native: Option<String>,
+    pub hopr: Option<String>,
+    pub safe_native: Option<String>,
+    pub safe_hopr: Option<String>,
+}
+
+/// Live state of a node gathered from its API
+#[derive(Debug, PartialEq, Clone)]
+pub struct NodeState {
+    pub network_health: String,
+    pub connected_peers: i32,
+    pub incoming_channels: i32,
+    pub outgoing_channels: i32,
+    pub balances: HoprdBalances,
+}
+
+impl NodeState {
+    pub fn is_network_healthy(&self, min_network_health: &str) -> bool {
+        network_health_level(&self.network_health) >= network_health_level(min_network_health)
+    }
+}
+
+/// Position of a network health value in the scale reported by the nodes, from `Unknown` to `Green`
+pub fn network_health_level(network_health: &str) -> u8 {
+    match network_health {
+        "Red" => 1,
+        "Orange" => 2,
+        "Yellow" => 3,
+        "Green" => 4,
+        _ => 0,
+    }
+}
+
+/// Client of the REST API exposed by hoprd nodes
+pub struct HoprdApiClient {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl HoprdApiClient {
+    pub fn new(base_url: String, token: Option<String>) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(constants::HOPRD_API_TIMEOUT))
+            .build()
+            .map_err(|e| Error::HoprdApiError(format!("Could not build the client for {}: {}", base_url, e)))?;
+        Ok(HoprdApiClient { client, base_url, token })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let url = format!("{}{}", self.base_url, path);
+        debug!("Querying hoprd API {}", url);
+        let mut request = self.client.get(&url);
+        if let Some(token) = &self.token {
+            request = request.header("X-Auth-Token", token);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::HoprdApiError(format!("Request to {} failed: {}", url, e)))?
+            .json()
+            .await
+            .map_err(|e| Error::HoprdApiError(format!("Invalid response from {}: {}", url, e)))
+    }
+
+    pub async fn get_info(&self) -> Result<NodeInfo, Error> {
+        self.get("/api/v4/node/info").await
+    }
+
+    pub async fn get_peers(&self) -> Result<NodePeers, Error> {
+        self.get("/api/v4/node/peers").await
+    }
+
+    pub async fn get_channels(&self) -> Result<NodeChannels, Error> {
+        self.get("/api/v4/channels").await
+    }
+
+    pub async fn get_balances(&self) -> Result<HoprdBalances, Error> {
+        self.get("/api/v4/account/balances").await
+    }
+
+    /// Queries the network health, peers, channels and balances of the node
+    pub async fn get_node_state(&self) -> Result<NodeState, Error> {
+        let (info, peers, channels, balances) = tokio::try_join!(self.get_info(), self.get_peers(), self.get_channels(), self.get_balances())?;
+        Ok(NodeState {
+            network_health: info.connectivity_status,
+            connected_peers: peers.connected.len() as i32,
+            incoming_channels: channels.incoming.len() as i32,
+            outgoing_channels: channels.outgoing.len() as i32,
+            balances,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+    use serde_json::json;
+
+    fn authorized(headers: &HeaderMap) -> bool {
+        headers.get("X-Auth-Token").and_then(|value| value.to_str().ok()) == Some("test-token")
+    }
+
+    async fn respond(headers: HeaderMap, body: Value) -> impl IntoResponse {
+        if authorized(&headers) {
+            (StatusCode::OK, Json(body))
+        } else {
+            (StatusCode::UNAUTHORIZED, Json(json!({ "status": "UNAUTHORIZED" })))
+        }
+    }
+
+    async fn start_mock_node() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route(
+                "/api/v4/node/info",
+                get(|headers: HeaderMap| respond(headers, json!({ "connectivityStatus": "Green", "network": "rotsee", "hoprNodeSafe": "0x0000000000000000000000000000000000000001" }))),
+            )
+            .route("/api/v4/node/peers", get(|headers: HeaderMap| respond(headers, json!({ "connected": [{ "peerId": "a" }, { "peerId": "b" }], "announced": [] }))))
+            .route("/api/v4/channels", get(|headers: HeaderMap| respond(headers, json!({ "incoming": [{ "id": "1" }], "outgoing": [], "all": [] }))))
+            .route(
+                "/api/v4/account/balances",
+                get(|headers: HeaderMap| respond(headers, json!({ "native": "1 xDai", "hopr": "0 wxHOPR", "safeNative": "2 xDai", "safeHopr": "100 wxHOPR", "safeHoprAllowance": "100 wxHOPR" }))),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn test_get_node_state_from_mock_node() {
+        let base_url = start_mock_node().await;
+        let client = HoprdApiClient::new(base_url.to_owned(), Some("test-token".to_owned())).unwrap();
+        let node_state = client.get_node_state().await.unwrap();
+        assert_eq!(node_state.network_health, "Green");
+        assert!(node_state.is_network_healthy("Green"));
+        assert!(!NodeState { network_health: "Orange".to_owned(), ..node_state.clone() }.is_network_healthy("Yellow"));
+        assert_eq!(node_state.connected_peers, 2);
+        assert_eq!(node_state.incoming_channels, 1);
+        assert_eq!(node_state.outgoing_channels, 0);
+        assert_eq!(node_state.balances.safe_hopr, Some("100 wxHOPR".to_owned()));
+
+        let unauthorized_client = HoprdApiClient::new(base_url, None).unwrap();
+        assert!(unauthorized_client.get_info().await.is_err());
+    }
+}
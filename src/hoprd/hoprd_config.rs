@@ -0,0 +1,210 @@
+use jsonschema::error::ValidationErrorKind;
+use k8s_openapi::api::core::v1::{ConfigMap, ConfigMapKeySelector, Secret, SecretKeySelector};
+use kube::{Api, Client, ResourceExt};
+use schemars::JsonSchema;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use tracing::debug;
+
+use crate::{hoprd::hoprd_resource::Hoprd, model::Error};
+
+/// Schemas of the hoprd configuration bundled with the operator, by the range of hoprd versions they apply to
+const CONFIG_SCHEMAS: [(&str, &str); 2] = [
+    (">=2.0.0, <3.0.0", include_str!("config_schemas/hoprd-2.json")),
+    (">=3.0.0, <4.0.0", include_str!("config_schemas/hoprd-3.json")),
+];
+
+/// Reference to the key of a ConfigMap or Secret holding the hoprd configuration, as an alternative to the inline `config`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdConfigRef {
+    pub config_map_key_ref: Option<ConfigMapKeySelector>,
+    pub secret_key_ref: Option<SecretKeySelector>,
+}
+
+impl HoprdConfigRef {
+    /// Returns whether the reference points to the given ConfigMap or Secret
+    pub fn references(&self, kind: &str, name: &str) -> bool {
+        match kind {
+            "ConfigMap" => self.config_map_key_ref.as_ref().is_some_and(|selector| selector.name == name),
+            "Secret" => self.secret_key_ref.as_ref().is_some_and(|selector| selector.name == name),
+            _ => false,
+        }
+    }
+}
+
+/// Values known by the operator that can be referenced in the configuration as `${NAME}`
+pub struct ConfigVariables {
+    pub node_name: String,
+    pub namespace: String,
+    pub announced_host: String,
+    pub safe_address: String,
+    pub module_address: String,
+    pub network: String,
+    pub session_port_range: Option<String>,
+}
+
+impl ConfigVariables {
+    /// Variables of a node that is not created yet. The values only known once its identity is locked and its ports are allocated are replaced by examples of the same shape
+    pub fn before_creation(node_name: String, namespace: String, network: String, ports_allocation: u16) -> Self {
+        let starting_port: u16 = 9091;
+        ConfigVariables {
+            node_name,
+            namespace,
+            announced_host: format!("127.0.0.1:{}", starting_port),
+            safe_address: format!("0x{}", "f".repeat(40)),
+            module_address: format!("0x{}", "f".repeat(40)),
+            network,
+            session_port_range: Some(format!("{}:{}", starting_port + 1, starting_port + ports_allocation - 1)).filter(|_| ports_allocation > 1),
+        }
+    }
+
+    fn to_map(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([
+            ("NODE_NAME", self.node_name.to_owned()),
+            ("NAMESPACE", self.namespace.to_owned()),
+            ("ANNOUNCED_HOST", self.announced_host.to_owned()),
+            ("SAFE_ADDRESS", self.safe_address.to_owned()),
+            ("MODULE_ADDRESS", self.module_address.to_owned()),
+            ("NETWORK", self.network.to_owned()),
+            ("SESSION_PORT_RANGE", self.session_port_range.clone().unwrap_or_default()),
+        ])
+    }
+}
+
+/// Reads the configuration of the node, either inline or from the referenced ConfigMap or Secret key
+pub async fn get_config(client: Client, hoprd: &Hoprd) -> Result<String, Error> {
+    let Some(config_ref) = hoprd.spec.config_ref.as_ref() else {
+        return Ok(hoprd.spec.config.to_owned());
+    };
+    let namespace = hoprd.namespace().unwrap();
+    if let Some(selector) = config_ref.config_map_key_ref.as_ref() {
+        let api: Api<ConfigMap> = Api::namespaced(client, &namespace);
+        api.get_opt(&selector.name)
+            .await?
+            .and_then(|config_map| config_map.data)
+            .and_then(|data| data.get(&selector.key).cloned())
+            .ok_or_else(|| Error::HoprdConfigError(format!("The key '{}' of ConfigMap '{}' does not exist", selector.key, selector.name)))
+    } else if let Some(selector) = config_ref.secret_key_ref.as_ref() {
+        let api: Api<Secret> = Api::namespaced(client, &namespace);
+        let data = api
+            .get_opt(&selector.name)
+            .await?
+            .and_then(|secret| secret.data)
+            .and_then(|data| data.get(&selector.key).cloned())
+            .ok_or_else(|| Error::HoprdConfigError(format!("The key '{}' of Secret '{}' does not exist", selector.key, selector.name)))?;
+        String::from_utf8(data.0).map_err(|_| Error::HoprdConfigError(format!("The key '{}' of Secret '{}' is not valid UTF-8", selector.key, selector.name)))
+    } else {
+        Err(Error::HoprdConfigError("The configRef must define either a configMapKeyRef or a secretKeyRef".to_owned()))
+    }
+}
+
+/// Replaces the `${NAME}` placeholders of the known variables. Other placeholders are kept as they are
+pub fn render(template: &str, variables: &ConfigVariables) -> String {
+    variables.to_map().iter().fold(template.to_owned(), |config, (name, value)| config.replace(&format!("${{{}}}", name), value))
+}
+
+/// Finds the schema applying to a hoprd version. Pre-releases use the schema of their release, and tags that are not versions are not validated
+fn get_schema(version: &str) -> Option<Value> {
+    let version = Version::parse(version.trim_start_matches('v')).ok()?;
+    let release = Version::new(version.major, version.minor, version.patch);
+    CONFIG_SCHEMAS
+        .iter()
+        .find(|(range, _)| VersionReq::parse(range).unwrap().matches(&release))
+        .map(|(_, schema)| serde_json::from_str(schema).unwrap())
+}
+
+/// Converts the YAML configuration into JSON. Tagged values like `!IPv4 0.0.0.0` become single key objects, as hoprd reads them as enums
+fn yaml_to_json(value: serde_yml::Value) -> Value {
+    match value {
+        serde_yml::Value::Sequence(items) => Value::Array(items.into_iter().map(yaml_to_json).collect()),
+        serde_yml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| (key.as_str().map(str::to_owned).unwrap_or_else(|| serde_yml::to_string(&key).unwrap_or_default().trim().to_owned()), yaml_to_json(value)))
+                .collect::<Map<String, Value>>(),
+        ),
+        serde_yml::Value::Tagged(tagged) => Value::Object(Map::from_iter([(tagged.tag.to_string().trim_start_matches('!').to_owned(), yaml_to_json(tagged.value))])),
+        value => serde_json::to_value(value).unwrap_or(Value::Null),
+    }
+}
+
+/// Validates the configuration against the schema of the hoprd version, reporting the path of the first problem found.
+/// Keys unknown to the schema are returned as warnings instead, as hoprd ignores them and the bundled schema may lag behind its patch releases
+pub fn validate(config: &str, version: &str) -> Result<Vec<String>, Error> {
+    let Some(schema) = get_schema(version) else {
+        debug!("There is no configuration schema for hoprd version {}", version);
+        return Ok(Vec::new());
+    };
+    let config: serde_yml::Value = serde_yml::from_str(config).map_err(|e| Error::HoprdConfigError(format!("The configuration is not valid YAML: {}", e)))?;
+    let config = match yaml_to_json(config) {
+        // An empty configuration runs the node with the default values
+        Value::Null => return Ok(Vec::new()),
+        config => config,
+    };
+    let validator = jsonschema::validator_for(&schema).map_err(|e| Error::HoprdConfigError(format!("The configuration schema of hoprd {} is invalid: {}", version, e)))?;
+    let mut warnings: Vec<String> = Vec::new();
+    for error in validator.iter_errors(&config) {
+        match &error.kind {
+            ValidationErrorKind::AdditionalProperties { unexpected } => {
+                warnings.extend(unexpected.iter().map(|key| format!("{}/{}: unknown key '{}' is ignored by hoprd {}", error.instance_path, key, key, version)));
+            }
+            _ => {
+                let path = error.instance_path.to_string();
+                return Err(Error::HoprdConfigError(format!("{}: {}", if path.is_empty() { "/" } else { &path }, error)));
+            }
+        }
+    }
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_config() {
+        let variables = ConfigVariables {
+            node_name: "hoprd-core-rotsee-1".to_owned(),
+            namespace: "core-team".to_owned(),
+            announced_host: "1.2.3.4:9091".to_owned(),
+            safe_address: "0x01".to_owned(),
+            module_address: "0x02".to_owned(),
+            network: "rotsee".to_owned(),
+            session_port_range: None,
+        };
+        let template = "hopr:\n  host:\n    address: !Domain ${ANNOUNCED_HOST}\n  safe_module:\n    safe_address: ${SAFE_ADDRESS}\n  chain:\n    network: ${NETWORK}\n  sessions: \"${SESSION_PORT_RANGE}\"\n  other: ${UNKNOWN}\n";
+        let config = render(template, &variables);
+        assert!(config.contains("address: !Domain 1.2.3.4:9091"));
+        assert!(config.contains("safe_address: 0x01"));
+        assert!(config.contains("network: rotsee"));
+        assert!(config.contains("sessions: \"\""));
+        assert!(config.contains("other: ${UNKNOWN}"));
+
+        // The variables are rendered before validating, so that a template is not rejected for its placeholders
+        let template = "hopr:\n  safe_module:\n    safe_address: ${SAFE_ADDRESS}\n    module_address: ${MODULE_ADDRESS}\n  chain:\n    network: ${NETWORK}\n";
+        assert!(validate(template, "3.0.0").is_err());
+        let variables = ConfigVariables::before_creation("hoprd-core-rotsee-1".to_owned(), "core-team".to_owned(), "rotsee".to_owned(), 4);
+        assert_eq!(variables.session_port_range.as_deref(), Some("9092:9094"));
+        assert!(validate(&render(template, &variables), "3.0.0").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let config = std::fs::read_to_string("test-data/hoprd-config-template.yaml").unwrap();
+        assert!(validate(&config, "2.1.0").is_ok());
+        assert!(validate(&config, "saint-louis-latest").is_ok());
+        assert!(validate(&config, "2.1.0").unwrap().is_empty());
+        let typo = "hopr:\n  chain:\n    network: rotsee\n  stratgy:\n    on_fail_continue: true\n";
+        let warnings = validate(typo, "v2.2.0-rc.1").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("/hopr/stratgy:"), "{}", warnings[0]);
+        let wrong_type = "hopr:\n  host:\n    address: !IPv4 0.0.0.0\n    port: \"9091\"\n";
+        match validate(wrong_type, "3.0.0") {
+            Err(Error::HoprdConfigError(message)) => assert!(message.starts_with("/hopr/host/port:"), "{}", message),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
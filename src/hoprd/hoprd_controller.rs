@@ -2,7 +2,7 @@ use futures::StreamExt;
 use k8s_openapi::api::{
     apps::v1::Deployment,
     batch::v1::Job,
-    core::v1::{Secret, Service},
+    core::v1::{ConfigMap, Secret, Service},
     networking::v1::Ingress,
 };
 use kube::{
@@ -10,9 +10,10 @@ use kube::{
     client::Client,
     runtime::{
         controller::{Action, Controller},
+        reflector::{ObjectRef, Store},
         watcher::Config,
     },
-    Resource, Result,
+    Resource, ResourceExt, Result,
 };
 use std::sync::Arc;
 use tokio::time::Duration;
@@ -32,8 +33,12 @@ enum HoprdAction {
     Create,
     /// Modify Hoprd resource
     Modify,
+    /// Resolve again the digest of the Hoprd image as requested by the refresh annotation
+    RefreshImage,
     /// Delete all subresources created in the `Create` phase
     Delete,
+    /// Refresh the runtime information recorded in the status of a node in the desired state
+    SyncStatus,
     /// This `Hoprd` resource is in desired state and requires no actions to be taken
     NoOp,
 }
@@ -50,7 +55,14 @@ fn determine_action(hoprd: &Hoprd) -> HoprdAction {
     } else if hoprd.meta().finalizers.as_ref().map_or(true, |finalizers| finalizers.is_empty()) {
         HoprdAction::Create
     } else if hoprd.status.as_ref().unwrap().phase == HoprdPhaseEnum::Failed {
-        HoprdAction::Modify
+        // A failed node is only recovered by a new modification of its spec
+        let current_generation = hoprd.meta().generation.unwrap_or(0);
+        let observed_generation = hoprd.status.as_ref().map_or(0, |status| status.observed_generation);
+        if observed_generation < current_generation {
+            HoprdAction::Modify
+        } else {
+            HoprdAction::NoOp
+        }
     } else if hoprd.status.as_ref().unwrap().phase == HoprdPhaseEnum::Deleting {
         HoprdAction::NoOp
     } else {
@@ -58,8 +70,10 @@ fn determine_action(hoprd: &Hoprd) -> HoprdAction {
         let observed_generation = hoprd.status.as_ref().map_or(0, |status| status.observed_generation);
         if observed_generation < current_generation {
             HoprdAction::Modify
+        } else if hoprd.annotations().contains_key(constants::ANNOTATION_REFRESH_IMAGE) {
+            HoprdAction::RefreshImage
         } else {
-            HoprdAction::NoOp
+            HoprdAction::SyncStatus
         }
     }
 }
@@ -71,6 +85,8 @@ async fn reconciler(hoprd: Arc<Hoprd>, context: Arc<ContextData>) -> Result<Acti
     match determine_action(hoprd_mutable) {
         HoprdAction::Create => hoprd_mutable.create(context.clone()).await,
         HoprdAction::Modify => hoprd_mutable.modify(context.clone()).await,
+        HoprdAction::RefreshImage => hoprd_mutable.refresh_image(context.clone()).await,
+        HoprdAction::SyncStatus => hoprd_mutable.sync_status(context.clone()).await,
         HoprdAction::Delete => hoprd_mutable.delete(context.clone()).await,
         HoprdAction::NoOp => Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))),
     }
@@ -89,6 +105,36 @@ pub fn on_error(hoprd: Arc<Hoprd>, error: &Error, _context: Arc<ContextData>) ->
     Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))
 }
 
+/// Finds the nodes consuming the given ConfigMap or Secret, either through their `configRef` or as the environment variables of their pool, so they are reconciled when it changes
+fn nodes_referencing<K: Resource<DynamicType = ()>>(store: &Store<Hoprd>, object: &K) -> Vec<ObjectRef<Hoprd>> {
+    let kind = K::kind(&());
+    store
+        .state()
+        .iter()
+        .filter(|hoprd| hoprd.namespace() == object.meta().namespace)
+        .filter(|hoprd| {
+            hoprd.spec.config_ref.as_ref().is_some_and(|config_ref| config_ref.references(&kind, &object.name_any())) || object.name_any() == format!("{}-env-vars", hoprd.spec.identity_pool_name)
+        })
+        .map(|hoprd| ObjectRef::from_obj(hoprd.as_ref()))
+        .collect()
+}
+
+/// Finds the nodes owning or consuming the given Secret, so that a single watch on Secrets reconciles both
+fn nodes_of_secret(store: &Store<Hoprd>, secret: &Secret) -> Vec<ObjectRef<Hoprd>> {
+    let mut nodes = nodes_referencing(store, secret);
+    let owners = secret
+        .owner_references()
+        .iter()
+        .filter(|owner| owner.kind == Hoprd::kind(&()) && owner.api_version.split('/').next() == Some(Hoprd::group(&()).as_ref()))
+        .map(|owner| ObjectRef::new(&owner.name).within(&secret.namespace().unwrap_or_default()));
+    for owner in owners {
+        if !nodes.contains(&owner) {
+            nodes.push(owner);
+        }
+    }
+    nodes
+}
+
 /// Initialize the controller
 pub async fn run(client: Client, context_data: Arc<ContextData>) {
     let owned_api: Api<Hoprd> = Api::<Hoprd>::all(client.clone());
@@ -98,14 +144,20 @@ pub async fn run(client: Client, context_data: Arc<ContextData>) {
     let service = Api::<Service>::all(client.clone());
     let service_monitor = Api::<ServiceMonitor>::all(client.clone());
     let ingress = Api::<Ingress>::all(client.clone());
+    let config_map = Api::<ConfigMap>::all(client.clone());
 
-    Controller::new(owned_api, Config::default())
+    let controller = Controller::new(owned_api, Config::default());
+    let config_map_store = controller.store();
+    let secret_store = controller.store();
+    controller
         .owns(job, Config::default())
         .owns(deployment, Config::default())
-        .owns(secret, Config::default())
         .owns(service, Config::default())
         .owns(service_monitor, Config::default())
         .owns(ingress, Config::default())
+        .watches(config_map, Config::default(), move |config_map: ConfigMap| nodes_referencing(&config_map_store, &config_map))
+        // Owned Secrets are mapped in the same watch, as a second watch on Secrets would duplicate the cache and the API connection
+        .watches(secret, Config::default(), move |secret: Secret| nodes_of_secret(&secret_store, &secret))
         .shutdown_on_signal()
         .run(reconciler, on_error, context_data)
         .for_each(|reconciliation_result| async move {
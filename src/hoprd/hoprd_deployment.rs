@@ -1,7 +1,11 @@
+use crate::hoprd::hoprd_config::{self, ConfigVariables};
 use crate::hoprd::hoprd_deployment_spec::HoprdDeploymentSpec;
+use crate::hoprd::hoprd_snapshot::{self, HoprdSnapshotSpec};
 use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprd;
+use crate::identity_hoprd::identity_hoprd_secret;
 use crate::identity_pool::identity_pool_resource::IdentityPool;
 use crate::model::Error;
+use crate::operator_config::ImagesConfig;
 use crate::{context_data::ContextData, hoprd::hoprd_deployment};
 use base64::{engine::general_purpose, Engine as _};
 use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
@@ -12,24 +16,24 @@ use crate::{
     hoprd::hoprd_resource::{Hoprd, HoprdSpec},
     utils,
 };
-use futures::StreamExt;
 use k8s_openapi::api::batch::v1::JobSpec;
 use k8s_openapi::api::core::v1::{
-    Capabilities, ConfigMapEnvSource, ConfigMapVolumeSource, Container, ContainerPort, EmptyDirVolumeSource, EnvFromSource, EnvVar, KeyToPath, PersistentVolumeClaimVolumeSource, PodSpec, PodTemplateSpec, Probe, SecretEnvSource, SecretVolumeSource, SecurityContext, TCPSocketAction, Volume, VolumeMount
+    Capabilities, ConfigMap, ConfigMapEnvSource, ConfigMapVolumeSource, Container, ContainerPort, EmptyDirVolumeSource, EnvFromSource, EnvVar, KeyToPath, PersistentVolumeClaimVolumeSource, PodSecurityContext, PodSpec, PodTemplateSpec, Probe, Secret, SecretEnvSource, SecretVolumeSource, SecurityContext, TCPSocketAction, Volume, VolumeMount
 };
 use k8s_openapi::api::{
     apps::v1::{Deployment, DeploymentSpec, DeploymentStrategy},
-    batch::v1::{Job, JobStatus},
+    batch::v1::Job,
 };
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, OwnerReference};
-use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams, WatchEvent, WatchParams};
+use kube::api::{DeleteParams, ObjectMeta, Patch, PatchParams, PostParams};
 use kube::runtime::wait::{await_condition, conditions};
 use kube::{Api, Client, Resource, ResourceExt};
 use rand::Rng;
-use serde_json::json;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// Creates a new deployment for running the hoprd node,
 ///
@@ -65,7 +69,7 @@ pub async fn create_deployment(context_data: Arc<ContextData>, hoprd: &Hoprd, id
             owner_references,
             ..ObjectMeta::default()
         },
-        spec: Some(build_deployment_spec(labels, &hoprd.spec, identity_pool, identity_hoprd, &hoprd_host, starting_port, last_port).await?),
+        spec: Some(build_deployment_spec(context_data.clone(), labels, hoprd, identity_hoprd, hoprd_host, starting_port, last_port).await?),
         ..Deployment::default()
     };
 
@@ -77,21 +81,65 @@ pub async fn create_deployment(context_data: Arc<ContextData>, hoprd: &Hoprd, id
 }
 
 pub async fn build_deployment_spec(
+    context_data: Arc<ContextData>,
     labels: BTreeMap<String, String>,
-    hoprd_spec: &HoprdSpec,
-    identity_pool: IdentityPool,
+    hoprd: &Hoprd,
     identity_hoprd: &IdentityHoprd,
     hoprd_host: &str,
     starting_port: u16,
     last_port: u16
 ) -> Result<DeploymentSpec, Error> {
-    let replicas: i32 = if hoprd_spec.enabled.unwrap_or(true) { 1 } else { 0 };
+    let hoprd_spec: &HoprdSpec = &hoprd.spec;
+    // The node stays scaled down while its database is being reset
+    let resetting_database = hoprd.status.as_ref().is_some_and(|status| status.database_reset_job.is_some());
+    let replicas: i32 = if hoprd_spec.enabled.unwrap_or(true) && !resetting_database { 1 } else { 0 };
+    let identity_pool: IdentityPool = identity_hoprd.get_identity_pool(context_data.client.clone()).await?;
+    let images = &context_data.config.images;
+    let security_context = HoprdDeploymentSpec::get_security_context(hoprd_spec.deployment.as_ref())?;
+    let config = render_config(context_data.clone(), hoprd, identity_hoprd, &identity_pool, hoprd_host, starting_port, last_port).await?;
+    let mut pod_annotations = hoprd_spec.deployment.as_ref().and_then(|deployment| deployment.pod_annotations.clone()).unwrap_or_default();
+    pod_annotations.insert(constants::ANNOTATION_CHECKSUM.to_owned(), get_checksum(context_data.clone(), &identity_pool, identity_hoprd, &config).await?);
+    let mut init_containers: Vec<Container> = Vec::new();
+    // The node publishing the logs snapshot does not download it
+    if !hoprd_spec.source_node_logs.unwrap_or(false) {
+        let snapshot = match hoprd_spec.snapshot.clone() {
+            Some(snapshot) => Some(snapshot),
+            None => get_legacy_snapshot(context_data.clone(), &identity_pool).await?,
+        };
+        if let Some(snapshot) = snapshot {
+            init_containers.push(hoprd_snapshot::snapshot_container(&snapshot, images, build_volume_mounts().unwrap_or_default(), security_context.clone()));
+        }
+    }
+    init_containers.push(init_container(&config, images, security_context.clone()));
     let mut containers: Vec<Container> = extra_containers(hoprd_spec.deployment.clone());
-    containers.push(hoprd_container(hoprd_spec, &identity_pool, identity_hoprd, hoprd_host, starting_port, last_port)?);
-    containers.push(metrics_container(&identity_pool));
+    containers.push(hoprd_container(hoprd, images, &identity_pool, identity_hoprd, hoprd_host, starting_port, last_port)?);
+    containers.push(metrics_container(images, &identity_pool, security_context.clone()));
     if hoprd_spec.profiling_enabled.unwrap_or(false) {
         containers.push(profiling_container());
     }
+    // User defined init containers run once the operator init container has written the identity and configuration files
+    let init_containers = merge_extra(
+        init_containers,
+        HoprdDeploymentSpec::get_extra_init_containers(hoprd_spec.deployment.as_ref())?,
+        |container| container.name.as_str(),
+        "container",
+    )?;
+    if let Some(container) = init_containers.iter().find(|init_container| containers.iter().any(|container| container.name == init_container.name)) {
+        return Err(Error::HoprdConfigError(format!("The container '{}' is defined more than once", container.name)));
+    }
+    let volumes = merge_extra(
+        build_volumes(identity_hoprd, labels.get(constants::LABEL_NODE_CLUSTER), hoprd_spec).await,
+        HoprdDeploymentSpec::get_extra_volumes(hoprd_spec.deployment.as_ref())?,
+        |volume| volume.name.as_str(),
+        "volume",
+    )?;
+    if let Some(volume_mount) = HoprdDeploymentSpec::get_extra_volume_mounts(hoprd_spec.deployment.as_ref())?.iter().find(|volume_mount| !volumes.iter().any(|volume| volume.name == volume_mount.name)) {
+        return Err(Error::HoprdConfigError(format!("The volume mount '{}' refers to the undefined volume '{}'", volume_mount.mount_path, volume_mount.name)));
+    }
+
+    // The operator labels are applied last, as the deployment selector depends on them
+    let mut pod_labels: BTreeMap<String, String> = hoprd_spec.deployment.as_ref().and_then(|deployment| deployment.pod_labels.clone()).unwrap_or_default();
+    pod_labels.extend(labels.clone());
 
     Ok(DeploymentSpec {
         replicas: Some(replicas),
@@ -106,13 +154,21 @@ pub async fn build_deployment_spec(
         template: PodTemplateSpec {
             spec: Some(PodSpec {
                 share_process_namespace: Some(hoprd_spec.profiling_enabled.unwrap_or(false)),
-                init_containers: Some(vec![init_container(hoprd_spec, &identity_pool, identity_hoprd)]),
+                init_containers: Some(init_containers),
                 containers,
-                volumes: Some(build_volumes(&identity_hoprd.name_any(), labels.get(constants::LABEL_NODE_CLUSTER), &hoprd_spec).await),
+                volumes: Some(volumes),
+                node_selector: hoprd_spec.deployment.as_ref().and_then(|deployment| deployment.node_selector.clone()),
+                tolerations: HoprdDeploymentSpec::get_tolerations(hoprd_spec.deployment.as_ref())?,
+                affinity: HoprdDeploymentSpec::get_affinity(hoprd_spec.deployment.as_ref())?,
+                topology_spread_constraints: HoprdDeploymentSpec::get_topology_spread_constraints(hoprd_spec.deployment.as_ref())?,
+                priority_class_name: hoprd_spec.deployment.as_ref().and_then(|deployment| deployment.priority_class_name.clone()),
+                security_context: Some(HoprdDeploymentSpec::get_pod_security_context(hoprd_spec.deployment.as_ref())?),
+                image_pull_secrets: images.get_pull_secrets(),
                 ..PodSpec::default()
             }),
             metadata: Some(ObjectMeta {
-                labels: Some(labels),
+                labels: Some(pod_labels),
+                annotations: Some(pod_annotations),
                 ..ObjectMeta::default()
             }),
         },
@@ -120,7 +176,9 @@ pub async fn build_deployment_spec(
     })
 }
 
-pub async fn modify_deployment(context_data: Arc<ContextData>, deployment_name: &str, namespace: &str, hoprd_spec: &HoprdSpec, identity_hoprd: &IdentityHoprd) -> Result<(), Error> {
+pub async fn modify_deployment(context_data: Arc<ContextData>, hoprd: &Hoprd, identity_hoprd: &IdentityHoprd) -> Result<(), Error> {
+    let deployment_name: &str = &hoprd.name_any();
+    let namespace: &str = &hoprd.namespace().unwrap();
     let api: Api<Deployment> = Api::namespaced(context_data.client.clone(), namespace);
     let deployment = match api.get_opt(deployment_name).await? {
         Some(deployment) => deployment,
@@ -129,21 +187,155 @@ pub async fn modify_deployment(context_data: Arc<ContextData>, deployment_name:
             return Err(Error::HoprdStatusError(format!("Deployment '{deployment_name}' not found in namespace '{namespace}'")));
         }
     };
-    let pod_spec = deployment.spec.clone().unwrap().template.spec.unwrap();
-    let hoprd_container = pod_spec.containers.iter().find(|&container| container.name == "hoprd").unwrap();
-    let hoprd_host_env_var = hoprd_container.env.as_ref().unwrap().iter().find(|&env_var| env_var.name.eq(&constants::HOPRD_HOST.to_owned())).unwrap();
-    let hoprd_host_port = hoprd_host_env_var.value.as_ref().unwrap().to_owned();
-    let hoprd_host = *hoprd_host_port.split(':').collect::<Vec<&str>>().get(0).unwrap();
-    let starting_port = hoprd_host_port.split(':').collect::<Vec<&str>>().get(1).unwrap().to_string().parse::<u16>().unwrap();
-    let ports_allocation = hoprd_spec.service.ports_allocation.clone();
+    let (hoprd_host, starting_port) = get_announced_host(&deployment)?;
+    let ports_allocation = hoprd.spec.service.ports_allocation;
     let last_port = starting_port + ports_allocation;
-    let identity_pool: IdentityPool = identity_hoprd.get_identity_pool(context_data.client.clone()).await.unwrap();
-    let spec = build_deployment_spec(deployment.labels().to_owned(), hoprd_spec, identity_pool, identity_hoprd, &hoprd_host, starting_port, last_port).await?;
+    let spec = build_deployment_spec(context_data.clone(), deployment.labels().to_owned(), hoprd, identity_hoprd, &hoprd_host, starting_port, last_port).await?;
+    let mut spec = json!(spec);
+    // Pod labels and annotations removed from the Hoprd need to be explicitly removed from the Deployment
+    let pod_metadata = deployment.spec.as_ref().and_then(|spec| spec.template.metadata.clone()).unwrap_or_default();
+    for (field, previous) in [("labels", pod_metadata.labels), ("annotations", pod_metadata.annotations)] {
+        for key in previous.unwrap_or_default().into_keys() {
+            let pointer = format!("/template/metadata/{}", field);
+            if spec.pointer(&pointer).is_none() {
+                if let Some(metadata) = spec.pointer_mut("/template/metadata").and_then(|metadata| metadata.as_object_mut()) {
+                    metadata.insert(field.to_owned(), json!({}));
+                }
+            }
+            if let Some(values) = spec.pointer_mut(&pointer).and_then(|values| values.as_object_mut()) {
+                values.entry(key).or_insert(Value::Null);
+            }
+        }
+    }
+    // Scheduling fields removed from the Hoprd need to be explicitly removed from the Deployment
+    if let Some(pod_spec) = spec.pointer_mut("/template/spec").and_then(|pod_spec| pod_spec.as_object_mut()) {
+        for field in ["nodeSelector", "tolerations", "affinity", "topologySpreadConstraints", "priorityClassName", "imagePullSecrets"] {
+            pod_spec.entry(field).or_insert(Value::Null);
+        }
+    }
     let patch = &Patch::Merge(json!({ "spec": spec }));
     api.patch(deployment_name, &PatchParams::default(), patch).await.unwrap();
     Ok(())
 }
 
+/// Reads the host and p2p port announced by the node from its deployment
+fn get_announced_host(deployment: &Deployment) -> Result<(String, u16), Error> {
+    let hoprd_host_port = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+        .and_then(|pod_spec| pod_spec.containers.iter().find(|container| container.name == "hoprd"))
+        .and_then(|container| container.env.as_ref())
+        .and_then(|env| env.iter().find(|env_var| env_var.name == constants::HOPRD_HOST))
+        .and_then(|env_var| env_var.value.clone())
+        .ok_or_else(|| Error::HoprdStatusError(format!("Deployment '{}' does not define the {} variable", deployment.name_any(), constants::HOPRD_HOST)))?;
+    match hoprd_host_port.split_once(':').map(|(host, port)| (host, port.parse::<u16>())) {
+        Some((host, Ok(port))) => Ok((host.to_owned(), port)),
+        _ => Err(Error::HoprdStatusError(format!("Deployment '{}' announces the invalid host '{}'", deployment.name_any(), hoprd_host_port))),
+    }
+}
+
+/// The session ports are the ones allocated to the node after the p2p port, if any
+fn get_session_port_range(starting_port: u16, last_port: u16) -> Option<String> {
+    if starting_port != last_port {
+        Some(format!("{}:{}", starting_port + 1, last_port - 1))
+    } else {
+        None
+    }
+}
+
+/// Reads the configuration of the node, replaces the variables known by the operator and validates the result against the schema of the hoprd version
+async fn render_config(
+    context_data: Arc<ContextData>,
+    hoprd: &Hoprd,
+    identity_hoprd: &IdentityHoprd,
+    identity_pool: &IdentityPool,
+    hoprd_host: &str,
+    starting_port: u16,
+    last_port: u16,
+) -> Result<String, Error> {
+    let config = hoprd_config::get_config(context_data.client.clone(), hoprd).await?;
+    let variables = ConfigVariables {
+        node_name: hoprd.name_any(),
+        namespace: hoprd.namespace().unwrap_or_default(),
+        announced_host: format!("{}:{}", hoprd_host, starting_port),
+        safe_address: identity_hoprd.spec.safe_address.to_owned(),
+        module_address: identity_hoprd.spec.module_address.to_owned(),
+        network: identity_pool.spec.network.to_owned(),
+        session_port_range: get_session_port_range(starting_port, last_port),
+    };
+    let config = hoprd_config::render(&config, &variables);
+    // The unknown keys do not block the rollout, they are reported by the node when it validates its configuration
+    hoprd_config::validate(&config, &hoprd.spec.version)?;
+    Ok(config)
+}
+
+/// Nodes without a snapshot section keep downloading the snapshot set in the deprecated `HOPRD_LOGS_SNAPSHOT_URL` variable of the `{pool}-env-vars` ConfigMap
+async fn get_legacy_snapshot(context_data: Arc<ContextData>, identity_pool: &IdentityPool) -> Result<Option<HoprdSnapshotSpec>, Error> {
+    let config_map_api: Api<ConfigMap> = Api::namespaced(context_data.client.clone(), &identity_pool.namespace().unwrap());
+    let url = config_map_api
+        .get_opt(&format!("{}-env-vars", identity_pool.name_any()))
+        .await?
+        .and_then(|config_map| config_map.data)
+        .and_then(|data| data.get(constants::HOPRD_LOGS_SNAPSHOT_URL).cloned())
+        .filter(|url| !url.is_empty());
+    if url.is_some() {
+        warn!("The {} variable of identity pool {} is deprecated, use the snapshot section of its nodes instead", constants::HOPRD_LOGS_SNAPSHOT_URL, identity_pool.name_any());
+    }
+    Ok(url.map(|url| HoprdSnapshotSpec { url, ..HoprdSnapshotSpec::default() }))
+}
+
+/// Computes a checksum of the configuration, the environment variables of the pool and the identity consumed by the pods
+async fn get_checksum(context_data: Arc<ContextData>, identity_pool: &IdentityPool, identity_hoprd: &IdentityHoprd, config: &str) -> Result<String, Error> {
+    let namespace = identity_pool.namespace().unwrap();
+    let env_vars_name = format!("{}-env-vars", identity_pool.name_any());
+    let secret_api: Api<Secret> = Api::namespaced(context_data.client.clone(), &namespace);
+    let config_map_api: Api<ConfigMap> = Api::namespaced(context_data.client.clone(), &namespace);
+    let secret_data = secret_api.get_opt(&env_vars_name).await?.and_then(|secret| secret.data).unwrap_or_default();
+    let config_map_data = config_map_api.get_opt(&env_vars_name).await?.and_then(|config_map| config_map.data).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(config.as_bytes());
+    for (key, value) in secret_data {
+        hasher.update(key.as_bytes());
+        hasher.update(&value.0);
+    }
+    for (key, value) in config_map_data {
+        hasher.update(key.as_bytes());
+        hasher.update(value.as_bytes());
+    }
+    hasher.update(identity_hoprd_secret::get_identity_file(context_data.client.clone(), identity_hoprd).await?);
+    for identity_field in [&identity_hoprd.spec.node_address, &identity_hoprd.spec.safe_address, &identity_hoprd.spec.module_address] {
+        hasher.update(identity_field.as_bytes());
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Rolls the node when the checksum of what its pods consume no longer matches the deployed one. Returns whether the node was rolled
+pub async fn sync_checksum(context_data: Arc<ContextData>, hoprd: &Hoprd, identity_hoprd: &IdentityHoprd) -> Result<bool, Error> {
+    let api: Api<Deployment> = Api::namespaced(context_data.client.clone(), &hoprd.namespace().unwrap());
+    let Some(deployment) = api.get_opt(&hoprd.name_any()).await? else {
+        return Ok(false);
+    };
+    let (hoprd_host, starting_port) = get_announced_host(&deployment)?;
+    let last_port = starting_port + hoprd.spec.service.ports_allocation;
+    let identity_pool: IdentityPool = identity_hoprd.get_identity_pool(context_data.client.clone()).await?;
+    let config = render_config(context_data.clone(), hoprd, identity_hoprd, &identity_pool, &hoprd_host, starting_port, last_port).await?;
+    let checksum = get_checksum(context_data.clone(), &identity_pool, identity_hoprd, &config).await?;
+    let deployed_checksum = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.metadata.as_ref())
+        .and_then(|metadata| metadata.annotations.as_ref())
+        .and_then(|annotations| annotations.get(constants::ANNOTATION_CHECKSUM));
+    // Deployments created before the checksum was recorded get it on their next modification
+    if deployed_checksum.is_none_or(|deployed_checksum| deployed_checksum == &checksum) {
+        return Ok(false);
+    }
+    info!("Configuration consumed by Hoprd node {} changed, rolling the deployment", hoprd.name_any());
+    modify_deployment(context_data, hoprd, identity_hoprd).await?;
+    Ok(true)
+}
+
 pub fn extra_containers(hoprd_deployment_spec: Option<HoprdDeploymentSpec>) -> Vec<Container> {
     let default_deployment_spec = HoprdDeploymentSpec::default();
     let hoprd_deployment_spec = hoprd_deployment_spec.unwrap_or(default_deployment_spec.clone());
@@ -155,95 +347,74 @@ pub fn extra_containers(hoprd_deployment_spec: Option<HoprdDeploymentSpec>) -> V
     }
 }
 
-pub fn init_container(hoprd_spec: &HoprdSpec,
-    identity_pool: &IdentityPool,
-    identity_hoprd: &IdentityHoprd,) -> Container {
-    let encoded_configuration = general_purpose::STANDARD.encode(&hoprd_spec.config);
-    let volume_mounts: Option<Vec<VolumeMount>> = build_volume_mounts();
-    let args = if hoprd_spec.source_node_logs.unwrap_or(false) {
-        Some(vec![format!(
-            "set -x\n\
-            set -e\n\
-            echo $HOPRD_IDENTITY_FILE | base64 -d > /app/hoprd-identity/.hopr-id\n\
-            echo $HOPRD_CONFIGURATION | base64 -d > /app/hoprd-identity/config.yaml"
-        )])
-    } else {
-        Some(vec![format!(
-            "set -x\n\
-            set -e\n\
-            if [ -n \"$HOPRD_LOGS_SNAPSHOT_URL\" ] && ! ls /app/hoprd-db/db/hopr_logs.db* 1> /dev/null 2>&1; then\n\
-            apk add --no-cache curl tar xz;\n\
-            mkdir -p /app/hoprd-db/db;\n\
-            curl -sf --retry 3 \"$HOPRD_LOGS_SNAPSHOT_URL\" -o /app/hoprd-db/db/snapshot.tar.xz;\n\
-            tar xf /app/hoprd-db/db/snapshot.tar.xz -C /app/hoprd-db/db;\n\
-            rm -f /app/hoprd-db/db/snapshot.tar.xz;\n\
-            fi;\n\
-            echo $HOPRD_IDENTITY_FILE | base64 -d > /app/hoprd-identity/.hopr-id\n\
-            echo $HOPRD_CONFIGURATION | base64 -d > /app/hoprd-identity/config.yaml"
-        )])
-    };
+pub fn init_container(config: &str,
+    images: &ImagesConfig,
+    security_context: SecurityContext) -> Container {
+    let encoded_configuration = general_purpose::STANDARD.encode(config);
+    // The identity file is only readable by the init container, which copies it to the writable identity volume
+    let mut volume_mounts: Vec<VolumeMount> = build_volume_mounts().unwrap_or_default();
+    volume_mounts.push(VolumeMount {
+        name: "hoprd-identity-file".to_owned(),
+        mount_path: "/app/hoprd-identity-file".to_owned(),
+        read_only: Some(true),
+        ..VolumeMount::default()
+    });
+    let args = Some(vec![format!(
+        "set -x\n\
+        set -e\n\
+        cp /app/hoprd-identity-file/{} /app/hoprd-identity/.hopr-id\n\
+        echo $HOPRD_CONFIGURATION | base64 -d > /app/hoprd-identity/config.yaml",
+        constants::IDENTITY_HOPRD_SECRET_KEY
+    )]);
     Container {
         name: "init".to_owned(),
-        image: Some("alpine".to_owned()),
+        image: Some(images.init.to_owned()),
+        image_pull_policy: images.pull_policy.clone(),
         env: Some(vec![
-            EnvVar {
-                name: constants::HOPRD_IDENTITY_FILE.to_owned(),
-                value: Some(identity_hoprd.spec.identity_file.to_owned()),
-                ..EnvVar::default()
-            },
             EnvVar {
                 name: constants::HOPRD_CONFIGURATION.to_owned(),
                 value: Some(encoded_configuration),
                 ..EnvVar::default()
             },
         ]),
-        env_from: Some(vec![
-            EnvFromSource {
-                config_map_ref: Some(ConfigMapEnvSource {
-                    name: format!("{}-env-vars", identity_pool.name_any()),
-                    ..ConfigMapEnvSource::default()
-                }),
-                ..EnvFromSource::default()
-            }
-        ]),
         command: Some(vec!["sh".to_string(), "-c".to_string()]),
         args,
-        volume_mounts,
+        volume_mounts: Some(volume_mounts),
+        security_context: Some(security_context),
         ..Container::default()
     }
 }
 
-pub fn hoprd_container(hoprd_spec: &HoprdSpec,
+pub fn hoprd_container(hoprd: &Hoprd,
+    images: &ImagesConfig,
     identity_pool: &IdentityPool,
     identity_hoprd: &IdentityHoprd,
     hoprd_host: &str,
     starting_port: u16,
     last_port: u16) -> Result<Container, Error> {
-    let image = format!(
-        "{}/{}:{}",
-        constants::HOPR_DOCKER_REGISTRY.to_owned(),
-        constants::HOPR_DOCKER_IMAGE_NAME.to_owned(),
-        &hoprd_spec.version.to_owned()
-    );
+    let hoprd_spec: &HoprdSpec = &hoprd.spec;
+    let image = hoprd.get_image(images);
 
     let resources = Some(HoprdDeploymentSpec::get_resource_requirements(hoprd_spec.deployment.clone()));
     let liveness_probe = HoprdDeploymentSpec::get_liveness_probe(hoprd_spec.deployment.clone());
     let readiness_probe = HoprdDeploymentSpec::get_readiness_probe(hoprd_spec.deployment.clone(), hoprd_spec.source_node_logs.unwrap_or(false));
     let startup_probe = HoprdDeploymentSpec::get_startup_probe(hoprd_spec.deployment.clone(), hoprd_spec.source_node_logs.unwrap_or(false));
-    let volume_mounts: Option<Vec<VolumeMount>> = build_volume_mounts();
+    let security_context = HoprdDeploymentSpec::get_security_context(hoprd_spec.deployment.as_ref())?;
+    let volume_mounts = merge_extra(
+        build_volume_mounts().unwrap_or_default(),
+        HoprdDeploymentSpec::get_extra_volume_mounts(hoprd_spec.deployment.as_ref())?,
+        |volume_mount| volume_mount.mount_path.as_str(),
+        "volume mount path",
+    )?;
     let hoprd_host_port = format!("{}:{}", hoprd_host, starting_port);
 
     // Only define the session port range env var if the ports allocated value is greater than 0
-    let session_port_range: Option<String> = if starting_port != last_port {
-        Some(format!("{}:{}", starting_port + 1, last_port - 1))
-    } else {
-        None
-    };
+    let session_port_range: Option<String> = get_session_port_range(starting_port, last_port);
 
     Ok(Container {
         name: "hoprd".to_owned(),
         image: Some(image),
-        image_pull_policy: Some("Always".to_owned()),
+        image_pull_policy: images.pull_policy.clone().or_else(|| Some("Always".to_owned())),
         ports: Some(build_ports(starting_port.into(), last_port.into())),
         env: Some(build_env_vars(identity_hoprd, &hoprd_host_port, hoprd_spec, session_port_range)?),
         env_from: Some(vec![
@@ -265,22 +436,18 @@ pub fn hoprd_container(hoprd_spec: &HoprdSpec,
         liveness_probe,
         readiness_probe,
         startup_probe,
-        volume_mounts,
+        volume_mounts: Some(volume_mounts),
         resources,
+        security_context: Some(security_context),
         ..Container::default()
     })
 }
 
-pub fn metrics_container(identity_pool: &IdentityPool) -> Container {
-    let image: String = format!(
-        "{}/{}:{}",
-        constants::HOPR_DOCKER_REGISTRY,
-        constants::HOPR_DOCKER_METRICS_IMAGE_NAME,
-        constants::OPERATOR_METRICS_CONTAINER_TAG
-    );
+pub fn metrics_container(images: &ImagesConfig, identity_pool: &IdentityPool, security_context: SecurityContext) -> Container {
     Container {
         name: "hoprd-metrics".to_owned(),
-        image: Some(image),
+        image: Some(images.metrics.to_owned()),
+        image_pull_policy: images.pull_policy.clone(),
         ports: Some(vec![ContainerPort {
             container_port: 8080,
             name: Some("metrics".to_owned()),
@@ -306,6 +473,12 @@ pub fn metrics_container(identity_pool: &IdentityPool) -> Container {
             failure_threshold: Some(6),
             ..Probe::default()
         }),
+        volume_mounts: Some(vec![VolumeMount {
+            name: "tmp-volume".to_owned(),
+            mount_path: "/tmp".to_owned(),
+            ..VolumeMount::default()
+        }]),
+        security_context: Some(security_context),
          ..Container::default()
     }
 }
@@ -315,8 +488,10 @@ pub fn profiling_container() -> Container {
         name: "hoprd-profiling".to_owned(),
         image: Some("ubuntu:24.04".to_string()),
         args: Some(vec![String::from("/scripts/profiling.sh")]),
+        // Profiling requires a privileged root container, so it cannot run in namespaces enforcing the restricted profile
         security_context: Some(SecurityContext {
             run_as_user: Some(0),
+            run_as_non_root: Some(false),
             privileged: Some(true),
             capabilities: Some(Capabilities {
                 add: Some(vec!["SYS_PTRACE".to_string(), "PERFMON".to_string(), "SYS_ADMIN".to_string()]),
@@ -368,35 +543,68 @@ pub async fn delete_depoyment(client: Client, name: &str, namespace: &str) -> Re
     }
 }
 
-pub async fn delete_database(context_data: Arc<ContextData>, deployment_name: &str, namespace: &str) -> Result<(), Error> {
+/// Starts resetting the database of the node: scales its deployment down and creates the job deleting the database. Returns the name of the job,
+/// which is followed by `check_database_reset` on later reconciliations
+pub async fn start_database_reset(context_data: Arc<ContextData>, hoprd: &Hoprd) -> Result<String, Error> {
+    let deployment_name: &str = &hoprd.name_any();
+    let namespace: &str = &hoprd.namespace().unwrap();
     let api: Api<Deployment> = Api::namespaced(context_data.client.clone(), namespace);
-    let deployment = api.get(deployment_name).await.unwrap();
-    let spec = deployment.spec.as_ref().unwrap();
-    let volumes = spec.template.spec.clone().unwrap().volumes.unwrap().clone();
-    let volume: &Volume = volumes.iter().find(|&volume| volume.name.eq("hoprd-db")).unwrap();
-    let pvc_name = volume.persistent_volume_claim.as_ref().unwrap().claim_name.clone();
+    let Some(deployment) = api.get_opt(deployment_name).await? else {
+        return Err(Error::HoprdStatusError(format!("Deployment '{deployment_name}' not found in namespace '{namespace}'")));
+    };
+    let pod_spec = deployment.spec.as_ref().and_then(|spec| spec.template.spec.as_ref());
+    let pvc_name = pod_spec
+        .and_then(|pod_spec| pod_spec.volumes.as_ref())
+        .and_then(|volumes| volumes.iter().find(|volume| volume.name.eq("hoprd-db")))
+        .and_then(|volume| volume.persistent_volume_claim.as_ref())
+        .map(|persistent_volume_claim| persistent_volume_claim.claim_name.clone())
+        .ok_or_else(|| Error::HoprdStatusError(format!("Deployment '{deployment_name}' does not mount the database from a persistent volume claim")))?;
+    let pod_security_context = pod_spec.and_then(|pod_spec| pod_spec.security_context.clone());
     info!("Scaling down deployment {} in namespace {}", deployment_name, namespace);
     let patch = Patch::Merge(json!({ "spec": { "replicas": 0 } }));
-    match api.patch(&deployment_name, &PatchParams::default(), &patch).await {
-        Ok(_) => {}
-        Err(error) => error!("Could not scale down deployment {deployment_name}: {:?}", error),
-    };
+    api.patch(deployment_name, &PatchParams::default(), &patch).await?;
     info!("Deleting hoprd database for {} in namespace {}", deployment_name, namespace);
-    let delete_result = hoprd_deployment::job_delete_database(context_data.clone(), deployment_name, namespace, &pvc_name).await;
-    if let Err(e) = delete_result {
-        error!("Failed to delete database: {:?}", e);
-        return Err(e);
+    let owner_references: Option<Vec<OwnerReference>> = Some(vec![hoprd.controller_owner_ref(&()).unwrap()]);
+    hoprd_deployment::job_delete_database(context_data.clone(), deployment_name, namespace, &pvc_name, pod_security_context, owner_references).await
+}
+
+/// Checks the job deleting the database of the node. Once it succeeds the deployment is scaled up again and the job deleted. Returns whether the reset finished,
+/// or a `JobExecutionError` when the job failed, in which case the job is kept for inspection and the node started with the database the job left
+pub async fn check_database_reset(context_data: Arc<ContextData>, hoprd: &Hoprd, job_name: &str) -> Result<bool, Error> {
+    let deployment_name: &str = &hoprd.name_any();
+    let namespace: &str = &hoprd.namespace().unwrap();
+    let job_api: Api<Job> = Api::namespaced(context_data.client.clone(), namespace);
+    let job = job_api.get_opt(job_name).await?;
+    let job_status = job.as_ref().and_then(|job| job.status.clone()).unwrap_or_default();
+    let succeeded = job_status.succeeded.unwrap_or(0) > 0;
+    if job.is_some() && !succeeded && job_status.failed.unwrap_or(0) == 0 {
+        return Ok(false);
     }
+    let replicas: i32 = if hoprd.spec.enabled.unwrap_or(true) { 1 } else { 0 };
     info!("Scaling up deployment {} in namespace {}", deployment_name, namespace);
-    let patch = Patch::Merge(json!({ "spec": { "replicas": 1 } }));
-    match api.patch(&deployment_name, &PatchParams::default(), &patch).await {
-        Ok(_) => {}
-        Err(error) => error!("Could not scale up deployment {deployment_name}: {:?}", error),
-    };
-    Ok(())
+    let api: Api<Deployment> = Api::namespaced(context_data.client.clone(), namespace);
+    let patch = Patch::Merge(json!({ "spec": { "replicas": replicas } }));
+    api.patch(deployment_name, &PatchParams::default(), &patch).await?;
+    if job.is_none() {
+        return Err(Error::JobExecutionError(format!("Job {} deleting the database of {} not found", job_name, deployment_name)));
+    }
+    if !succeeded {
+        return Err(Error::JobExecutionError(format!("Job {} deleting the database of {} failed", job_name, deployment_name)));
+    }
+    info!("Job {} completed successfully", job_name);
+    job_api.delete(job_name, &DeleteParams::background()).await?;
+    Ok(true)
 }
 
-pub async fn job_delete_database(context_data: Arc<ContextData>, deployment_name: &str, namespace: &str, pvc_name: &str) -> Result<(), Error> {
+/// Creates a job deleting the contents of the node database volume. The job runs with the same pod security context as the node, so it owns the same files
+pub async fn job_delete_database(
+    context_data: Arc<ContextData>,
+    deployment_name: &str,
+    namespace: &str,
+    pvc_name: &str,
+    pod_security_context: Option<PodSecurityContext>,
+    owner_references: Option<Vec<OwnerReference>>,
+) -> Result<String, Error> {
     let api: Api<Job> = Api::namespaced(context_data.client.clone(), namespace);
     let rng = rand::rng();
     let suffix: String = rng.sample_iter(&Alphanumeric).take(10).map(char::from).collect();
@@ -407,6 +615,7 @@ pub async fn job_delete_database(context_data: Arc<ContextData>, deployment_name
     let job = Job {
         metadata: ObjectMeta {
             name: Some(job_name.clone()),
+            owner_references,
             ..Default::default()
         },
         spec: Some(JobSpec {
@@ -422,15 +631,19 @@ pub async fn job_delete_database(context_data: Arc<ContextData>, deployment_name
                     }]),
                     containers: vec![Container {
                         name: "delete-hoprd-db".to_string(),
-                        image: Some("debian:stable".to_string()),
+                        image: Some(context_data.config.images.delete_database.to_owned()),
+                        image_pull_policy: context_data.config.images.pull_policy.clone(),
                         command: Some(vec!["/bin/sh".to_string(), "-c".to_string(), command]),
                         volume_mounts: Some(vec![VolumeMount {
                             name: "hoprd-db".to_string(),
                             mount_path: "/app/hoprd-db".to_string(),
                             ..Default::default()
                         }]),
+                        security_context: Some(HoprdDeploymentSpec::default_security_context()),
                         ..Default::default()
                     }],
+                    security_context: pod_security_context,
+                    image_pull_secrets: context_data.config.images.get_pull_secrets(),
                     restart_policy: Some("Never".to_string()),
                     ..Default::default()
                 }),
@@ -442,28 +655,7 @@ pub async fn job_delete_database(context_data: Arc<ContextData>, deployment_name
     };
 
     api.create(&PostParams::default(), &job).await?;
-    // Watch the Job to wait for it to complete
-    let mut stream = api.watch(&WatchParams::default(), "0").await?.boxed();
-
-    while let Some(event) = stream.next().await {
-        match event {
-            Ok(WatchEvent::Modified(ref job)) if job.metadata.name.as_deref() == Some(&job_name.clone()) => {
-                if let Some(JobStatus { succeeded: Some(1), .. }) = job.status {
-                    info!("Job {} completed successfully", job_name);
-                    return Ok(());
-                } else if let Some(JobStatus { failed: Some(1), .. }) = job.status {
-                    error!("Job {} failed", job_name);
-                    return Err(Error::JobExecutionError(format!("Job {} failed", job_name)));
-                }
-            }
-            Err(e) => {
-                error!("Error watching Job {}: {}", job_name, e);
-                return Err(Error::JobExecutionError(format!("Error watching Job {}", job_name)));
-            }
-            _ => {}
-        }
-    }
-    Ok(())
+    Ok(job_name)
 }
 
 /// Builds the struct VolumeMount to be attached into the Container
@@ -487,31 +679,53 @@ fn build_volume_mounts() -> Option<Vec<VolumeMount>> {
     ])
 }
 
+/// Appends the items defined by the user after the ones managed by the operator, keeping their order and rejecting duplicated keys
+fn merge_extra<T>(managed: Vec<T>, extra: Vec<T>, key: fn(&T) -> &str, kind: &str) -> Result<Vec<T>, Error> {
+    let mut merged = managed;
+    for item in extra {
+        if merged.iter().any(|existing| key(existing) == key(&item)) {
+            return Err(Error::HoprdConfigError(format!("The {} '{}' is defined more than once", kind, key(&item))));
+        }
+        merged.push(item);
+    }
+    Ok(merged)
+}
+
 /// Builds the struct Volume to be included as part of the PodSpec
 ///
 /// # Arguments
-/// - `secret` - Secret struct used to build the volume for HOPRD_IDENTITY path
-async fn build_volumes(pvc_name: &String, cluster_hoprd: Option<&String>, hoprd_spec: &HoprdSpec) -> Vec<Volume> {
-    let mut volumes: Vec<Volume> =vec![];
-    volumes.push(
-    Volume {
-        name: "hoprd-identity".to_owned(),
-        empty_dir: Some(EmptyDirVolumeSource::default()),
-        ..Volume::default()
-    });
-    volumes.push(Volume {
-        name: "hoprd-db".to_owned(),
-        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
-            claim_name: pvc_name.to_owned(),
-            read_only: Some(false),
-        }),
-        ..Volume::default()
-    });
-    volumes.push(Volume {
-        name: "tmp-volume".to_owned(),
-        empty_dir: Some(EmptyDirVolumeSource::default()),
-        ..Volume::default()
-    });
+/// - `identity_hoprd` - Identity whose PVC holds the database and whose Secret holds the identity file
+async fn build_volumes(identity_hoprd: &IdentityHoprd, cluster_hoprd: Option<&String>, hoprd_spec: &HoprdSpec) -> Vec<Volume> {
+    let mut volumes: Vec<Volume> = vec![
+        Volume {
+            name: "hoprd-identity".to_owned(),
+            empty_dir: Some(EmptyDirVolumeSource::default()),
+            ..Volume::default()
+        },
+        Volume {
+            name: "hoprd-identity-file".to_owned(),
+            secret: Some(SecretVolumeSource {
+                secret_name: Some(identity_hoprd_secret::secret_name(identity_hoprd)),
+                // The fsGroup of the pod grants the group read access on top of it
+                default_mode: Some(0o400),
+                ..SecretVolumeSource::default()
+            }),
+            ..Volume::default()
+        },
+        Volume {
+            name: "hoprd-db".to_owned(),
+            persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
+                claim_name: identity_hoprd.name_any(),
+                read_only: Some(false),
+            }),
+            ..Volume::default()
+        },
+        Volume {
+            name: "tmp-volume".to_owned(),
+            empty_dir: Some(EmptyDirVolumeSource::default()),
+            ..Volume::default()
+        },
+    ];
     if hoprd_spec.profiling_enabled.unwrap_or(false) {
         volumes.push(Volume {
             name: "service-account-key".to_owned(),
@@ -639,3 +853,34 @@ fn build_env_vars(identity_hoprd: &IdentityHoprd, hoprd_host: &String, hoprd_spe
     }
     Ok(env_vars)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_extra_volumes() {
+        let volume = |name: &str| Volume { name: name.to_owned(), ..Volume::default() };
+        let merged = merge_extra(vec![volume("hoprd-db"), volume("tmp-volume")], vec![volume("ca-bundle"), volume("vault")], |volume| volume.name.as_str(), "volume").unwrap();
+        let names: Vec<&str> = merged.iter().map(|volume| volume.name.as_str()).collect();
+        assert_eq!(names, vec!["hoprd-db", "tmp-volume", "ca-bundle", "vault"]);
+
+        let error = merge_extra(vec![volume("hoprd-db")], vec![volume("hoprd-db")], |volume| volume.name.as_str(), "volume").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid Hoprd configuration: The volume 'hoprd-db' is defined more than once");
+    }
+
+    #[tokio::test]
+    async fn test_identity_file_volume_is_read_only_for_the_owner() {
+        let identity_hoprd = IdentityHoprd {
+            metadata: ObjectMeta {
+                name: Some("core-rotsee-1".to_owned()),
+                ..ObjectMeta::default()
+            },
+            ..IdentityHoprd::default()
+        };
+        let volumes = build_volumes(&identity_hoprd, None, &HoprdSpec::default()).await;
+        let identity_file = volumes.iter().find(|volume| volume.name == "hoprd-identity-file").and_then(|volume| volume.secret.as_ref()).unwrap();
+        assert_eq!(identity_file.secret_name.as_deref(), Some("core-rotsee-1-identity"));
+        assert_eq!(identity_file.default_mode, Some(0o400));
+    }
+}
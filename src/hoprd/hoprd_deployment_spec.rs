@@ -1,13 +1,15 @@
 use k8s_openapi::{
-    api::core::v1::{EnvVar, EnvVarSource, HTTPGetAction, Probe, ResourceRequirements, SecretKeySelector},
-    apimachinery::pkg::{api::resource::Quantity, util::intstr::IntOrString},
+    api::core::v1::{
+        Affinity, Capabilities, Container, EnvVar, EnvVarSource, HTTPGetAction, PodSecurityContext, Probe, ResourceRequirements, SeccompProfile, SecretKeySelector, SecurityContext, Toleration, TopologySpreadConstraint, Volume, VolumeMount,
+    },
+    apimachinery::pkg::{api::resource::Quantity, apis::meta::v1::LabelSelector, util::intstr::IntOrString},
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 use std::collections::BTreeMap;
 
-use crate::model::Error;
+use crate::{constants, model::Error};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct CustomKeyRef {
@@ -17,6 +19,7 @@ struct CustomKeyRef {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct CustomValueFrom {
+    #[serde(alias = "secretKeyRef")]
     secret_key_ref: CustomKeyRef,
 }
 
@@ -72,6 +75,18 @@ pub struct HoprdDeploymentSpec {
     pub liveness_probe: Option<String>,
     pub readiness_probe: Option<String>,
     pub extra_containers: Option<String>,
+    pub extra_init_containers: Option<String>,
+    pub extra_volumes: Option<String>,
+    pub extra_volume_mounts: Option<String>,
+    pub node_selector: Option<BTreeMap<String, String>>,
+    pub tolerations: Option<String>,
+    pub affinity: Option<String>,
+    pub topology_spread_constraints: Option<String>,
+    pub priority_class_name: Option<String>,
+    pub pod_labels: Option<BTreeMap<String, String>>,
+    pub pod_annotations: Option<BTreeMap<String, String>>,
+    pub pod_security_context: Option<String>,
+    pub security_context: Option<String>,
 }
 
 impl Default for HoprdDeploymentSpec {
@@ -89,9 +104,6 @@ impl Default for HoprdDeploymentSpec {
         })
         .unwrap();
 
-        let default_probe = HoprdDeploymentSpec::build_probe("some/path".to_string(), Some(5), Some(1), Some(10));
-        let default_probe_string = Some(serde_yml::to_string(&default_probe).unwrap());
-
         let default_env = vec![
             CustomEnvVar::new_value("RUST_BACKTRACE".to_owned(), "full".to_owned()),
             CustomEnvVar::new_value("RUST_LOG".to_owned(), "info".to_owned()),
@@ -101,11 +113,23 @@ impl Default for HoprdDeploymentSpec {
 
         Self {
             resources: Some(resources_spec),
-            startup_probe: default_probe_string.clone(),
-            liveness_probe: default_probe_string.clone(),
-            readiness_probe: default_probe_string.clone(),
+            startup_probe: Some(serde_yml::to_string(&HoprdDeploymentSpec::default_startup_probe(false)).unwrap()),
+            liveness_probe: Some(serde_yml::to_string(&HoprdDeploymentSpec::default_liveness_probe()).unwrap()),
+            readiness_probe: Some(serde_yml::to_string(&HoprdDeploymentSpec::default_readiness_probe(false)).unwrap()),
             env: default_env_string,
             extra_containers: None,
+            extra_init_containers: None,
+            extra_volumes: None,
+            extra_volume_mounts: None,
+            node_selector: None,
+            tolerations: None,
+            affinity: None,
+            topology_spread_constraints: None,
+            priority_class_name: None,
+            pod_labels: None,
+            pod_annotations: None,
+            pod_security_context: None,
+            security_context: None,
         }
     }
 }
@@ -128,15 +152,31 @@ impl HoprdDeploymentSpec {
         // Get custom env vars
         let custom_environment_variables_string = hoprd_deployment_spec.env.as_ref().unwrap_or(default_deployment_spec.env.as_ref().unwrap());
         trace!("Custom environment variables string: {}", custom_environment_variables_string);
-        let custom_environment_variables: Vec<CustomEnvVar> = serde_yml::from_str(custom_environment_variables_string)?;
+        let custom_environment_variables: Vec<EnvVar> = HoprdDeploymentSpec::parse_environment_variables(custom_environment_variables_string)?;
 
         // Merge default and custom env vars, giving precedence to custom ones in case of name conflicts
         let env_vars: Vec<EnvVar> = default_environment_variables.iter().filter(| &default_environment_variable| {
             !custom_environment_variables.iter().any(|custom_environment_variable| custom_environment_variable.name == default_environment_variable.name)
-        }).chain(custom_environment_variables.iter()).map(|env| env.to_env_var()).collect();
+        }).map(|env| env.to_env_var()).chain(custom_environment_variables.iter().cloned()).collect();
         Ok(env_vars)
     }
 
+    /// Parses the `env` field, which accepts both Kubernetes env vars and the legacy `value_from.secret_key_ref` notation
+    pub fn parse_environment_variables(env: &str) -> Result<Vec<EnvVar>, Error> {
+        let env_values: Option<Vec<serde_yml::Value>> = serde_yml::from_str(env)?;
+        env_values
+            .unwrap_or_default()
+            .into_iter()
+            .map(|env_value| {
+                if env_value.get("value_from").is_some() {
+                    Ok(serde_yml::from_value::<CustomEnvVar>(env_value)?.to_env_var())
+                } else {
+                    Ok(serde_yml::from_value::<EnvVar>(env_value)?)
+                }
+            })
+            .collect()
+    }
+
     pub fn build_probe(path: String, period_seconds: Option<i32>, success_threshold: Option<i32>, failure_threshold: Option<i32>) -> Probe {
         Probe {
             http_get: Some(HTTPGetAction {
@@ -152,8 +192,48 @@ impl HoprdDeploymentSpec {
         }
     }
 
+    pub fn default_liveness_probe() -> Probe {
+        HoprdDeploymentSpec::build_probe("/healthyz".to_owned(), Some(5), Some(1), Some(3))
+    }
+
+    pub fn default_startup_probe(source_node_logs: bool) -> Probe {
+        let period_seconds = if source_node_logs { Some(60) } else { Some(15) };
+        HoprdDeploymentSpec::build_probe("/startedz".to_owned(), period_seconds, Some(1), Some(60))
+    }
+
+    pub fn default_readiness_probe(source_node_logs: bool) -> Probe {
+        let period_seconds = if source_node_logs { Some(60) } else { Some(15) };
+        HoprdDeploymentSpec::build_probe("/readyz".to_owned(), period_seconds, Some(1), Some(60))
+    }
+
+    /// Fills the missing fields of the deployment specification with the values the operator would use otherwise.
+    /// Probes are only filled for single nodes, as their defaults depend on whether the node sources the logs snapshot.
+    pub fn with_defaults(hoprd_deployment_spec: Option<HoprdDeploymentSpec>, source_node_logs: Option<bool>) -> HoprdDeploymentSpec {
+        let default_deployment_spec = HoprdDeploymentSpec::default();
+        let mut hoprd_deployment_spec = hoprd_deployment_spec.unwrap_or_else(|| HoprdDeploymentSpec {
+            startup_probe: None,
+            liveness_probe: None,
+            readiness_probe: None,
+            ..default_deployment_spec.clone()
+        });
+        hoprd_deployment_spec.resources = hoprd_deployment_spec.resources.or(default_deployment_spec.resources);
+        hoprd_deployment_spec.env = hoprd_deployment_spec.env.or(default_deployment_spec.env);
+        if let Some(source_node_logs) = source_node_logs {
+            hoprd_deployment_spec.liveness_probe = hoprd_deployment_spec
+                .liveness_probe
+                .or_else(|| serde_yml::to_string(&HoprdDeploymentSpec::default_liveness_probe()).ok());
+            hoprd_deployment_spec.startup_probe = hoprd_deployment_spec
+                .startup_probe
+                .or_else(|| serde_yml::to_string(&HoprdDeploymentSpec::default_startup_probe(source_node_logs)).ok());
+            hoprd_deployment_spec.readiness_probe = hoprd_deployment_spec
+                .readiness_probe
+                .or_else(|| serde_yml::to_string(&HoprdDeploymentSpec::default_readiness_probe(source_node_logs)).ok());
+        }
+        hoprd_deployment_spec
+    }
+
     pub fn get_liveness_probe(hoprd_deployment_spec_option: Option<HoprdDeploymentSpec>) -> Option<Probe> {
-        let default_liveness_probe = HoprdDeploymentSpec::build_probe("/healthyz".to_owned(), Some(5), Some(1), Some(3));
+        let default_liveness_probe = HoprdDeploymentSpec::default_liveness_probe();
         if let Some(hoprd_deployment_spec) = hoprd_deployment_spec_option {
             if let Some(liveness_probe_string) = hoprd_deployment_spec.liveness_probe {
                 Some(serde_yml::from_str(&liveness_probe_string).unwrap())
@@ -166,8 +246,7 @@ impl HoprdDeploymentSpec {
     }
 
     pub fn get_startup_probe(hoprd_deployment_spec_option: Option<HoprdDeploymentSpec>, source_node_logs: bool) -> Option<Probe> {
-        let period_seconds = if source_node_logs { Some(60) } else { Some(15) };
-        let default_startup_probe = HoprdDeploymentSpec::build_probe("/startedz".to_owned(), period_seconds, Some(1), Some(60));
+        let default_startup_probe = HoprdDeploymentSpec::default_startup_probe(source_node_logs);
         if let Some(hoprd_deployment_spec) = hoprd_deployment_spec_option {
             if let Some(startup_probe_string) = hoprd_deployment_spec.startup_probe {
                 Some(serde_yml::from_str(&startup_probe_string).unwrap())
@@ -180,8 +259,7 @@ impl HoprdDeploymentSpec {
     }
 
     pub fn get_readiness_probe(hoprd_deployment_spec_option: Option<HoprdDeploymentSpec>, source_node_logs: bool) -> Option<Probe> {
-        let period_seconds = if source_node_logs { Some(60) } else { Some(15) };
-        let default_readiness_probe = HoprdDeploymentSpec::build_probe("/readyz".to_owned(), period_seconds, Some(1), Some(60));
+        let default_readiness_probe = HoprdDeploymentSpec::default_readiness_probe(source_node_logs);
         if let Some(hoprd_deployment_spec) = hoprd_deployment_spec_option {
             if let Some(readiness_probe_string) = hoprd_deployment_spec.readiness_probe {
                 Some(serde_yml::from_str(&readiness_probe_string).unwrap())
@@ -192,4 +270,192 @@ impl HoprdDeploymentSpec {
             Some(default_readiness_probe)
         }
     }
+
+    /// Pod security context compliant with the `restricted` Pod Security Standard
+    pub fn default_pod_security_context() -> PodSecurityContext {
+        PodSecurityContext {
+            run_as_non_root: Some(true),
+            run_as_user: Some(constants::HOPRD_POD_USER_ID),
+            run_as_group: Some(constants::HOPRD_POD_USER_ID),
+            fs_group: Some(constants::HOPRD_POD_USER_ID),
+            fs_group_change_policy: Some("OnRootMismatch".to_owned()),
+            seccomp_profile: Some(SeccompProfile {
+                type_: "RuntimeDefault".to_owned(),
+                ..SeccompProfile::default()
+            }),
+            ..PodSecurityContext::default()
+        }
+    }
+
+    /// Container security context applied to the containers managed by the operator. Writable paths are mounted as volumes
+    pub fn default_security_context() -> SecurityContext {
+        SecurityContext {
+            allow_privilege_escalation: Some(false),
+            read_only_root_filesystem: Some(true),
+            run_as_non_root: Some(true),
+            capabilities: Some(Capabilities {
+                drop: Some(vec!["ALL".to_owned()]),
+                ..Capabilities::default()
+            }),
+            seccomp_profile: Some(SeccompProfile {
+                type_: "RuntimeDefault".to_owned(),
+                ..SeccompProfile::default()
+            }),
+            ..SecurityContext::default()
+        }
+    }
+
+    /// An override without `fsGroup` keeps the default group, as the non-root containers read the identity file through the group of its volume
+    pub fn get_pod_security_context(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<PodSecurityContext, Error> {
+        match hoprd_deployment_spec.and_then(|spec| spec.pod_security_context.as_deref()) {
+            Some(pod_security_context) => {
+                let mut pod_security_context: PodSecurityContext = serde_yml::from_str(pod_security_context)?;
+                if pod_security_context.fs_group.is_none() {
+                    let default_pod_security_context = HoprdDeploymentSpec::default_pod_security_context();
+                    pod_security_context.fs_group = default_pod_security_context.fs_group;
+                    pod_security_context.fs_group_change_policy = pod_security_context.fs_group_change_policy.or(default_pod_security_context.fs_group_change_policy);
+                }
+                Ok(pod_security_context)
+            }
+            None => Ok(HoprdDeploymentSpec::default_pod_security_context()),
+        }
+    }
+
+    pub fn get_security_context(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<SecurityContext, Error> {
+        match hoprd_deployment_spec.and_then(|spec| spec.security_context.as_deref()) {
+            Some(security_context) => Ok(serde_yml::from_str(security_context)?),
+            None => Ok(HoprdDeploymentSpec::default_security_context()),
+        }
+    }
+
+    pub fn get_extra_init_containers(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Vec<Container>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.extra_init_containers.as_deref()).map(serde_yml::from_str).transpose()?.unwrap_or_default())
+    }
+
+    pub fn get_extra_volumes(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Vec<Volume>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.extra_volumes.as_deref()).map(serde_yml::from_str).transpose()?.unwrap_or_default())
+    }
+
+    pub fn get_extra_volume_mounts(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Vec<VolumeMount>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.extra_volume_mounts.as_deref()).map(serde_yml::from_str).transpose()?.unwrap_or_default())
+    }
+
+    pub fn get_tolerations(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Option<Vec<Toleration>>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.tolerations.as_deref()).map(serde_yml::from_str).transpose()?)
+    }
+
+    pub fn get_affinity(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Option<Affinity>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.affinity.as_deref()).map(serde_yml::from_str).transpose()?)
+    }
+
+    /// Deployment specification of the nodes of a cluster, which are spread across zones unless other topology spread constraints are given.
+    /// The other fields are passed as they are, so that each node fills the missing ones with its own defaults
+    pub fn for_cluster_nodes(hoprd_deployment_spec: Option<HoprdDeploymentSpec>, cluster_name: &str) -> Result<HoprdDeploymentSpec, Error> {
+        let mut hoprd_deployment_spec = hoprd_deployment_spec.unwrap_or(HoprdDeploymentSpec {
+            env: None,
+            resources: None,
+            startup_probe: None,
+            liveness_probe: None,
+            readiness_probe: None,
+            ..HoprdDeploymentSpec::default()
+        });
+        if hoprd_deployment_spec.topology_spread_constraints.is_none() {
+            let zone_spread_constraint = TopologySpreadConstraint {
+                max_skew: 1,
+                topology_key: constants::LABEL_KUBERNETES_TOPOLOGY_ZONE.to_owned(),
+                when_unsatisfiable: "ScheduleAnyway".to_owned(),
+                label_selector: Some(LabelSelector {
+                    match_labels: Some(BTreeMap::from([(constants::LABEL_NODE_CLUSTER.to_owned(), cluster_name.to_owned())])),
+                    ..LabelSelector::default()
+                }),
+                ..TopologySpreadConstraint::default()
+            };
+            hoprd_deployment_spec.topology_spread_constraints = Some(serde_yml::to_string(&vec![zone_spread_constraint])?);
+        }
+        Ok(hoprd_deployment_spec)
+    }
+
+    pub fn get_topology_spread_constraints(hoprd_deployment_spec: Option<&HoprdDeploymentSpec>) -> Result<Option<Vec<TopologySpreadConstraint>>, Error> {
+        Ok(hoprd_deployment_spec.and_then(|spec| spec.topology_spread_constraints.as_deref()).map(serde_yml::from_str).transpose()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_context_defaults_and_overrides() {
+        let pod_security_context = HoprdDeploymentSpec::get_pod_security_context(None).unwrap();
+        assert_eq!(pod_security_context.run_as_non_root, Some(true));
+        assert_eq!(pod_security_context.fs_group, Some(constants::HOPRD_POD_USER_ID));
+        let security_context = HoprdDeploymentSpec::get_security_context(None).unwrap();
+        assert_eq!(security_context.read_only_root_filesystem, Some(true));
+        assert_eq!(security_context.capabilities.unwrap().drop, Some(vec!["ALL".to_owned()]));
+
+        let hoprd_deployment_spec = HoprdDeploymentSpec {
+            pod_security_context: Some("runAsUser: 2000\nfsGroup: 2000\n".to_owned()),
+            security_context: Some("readOnlyRootFilesystem: false\n".to_owned()),
+            ..HoprdDeploymentSpec::default()
+        };
+        let pod_security_context = HoprdDeploymentSpec::get_pod_security_context(Some(&hoprd_deployment_spec)).unwrap();
+        assert_eq!(pod_security_context.run_as_user, Some(2000));
+        assert_eq!(pod_security_context.run_as_non_root, None);
+        assert_eq!(pod_security_context.fs_group, Some(2000));
+        // The identity file mounted read-only for its group stays readable without fsGroup in the override
+        let without_fs_group = HoprdDeploymentSpec {
+            pod_security_context: Some("runAsUser: 2000\nrunAsGroup: 2000\n".to_owned()),
+            ..HoprdDeploymentSpec::default()
+        };
+        let pod_security_context = HoprdDeploymentSpec::get_pod_security_context(Some(&without_fs_group)).unwrap();
+        assert_eq!(pod_security_context.run_as_user, Some(2000));
+        assert_eq!(pod_security_context.fs_group, Some(constants::HOPRD_POD_USER_ID));
+        assert_eq!(pod_security_context.fs_group_change_policy.as_deref(), Some("OnRootMismatch"));
+        let security_context = HoprdDeploymentSpec::get_security_context(Some(&hoprd_deployment_spec)).unwrap();
+        assert_eq!(security_context.read_only_root_filesystem, Some(false));
+    }
+
+    #[test]
+    fn test_cluster_nodes_zone_spread_by_default() {
+        let hoprd_deployment_spec = HoprdDeploymentSpec::for_cluster_nodes(None, "core-rotsee").unwrap();
+        let constraints = HoprdDeploymentSpec::get_topology_spread_constraints(Some(&hoprd_deployment_spec)).unwrap().unwrap();
+        assert_eq!(constraints.len(), 1);
+        assert_eq!(constraints[0].topology_key, constants::LABEL_KUBERNETES_TOPOLOGY_ZONE);
+        assert_eq!(constraints[0].when_unsatisfiable, "ScheduleAnyway");
+        let match_labels = constraints[0].label_selector.as_ref().and_then(|selector| selector.match_labels.clone()).unwrap();
+        assert_eq!(match_labels.get(constants::LABEL_NODE_CLUSTER).map(String::as_str), Some("core-rotsee"));
+        // The defaults are left to the nodes, which depend on whether they source the logs snapshot
+        assert_eq!(hoprd_deployment_spec.resources, None);
+        assert_eq!(hoprd_deployment_spec.env, None);
+        assert_eq!(hoprd_deployment_spec.startup_probe, None);
+        assert_eq!(HoprdDeploymentSpec::get_startup_probe(Some(hoprd_deployment_spec), true), Some(HoprdDeploymentSpec::default_startup_probe(true)));
+    }
+
+    #[test]
+    fn test_cluster_nodes_scheduling_passthrough() {
+        let topology_spread_constraints = "- maxSkew: 2\n  topologyKey: kubernetes.io/hostname\n  whenUnsatisfiable: DoNotSchedule\n".to_owned();
+        let cluster_deployment_spec = HoprdDeploymentSpec {
+            node_selector: Some(BTreeMap::from([("pool".to_owned(), "hoprd".to_owned())])),
+            tolerations: Some("- key: dedicated\n  operator: Equal\n  value: hoprd\n  effect: NoSchedule\n".to_owned()),
+            affinity: Some("nodeAffinity: {}\n".to_owned()),
+            topology_spread_constraints: Some(topology_spread_constraints.clone()),
+            priority_class_name: Some("hoprd-critical".to_owned()),
+            resources: None,
+            ..HoprdDeploymentSpec::default()
+        };
+        let hoprd_deployment_spec = HoprdDeploymentSpec::for_cluster_nodes(Some(cluster_deployment_spec.clone()), "core-rotsee").unwrap();
+        assert_eq!(hoprd_deployment_spec, cluster_deployment_spec);
+        assert_eq!(hoprd_deployment_spec.topology_spread_constraints, Some(topology_spread_constraints));
+        assert_eq!(HoprdDeploymentSpec::get_tolerations(Some(&hoprd_deployment_spec)).unwrap().unwrap()[0].key.as_deref(), Some("dedicated"));
+
+        let without_constraints = HoprdDeploymentSpec {
+            topology_spread_constraints: None,
+            ..cluster_deployment_spec
+        };
+        let hoprd_deployment_spec = HoprdDeploymentSpec::for_cluster_nodes(Some(without_constraints.clone()), "core-rotsee").unwrap();
+        assert_eq!(hoprd_deployment_spec.node_selector, without_constraints.node_selector);
+        assert_eq!(hoprd_deployment_spec.priority_class_name, without_constraints.priority_class_name);
+        assert_eq!(hoprd_deployment_spec.resources, None);
+        assert!(hoprd_deployment_spec.topology_spread_constraints.unwrap().contains(constants::LABEL_KUBERNETES_TOPOLOGY_ZONE));
+    }
 }
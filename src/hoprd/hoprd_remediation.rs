@@ -0,0 +1,266 @@
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::{Pod, Probe};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Policy applied to nodes whose pod is crash-looping or does not become ready
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdRemediationSpec {
+    /// Whether the operator remediates the node. Disabled by default
+    pub enabled: Option<bool>,
+    /// Seconds the pod can stay unready after the hoprd container started before it is remediated. Defaults to the startup probe window plus 600
+    pub unready_timeout: Option<u64>,
+    /// Minimum seconds between two remediation actions on the node
+    pub min_interval: Option<u64>,
+    /// Pod restarts attempted before resetting the database
+    pub max_restarts: Option<u32>,
+    /// Whether the database of the node is reset once the pod restarts did not help
+    pub reset_database: Option<bool>,
+}
+
+impl HoprdRemediationSpec {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    /// The default leaves the startup probe the whole of its window, as the node is unready while it syncs
+    pub fn get_unready_timeout(&self, startup_window: u64) -> u64 {
+        self.unready_timeout.unwrap_or(startup_window + 600)
+    }
+
+    pub fn get_min_interval(&self) -> u64 {
+        self.min_interval.unwrap_or(900)
+    }
+
+    /// Escalates from restarting the pod to resetting the database, and marks the node as failed when nothing else is left
+    pub fn next_action(&self, attempts: u32) -> HoprdRemediationActionEnum {
+        let max_restarts = self.max_restarts.unwrap_or(2);
+        if attempts < max_restarts {
+            HoprdRemediationActionEnum::RestartPod
+        } else if attempts == max_restarts && self.reset_database.unwrap_or(true) {
+            HoprdRemediationActionEnum::ResetDatabase
+        } else {
+            HoprdRemediationActionEnum::MarkFailed
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Copy)]
+pub enum HoprdRemediationActionEnum {
+    RestartPod,
+    ResetDatabase,
+    MarkFailed,
+}
+
+impl Display for HoprdRemediationActionEnum {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            HoprdRemediationActionEnum::RestartPod => write!(f, "RestartPod"),
+            HoprdRemediationActionEnum::ResetDatabase => write!(f, "ResetDatabase"),
+            HoprdRemediationActionEnum::MarkFailed => write!(f, "MarkFailed"),
+        }
+    }
+}
+
+/// Outcome of checking the pod of a node with remediation enabled
+#[derive(Debug, PartialEq, Clone)]
+pub enum RemediationDecision {
+    /// Nothing to do, including while a remediated pod is starting again
+    Keep,
+    /// The pod has been ready for `minInterval` after the last remediation, which clears the attempts
+    Recovered,
+    /// The pod has a problem, but the last remediation is too recent
+    Wait(PodProblem),
+    /// The pod has a problem and the next action of the escalation is applied
+    Remediate(HoprdRemediationActionEnum, PodProblem),
+}
+
+impl HoprdRemediationSpec {
+    /// Decides how to remediate the pod. The attempts are kept until the pod is ready for a stable period,
+    /// as a restarted pod is starting and therefore has no problem yet on the first checks after the restart
+    pub fn decide(&self, pod: &Pod, startup_window: u64, attempts: u32, last_remediation: Option<DateTime<Utc>>, now: DateTime<Utc>) -> RemediationDecision {
+        match detect_problem(pod, self.get_unready_timeout(startup_window), now) {
+            None if attempts > 0 && is_ready_for(pod, self.get_min_interval(), now) => RemediationDecision::Recovered,
+            None => RemediationDecision::Keep,
+            Some(problem) if last_remediation.is_some_and(|time| (now - time).num_seconds() < self.get_min_interval() as i64) => RemediationDecision::Wait(problem),
+            Some(problem) => RemediationDecision::Remediate(self.next_action(attempts), problem),
+        }
+    }
+}
+
+/// Longest time the probe lets the container start, in seconds
+pub fn probe_window(probe: &Probe) -> u64 {
+    let initial_delay = probe.initial_delay_seconds.unwrap_or(0).max(0) as u64;
+    initial_delay + probe.failure_threshold.unwrap_or(3).max(1) as u64 * probe.period_seconds.unwrap_or(10).max(1) as u64
+}
+
+/// Whether the pod has been ready for at least the given seconds
+fn is_ready_for(pod: &Pod, seconds: u64, now: DateTime<Utc>) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|pod_status| pod_status.conditions.as_ref())
+        .and_then(|conditions| conditions.iter().find(|condition| condition.type_ == "Ready"))
+        .filter(|condition| condition.status == "True")
+        .and_then(|condition| condition.last_transition_time.as_ref())
+        .is_some_and(|ready_since| (now - ready_since.0).num_seconds() >= seconds as i64)
+}
+
+/// Remediation action taken on a node, kept in its status
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdRemediation {
+    /// Time of the action, in RFC 3339 format
+    pub time: String,
+    pub action: HoprdRemediationActionEnum,
+    /// Problem detected on the pod: `CrashLoopBackOff` or `Unready`
+    pub reason: String,
+    pub message: Option<String>,
+}
+
+/// Problem detected on the pod of a node
+#[derive(Debug, PartialEq, Clone)]
+pub struct PodProblem {
+    pub reason: String,
+    pub message: String,
+}
+
+impl Display for PodProblem {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.reason, self.message)
+    }
+}
+
+/// Looks for a crash-looping hoprd container or a pod that has not been ready for longer than the timeout.
+/// The time unready is only counted once the hoprd container started, so the init containers, like the snapshot download, are not counted
+pub fn detect_problem(pod: &Pod, unready_timeout: u64, now: DateTime<Utc>) -> Option<PodProblem> {
+    let pod_status = pod.status.as_ref()?;
+    let hoprd_status = pod_status.container_statuses.as_ref().and_then(|container_statuses| container_statuses.iter().find(|container_status| container_status.name == "hoprd"));
+    if let Some(hoprd_status) = hoprd_status {
+        let waiting_reason = hoprd_status.state.as_ref().and_then(|state| state.waiting.as_ref()).and_then(|waiting| waiting.reason.clone());
+        if waiting_reason.as_deref() == Some("CrashLoopBackOff") {
+            let message = hoprd_status
+                .last_state
+                .as_ref()
+                .and_then(|last_state| last_state.terminated.as_ref())
+                .map(|terminated| terminated.message.clone().unwrap_or(format!("exited with code {}", terminated.exit_code)))
+                .unwrap_or("no termination message".to_owned());
+            return Some(PodProblem {
+                reason: "CrashLoopBackOff".to_owned(),
+                message,
+            });
+        }
+    }
+    let hoprd_status = hoprd_status.filter(|hoprd_status| hoprd_status.started == Some(true))?;
+    let ready_condition = pod_status.conditions.as_ref()?.iter().find(|condition| condition.type_ == "Ready")?;
+    let started_at = hoprd_status.state.as_ref().and_then(|state| state.running.as_ref()).and_then(|running| running.started_at.as_ref()).map(|started_at| started_at.0);
+    let unready_since = ready_condition.last_transition_time.as_ref()?.0.max(started_at.unwrap_or(DateTime::<Utc>::MIN_UTC));
+    if ready_condition.status != "True" && (now - unready_since).num_seconds() >= unready_timeout as i64 {
+        return Some(PodProblem {
+            reason: "Unready".to_owned(),
+            message: format!("not ready since {}", unready_since.to_rfc3339()),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hoprd::hoprd_deployment_spec::HoprdDeploymentSpec;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn pod(hoprd_state: serde_json::Value, ready: &str, since: DateTime<Utc>) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": "hoprd-1" },
+            "status": {
+                "conditions": [{ "type": "Ready", "status": ready, "lastTransitionTime": since.to_rfc3339() }],
+                "containerStatuses": [{
+                    "name": "hoprd",
+                    "image": "hoprd",
+                    "imageID": "",
+                    "ready": false,
+                    "started": true,
+                    "restartCount": 5,
+                    "state": hoprd_state,
+                    "lastState": { "terminated": { "exitCode": 1, "message": "Database is corrupted" } }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_detect_problem() {
+        let now = Utc::now();
+        let crash_looping = pod(json!({ "waiting": { "reason": "CrashLoopBackOff" } }), "False", now);
+        assert_eq!(detect_problem(&crash_looping, 600, now).unwrap().message, "Database is corrupted");
+        let starting = pod(json!({ "running": {} }), "False", now - Duration::seconds(60));
+        assert_eq!(detect_problem(&starting, 600, now), None);
+        let stuck = pod(json!({ "running": {} }), "False", now - Duration::seconds(900));
+        assert_eq!(detect_problem(&stuck, 600, now).unwrap().reason, "Unready");
+        let ready = pod(json!({ "running": {} }), "True", now - Duration::seconds(900));
+        assert_eq!(detect_problem(&ready, 600, now), None);
+
+        // The init containers and the startup probe are not counted
+        let mut not_started = stuck.clone();
+        not_started.status.as_mut().unwrap().container_statuses.as_mut().unwrap()[0].started = Some(false);
+        assert_eq!(detect_problem(&not_started, 600, now), None);
+        let recently_started = pod(json!({ "running": { "startedAt": (now - Duration::seconds(60)).to_rfc3339() } }), "False", now - Duration::seconds(3600));
+        assert_eq!(detect_problem(&recently_started, 600, now), None);
+    }
+
+    #[test]
+    fn test_unready_timeout_covers_startup_probe() {
+        let remediation = HoprdRemediationSpec::default();
+        let startup_window = probe_window(&HoprdDeploymentSpec::default_startup_probe(false));
+        assert_eq!(startup_window, 900);
+        assert_eq!(remediation.get_unready_timeout(startup_window), 1500);
+        assert_eq!(remediation.get_unready_timeout(probe_window(&HoprdDeploymentSpec::default_startup_probe(true))), 4200);
+        let explicit = HoprdRemediationSpec { unready_timeout: Some(300), ..HoprdRemediationSpec::default() };
+        assert_eq!(explicit.get_unready_timeout(startup_window), 300);
+    }
+
+    #[test]
+    fn test_remediation_escalation() {
+        let remediation = HoprdRemediationSpec::default();
+        assert_eq!(remediation.next_action(0), HoprdRemediationActionEnum::RestartPod);
+        assert_eq!(remediation.next_action(2), HoprdRemediationActionEnum::ResetDatabase);
+        assert_eq!(remediation.next_action(3), HoprdRemediationActionEnum::MarkFailed);
+        let without_reset = HoprdRemediationSpec { reset_database: Some(false), ..HoprdRemediationSpec::default() };
+        assert_eq!(without_reset.next_action(2), HoprdRemediationActionEnum::MarkFailed);
+    }
+
+    #[test]
+    fn test_escalation_continues_while_restarted_pod_starts() {
+        let remediation = HoprdRemediationSpec { enabled: Some(true), ..HoprdRemediationSpec::default() };
+        let startup_window = 900;
+        let start = Utc::now();
+        let stuck = pod(json!({ "running": {} }), "False", start - Duration::seconds(2000));
+        let RemediationDecision::Remediate(action, _) = remediation.decide(&stuck, startup_window, 0, None, start) else {
+            panic!("The stuck pod is not remediated");
+        };
+        assert_eq!(action, HoprdRemediationActionEnum::RestartPod);
+
+        // The restarted pod is starting, which is not a recovery
+        let starting = pod(json!({ "running": {} }), "False", start + Duration::seconds(30));
+        assert_eq!(remediation.decide(&starting, startup_window, 1, Some(start), start + Duration::seconds(60)), RemediationDecision::Keep);
+        // A pod that was ready only briefly does not clear the attempts either
+        let briefly_ready = pod(json!({ "running": {} }), "True", start + Duration::seconds(120));
+        assert_eq!(remediation.decide(&briefly_ready, startup_window, 1, Some(start), start + Duration::seconds(180)), RemediationDecision::Keep);
+
+        // Still unready after the timeout, the escalation goes on from the kept attempts
+        let now = start + Duration::seconds(2000);
+        let still_unready = pod(json!({ "running": {} }), "False", start + Duration::seconds(30));
+        assert!(matches!(remediation.decide(&still_unready, startup_window, 1, Some(start), now), RemediationDecision::Remediate(HoprdRemediationActionEnum::RestartPod, _)));
+        let now = start + Duration::seconds(4000);
+        assert!(matches!(remediation.decide(&still_unready, startup_window, 2, Some(start + Duration::seconds(2000)), now), RemediationDecision::Remediate(HoprdRemediationActionEnum::ResetDatabase, _)));
+        assert!(matches!(remediation.decide(&still_unready, startup_window, 2, Some(now - Duration::seconds(60)), now), RemediationDecision::Wait(_)));
+
+        // Ready for the minimum interval clears the attempts
+        let ready = pod(json!({ "running": {} }), "True", now - Duration::seconds(900));
+        assert_eq!(remediation.decide(&ready, startup_window, 3, Some(start), now), RemediationDecision::Recovered);
+    }
+}
@@ -2,22 +2,38 @@ use crate::cluster::cluster_hoprd::{ClusterHoprd, ClusterHoprdPhaseEnum};
 use crate::events::{ClusterHoprdEventEnum, HoprdEventEnum, IdentityHoprdEventEnum, IdentityPoolEventEnum};
 use crate::identity_hoprd::identity_hoprd_resource::{IdentityHoprd, IdentityHoprdPhaseEnum};
 use crate::identity_pool::identity_pool_resource::{IdentityPool, IdentityPoolPhaseEnum};
+use crate::image_registry::{self, ImageReference};
 use crate::model::Error;
+use crate::operator_config::{HealthConfig, ImagesConfig};
 use crate::resource_generics;
 use crate::{
     constants,
     context_data::ContextData,
-    hoprd::{hoprd_deployment, hoprd_deployment_spec::HoprdDeploymentSpec, hoprd_ingress, hoprd_service, hoprd_service::HoprdServiceSpec},
+    hoprd::{
+        hoprd_api::{HoprdApiClient, HoprdBalances},
+        hoprd_config::{self, ConfigVariables, HoprdConfigRef},
+        hoprd_deployment,
+        hoprd_deployment_spec::HoprdDeploymentSpec,
+        hoprd_ingress,
+        hoprd_remediation::{self, HoprdRemediation, HoprdRemediationActionEnum, HoprdRemediationSpec, RemediationDecision},
+        hoprd_service,
+        hoprd_service::{HoprdServiceSpec, ServiceTypeEnum},
+        hoprd_snapshot::{self, HoprdSnapshotSpec, HoprdSnapshotStatus},
+    },
+    utils,
 };
 use futures::{StreamExt, TryStreamExt};
 use k8s_openapi::api::apps::v1::Deployment;
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use chrono::{DateTime, SecondsFormat, Utc};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Pod, Secret, Service};
+use k8s_openapi::api::networking::v1::Ingress;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, OwnerReference};
 use kube::api::WatchParams;
 use kube::core::object::HasSpec;
 use kube::core::{ObjectMeta, WatchEvent};
 use kube::Resource;
 use kube::{
-    api::{Api, Patch, PatchParams, ResourceExt},
+    api::{Api, DeleteParams, ListParams, Patch, PatchParams, ResourceExt},
     client::Client,
     runtime::controller::Action,
     CustomResource, Result,
@@ -42,7 +58,10 @@ pub struct HoprdSpec {
     pub identity_pool_name: String,
     pub identity_name: String,
     pub version: String,
+    pub image: Option<String>,
+    #[serde(default)]
     pub config: String,
+    pub config_ref: Option<HoprdConfigRef>,
     pub enabled: Option<bool>,
     pub delete_database: Option<bool>,
     pub service: HoprdServiceSpec,
@@ -50,6 +69,8 @@ pub struct HoprdSpec {
     pub profiling_enabled: Option<bool>,
     pub source_node_logs: Option<bool>,
     pub dns_name: Option<String>,
+    pub remediation: Option<HoprdRemediationSpec>,
+    pub snapshot: Option<HoprdSnapshotSpec>,
 }
 
 /// The status object of `Hoprd`
@@ -58,6 +79,42 @@ pub struct HoprdSpec {
 pub struct HoprdStatus {
     pub phase: HoprdPhaseEnum,
     pub observed_generation: i64,
+    /// Image run by the node, pinned to the digest the version resolved to
+    pub image: Option<String>,
+    /// Version resolved into the pinned image
+    pub version: Option<String>,
+    pub digest: Option<String>,
+    /// Time of the last phase change, in RFC 3339 format
+    pub last_transition_time: Option<String>,
+    /// Name of the identity locked by the node
+    pub identity_name: Option<String>,
+    pub node_address: Option<String>,
+    pub safe_address: Option<String>,
+    /// Host and port announced by the node to the p2p network
+    pub p2p_address: Option<String>,
+    /// Range of ports allocated to the node, including the p2p port
+    pub port_range: Option<String>,
+    pub session_port_range: Option<String>,
+    pub api_url: Option<String>,
+    /// Name of the most recent pod of the node deployment
+    pub pod_name: Option<String>,
+    /// Restarts of the hoprd container in the current pod
+    pub restart_count: Option<i32>,
+    /// Network health reported by the node API
+    pub network_health: Option<String>,
+    pub connected_peers: Option<i32>,
+    pub incoming_channels: Option<i32>,
+    pub outgoing_channels: Option<i32>,
+    pub balances: Option<HoprdBalances>,
+    pub conditions: Option<Vec<Condition>>,
+    /// Remediation actions taken since the pod was last healthy
+    pub remediation_attempts: Option<u32>,
+    /// Latest remediation actions taken on the node
+    pub remediations: Option<Vec<HoprdRemediation>>,
+    /// Progress of the logs snapshot download of the current pod
+    pub snapshot: Option<HoprdSnapshotStatus>,
+    /// Job deleting the database of the node, which stays scaled down until the job succeeds
+    pub database_reset_job: Option<String>,
 }
 
 impl Default for HoprdStatus {
@@ -65,6 +122,29 @@ impl Default for HoprdStatus {
         Self {
             phase: HoprdPhaseEnum::Initializing,
             observed_generation: 0,
+            image: None,
+            version: None,
+            digest: None,
+            last_transition_time: None,
+            identity_name: None,
+            node_address: None,
+            safe_address: None,
+            p2p_address: None,
+            port_range: None,
+            session_port_range: None,
+            api_url: None,
+            pod_name: None,
+            restart_count: None,
+            network_health: None,
+            connected_peers: None,
+            incoming_channels: None,
+            outgoing_channels: None,
+            balances: None,
+            conditions: None,
+            remediation_attempts: None,
+            remediations: None,
+            snapshot: None,
+            database_reset_job: None,
         }
     }
 }
@@ -81,6 +161,8 @@ pub enum HoprdPhaseEnum {
     Failed,
     /// Event that triggers when node is modified
     Modified,
+    /// The node is running but its network health stays below the configured threshold
+    Degraded,
     /// Event that triggers when node is being deleted
     Deleting,
 }
@@ -93,6 +175,7 @@ impl Display for HoprdPhaseEnum {
             HoprdPhaseEnum::Stopped => write!(f, "Stopped"),
             HoprdPhaseEnum::Modified => write!(f, "Modified"),
             HoprdPhaseEnum::Failed => write!(f, "Failed"),
+            HoprdPhaseEnum::Degraded => write!(f, "Degraded"),
             HoprdPhaseEnum::Deleting => write!(f, "Deleting"),
         }
     }
@@ -114,6 +197,16 @@ impl Hoprd {
         let client: Client = context_data.client.clone();
         let hoprd_namespace: String = self.namespace().unwrap();
         let hoprd_name: String = self.name_any();
+        // Nothing is created while the configuration is invalid, so the node is created again once it is fixed
+        let unknown_keys = match self.validate_config(client.clone()).await {
+            Ok(unknown_keys) => unknown_keys,
+            Err(Error::HoprdConfigError(problem)) => {
+                self.set_config_validity(context_data.clone(), Err(problem)).await?;
+                self.update_status(client.clone(), HoprdPhaseEnum::Failed).await?;
+                return Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY)));
+            }
+            Err(error) => return Err(error),
+        };
         context_data.send_event(self, HoprdEventEnum::Initializing, None).await;
         self.update_status(client.clone(), HoprdPhaseEnum::Initializing).await?;
         info!("Starting to create Hoprd node {hoprd_name} in namespace {hoprd_namespace}");
@@ -136,6 +229,7 @@ impl Hoprd {
             )
             .await?;
             let last_port: u16 = starting_port + session_ports_allocation;
+            self.resolve_image(context_data.clone(), false).await?;
             let hoprd_host = hoprd_service::create_service(
                 context_data.clone(),
                 &hoprd_name,
@@ -150,17 +244,21 @@ impl Hoprd {
             .await
             .unwrap();
             hoprd_deployment::create_deployment(context_data.clone(), self, &identity, &hoprd_host, starting_port, last_port).await?;
+            self.set_config_validity(context_data.clone(), Ok(unknown_keys)).await?;
+            self.propagate_labels(context_data.clone(), &identity).await?;
             self.wait_deployment(client.clone()).await?;
 
             self.set_running_status(context_data.clone()).await?;
-            let api: Api<Hoprd> = Api::namespaced(client.clone(), &hoprd_namespace.to_owned());
+            // The mutating webhook sets it on admission, this covers the nodes admitted without the webhook
             if self.spec_mut().delete_database.is_none() {
+                let api: Api<Hoprd> = Api::namespaced(client.clone(), &hoprd_namespace.to_owned());
                 let patch = Patch::Merge(json!({ "spec": { "deleteDatabase": false } }));
                 match api.patch(&hoprd_name, &PatchParams::default(), &patch).await {
                     Ok(_) => {}
                     Err(error) => error!("Could not update the deleteDatabase field {hoprd_name}: {:?}", error),
                 };
             }
+            self.update_runtime_status(context_data.clone()).await?;
             info!("Hoprd node {hoprd_name} in namespace {hoprd_namespace} has been successfully created");
         } else {
             context_data.send_event(self, HoprdEventEnum::Failed, None).await;
@@ -226,10 +324,25 @@ impl Hoprd {
     async fn apply_modification(&mut self, context_data: Arc<ContextData>, identity: &IdentityHoprd) -> Result<(), Error> {
         let hoprd_namespace: String = self.namespace().unwrap();
         let hoprd_name: String = self.name_any();
-        hoprd_deployment::modify_deployment(context_data.clone(), &hoprd_name.to_owned(), &hoprd_namespace.to_owned(), &self.spec.to_owned(), identity).await?;
+        self.resolve_image(context_data.clone(), false).await?;
+        match hoprd_deployment::modify_deployment(context_data.clone(), self, identity).await {
+            Err(Error::HoprdConfigError(problem)) => {
+                // The deployment keeps running the previous configuration until the spec is fixed
+                self.set_config_validity(context_data.clone(), Err(problem)).await?;
+                let phase = self.status.as_ref().map_or(HoprdPhaseEnum::Running, |status| status.phase);
+                return self.update_status(context_data.client.clone(), phase).await;
+            }
+            result => result?,
+        }
+        let unknown_keys = self.validate_config(context_data.client.clone()).await?;
+        self.set_config_validity(context_data.clone(), Ok(unknown_keys)).await?;
+        self.propagate_labels(context_data.clone(), identity).await?;
         if self.spec_mut().delete_database.unwrap_or(false) {
-            info!("Deleting database for Hoprd node {hoprd_name} in namespace {hoprd_namespace}");
-            hoprd_deployment::delete_database(context_data.clone(), &hoprd_name.to_owned(), &hoprd_namespace.to_owned()).await?;
+            if self.status.as_ref().is_none_or(|status| status.database_reset_job.is_none()) {
+                info!("Deleting database for Hoprd node {hoprd_name} in namespace {hoprd_namespace}");
+                let job_name = hoprd_deployment::start_database_reset(context_data.clone(), self).await?;
+                self.set_database_reset_job(context_data.client.clone(), Some(job_name)).await?;
+            }
             let client: Client = context_data.client.clone();
             let api: Api<Hoprd> = Api::namespaced(client.clone(), &hoprd_namespace.to_owned());
             let patch = Patch::Merge(json!({ "spec": { "deleteDatabase": false } }));
@@ -238,17 +351,416 @@ impl Hoprd {
                 Err(error) => error!("Could not update the deleteDatabaseField on {hoprd_name}: {:?}", error),
             };
         }
+        if self.status.as_ref().is_some_and(|status| status.database_reset_job.is_some()) {
+            // The deployment is scaled up once the database reset finishes
+            info!("Hoprd node {hoprd_name} in namespace {hoprd_namespace} has been modified and is resetting its database");
+            context_data.send_event(self, HoprdEventEnum::Modified, None).await;
+            return self.set_running_status(context_data.clone()).await;
+        }
         match self.wait_deployment(context_data.client.clone()).await {
             Ok(()) => {
                 info!("Hoprd node {hoprd_name} in namespace {hoprd_namespace} has been successfully modified");
                 context_data.send_event(self, HoprdEventEnum::Modified, None).await;
                 self.set_running_status(context_data.clone()).await?;
-                Ok(())
+                self.update_runtime_status(context_data.clone()).await
             }
             Err(_) => Ok(warn!("Error waiting for deployment of {hoprd_name} to become ready")),
         }
     }
 
+    /// Refreshes the runtime information of the node, like the pod restarts, while it is in the desired state
+    pub async fn sync_status(&mut self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        if self.sync_database_reset(context_data.clone()).await? {
+            return Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)));
+        }
+        self.update_runtime_status(context_data.clone()).await?;
+        self.remediate(context_data.clone()).await?;
+        let status = self.status.clone().unwrap_or_default();
+        if status.phase.eq(&HoprdPhaseEnum::Running) || status.phase.eq(&HoprdPhaseEnum::Degraded) {
+            if let Some(identity) = self.get_identity(context_data.client.clone()).await? {
+                let validation = match hoprd_deployment::sync_checksum(context_data.clone(), self, &identity).await {
+                    Ok(rolled) => {
+                        if rolled {
+                            context_data.send_event(self, HoprdEventEnum::Modified, None).await;
+                        }
+                        Ok(self.validate_config(context_data.client.clone()).await?)
+                    }
+                    Err(Error::HoprdConfigError(problem)) => Err(problem),
+                    Err(error) => return Err(error),
+                };
+                self.set_config_validity(context_data.clone(), validation).await?;
+            }
+        }
+        match health_transition(status.phase, self.is_degraded(&context_data.config.health)) {
+            Some(HoprdPhaseEnum::Degraded) => {
+                warn!("Hoprd node {} reports a {} network health", self.name_any(), status.network_health.clone().unwrap_or_default());
+                context_data.send_event(self, HoprdEventEnum::Degraded, status.network_health.clone()).await;
+                self.update_status(context_data.client.clone(), HoprdPhaseEnum::Degraded).await?;
+            }
+            Some(_) => {
+                info!("Hoprd node {} recovered its network health", self.name_any());
+                self.set_running_status(context_data.clone()).await?;
+            }
+            None => {}
+        }
+        Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+    }
+
+    /// Records in the status the identity, addresses, ports and pod of the node, reading them from the managed resources
+    async fn update_runtime_status(&mut self, context_data: Arc<ContextData>) -> Result<(), Error> {
+        let client: Client = context_data.client.clone();
+        let hoprd_namespace: String = self.namespace().unwrap();
+        let hoprd_name: String = self.name_any();
+        let mut status = self.status.clone().unwrap_or_default();
+        if let Some(identity) = self.get_identity(client.clone()).await? {
+            status.identity_name = Some(identity.name_any());
+            status.node_address = Some(identity.spec.node_address.to_owned());
+            status.safe_address = Some(identity.spec.safe_address.to_owned());
+        }
+        let deployment_api: Api<Deployment> = Api::namespaced(client.clone(), &hoprd_namespace);
+        if let Some(deployment) = deployment_api.get_opt(&hoprd_name).await? {
+            self.read_deployment_status(&deployment, &mut status);
+            let pod_api: Api<Pod> = Api::namespaced(client.clone(), &hoprd_namespace);
+            let selector = deployment
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.selector.match_labels.as_ref())
+                .map(|labels| labels.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<String>>().join(","))
+                .unwrap_or_default();
+            let pods = pod_api.list(&ListParams::default().labels(&selector)).await?;
+            read_pod_status(pods.items.iter().max_by_key(|pod| pod.metadata.creation_timestamp.clone()), &mut status);
+        }
+        if self.spec.enabled.unwrap_or(true) && status.pod_name.is_some() {
+            self.update_node_state(context_data.clone(), &mut status).await?;
+        }
+        let ingress_api: Api<Ingress> = Api::namespaced(client.clone(), &hoprd_namespace);
+        status.api_url = ingress_api
+            .get_opt(&hoprd_name)
+            .await?
+            .and_then(|ingress| ingress.spec)
+            .and_then(|spec| spec.rules)
+            .and_then(|rules| rules.first().and_then(|rule| rule.host.clone()))
+            .map(|host| format!("https://{}", host));
+        if self.status.as_ref() != Some(&status) {
+            let api: Api<Hoprd> = Api::namespaced(client, &hoprd_namespace);
+            if let Err(error) = api.patch_status(&hoprd_name, &PatchParams::default(), &Patch::Merge(runtime_status_patch(&status))).await {
+                error!("Could not record the runtime status of Hoprd node {hoprd_name}: {:?}", error);
+            }
+            self.status = Some(status);
+        }
+        Ok(())
+    }
+
+    /// Records the announced address and the port ranges of the node, as set in the environment of its deployment
+    fn read_deployment_status(&self, deployment: &Deployment, status: &mut HoprdStatus) {
+        let hoprd_env = deployment
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.template.spec.as_ref())
+            .and_then(|pod_spec| pod_spec.containers.iter().find(|container| container.name == "hoprd"))
+            .and_then(|container| container.env.clone())
+            .unwrap_or_default();
+        let env_value = |name: &str| hoprd_env.iter().find(|env_var| env_var.name == name).and_then(|env_var| env_var.value.clone());
+        status.p2p_address = env_value(constants::HOPRD_HOST);
+        status.session_port_range = env_value(constants::HOPRD_SESSION_PORT_RANGE);
+        status.port_range = status
+            .p2p_address
+            .as_ref()
+            .and_then(|p2p_address| p2p_address.rsplit_once(':'))
+            .and_then(|(_, port)| port.parse::<u16>().ok())
+            .map(|starting_port| format!("{}:{}", starting_port, starting_port + self.spec.service.ports_allocation));
+    }
+
+    /// Remediates a crash-looping or unready pod, escalating from restarting the pod to resetting the database and finally marking the node as failed
+    async fn remediate(&mut self, context_data: Arc<ContextData>) -> Result<(), Error> {
+        let remediation = self.spec.remediation.clone().unwrap_or_default();
+        let mut status = self.status.clone().unwrap_or_default();
+        if !remediation.is_enabled() || !(status.phase.eq(&HoprdPhaseEnum::Running) || status.phase.eq(&HoprdPhaseEnum::Degraded)) {
+            return Ok(());
+        }
+        let Some(pod_name) = status.pod_name.clone() else {
+            return Ok(());
+        };
+        let hoprd_namespace: String = self.namespace().unwrap();
+        let hoprd_name: String = self.name_any();
+        let pod_api: Api<Pod> = Api::namespaced(context_data.client.clone(), &hoprd_namespace);
+        let Some(pod) = pod_api.get_opt(&pod_name).await? else {
+            return Ok(());
+        };
+        let attempts = status.remediation_attempts.unwrap_or(0);
+        let now = Utc::now();
+        let last_remediation = status
+            .remediations
+            .as_ref()
+            .and_then(|remediations| remediations.last())
+            .and_then(|remediation| DateTime::parse_from_rfc3339(&remediation.time).ok())
+            .map(|time| time.with_timezone(&Utc));
+        let startup_probe = HoprdDeploymentSpec::get_startup_probe(self.spec.deployment.clone(), self.spec.source_node_logs.unwrap_or(false));
+        let startup_window = startup_probe.as_ref().map_or(0, hoprd_remediation::probe_window);
+        match remediation.decide(&pod, startup_window, attempts, last_remediation, now) {
+            RemediationDecision::Keep => return Ok(()),
+            RemediationDecision::Recovered => {
+                info!("Hoprd node {hoprd_name} is healthy again after {attempts} remediation actions");
+                status.remediation_attempts = Some(0);
+            }
+            RemediationDecision::Wait(problem) => {
+                debug!("Hoprd node {hoprd_name} has {problem}, waiting before the next remediation");
+                return Ok(());
+            }
+            RemediationDecision::Remediate(action, problem) => {
+                warn!("Hoprd node {hoprd_name} has {problem}, applying remediation {action}");
+                context_data.send_event(self, HoprdEventEnum::Remediating, Some(format!("{} after {}", action, problem))).await;
+                match action {
+                    HoprdRemediationActionEnum::RestartPod => {
+                        pod_api.delete(&pod_name, &DeleteParams::default()).await?;
+                    }
+                    HoprdRemediationActionEnum::ResetDatabase => {
+                        status.database_reset_job = Some(hoprd_deployment::start_database_reset(context_data.clone(), self).await?);
+                    }
+                    HoprdRemediationActionEnum::MarkFailed => {
+                        context_data.send_event(self, HoprdEventEnum::Failed, None).await;
+                        self.update_status(context_data.client.clone(), HoprdPhaseEnum::Failed).await?;
+                        status.phase = HoprdPhaseEnum::Failed;
+                    }
+                }
+                let mut remediations = status.remediations.clone().unwrap_or_default();
+                remediations.push(HoprdRemediation {
+                    time: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    action,
+                    reason: problem.reason,
+                    message: Some(problem.message),
+                });
+                if remediations.len() > constants::HOPRD_REMEDIATION_HISTORY_SIZE {
+                    remediations.drain(..remediations.len() - constants::HOPRD_REMEDIATION_HISTORY_SIZE);
+                }
+                status.remediations = Some(remediations);
+                status.remediation_attempts = Some(attempts + 1);
+            }
+        }
+        let api: Api<Hoprd> = Api::namespaced(context_data.client.clone(), &hoprd_namespace);
+        let patch = Patch::Merge(json!({
+            "status": {
+                "remediationAttempts": status.remediation_attempts,
+                "remediations": status.remediations,
+                "databaseResetJob": status.database_reset_job
+            }
+        }));
+        if let Err(error) = api.patch_status(&hoprd_name, &PatchParams::default(), &patch).await {
+            error!("Could not record the remediation of Hoprd node {hoprd_name}: {:?}", error);
+        }
+        self.status = Some(status);
+        Ok(())
+    }
+
+    /// Follows the job resetting the database of the node. Returns whether the reset is still in progress
+    async fn sync_database_reset(&mut self, context_data: Arc<ContextData>) -> Result<bool, Error> {
+        let Some(job_name) = self.status.as_ref().and_then(|status| status.database_reset_job.clone()) else {
+            return Ok(false);
+        };
+        match hoprd_deployment::check_database_reset(context_data.clone(), self, &job_name).await {
+            Ok(false) => return Ok(true),
+            Ok(true) => info!("Database of Hoprd node {} has been reset", self.name_any()),
+            Err(Error::JobExecutionError(problem)) => {
+                error!("{problem}");
+                context_data.send_event(self, HoprdEventEnum::DatabaseResetFailed, Some(job_name)).await;
+            }
+            Err(error) => return Err(error),
+        }
+        self.set_database_reset_job(context_data.client.clone(), None).await?;
+        Ok(false)
+    }
+
+    async fn set_database_reset_job(&mut self, client: Client, job_name: Option<String>) -> Result<(), Error> {
+        let api: Api<Hoprd> = Api::namespaced(client, &self.namespace().unwrap());
+        let patch = Patch::Merge(json!({ "status": { "databaseResetJob": job_name } }));
+        api.patch_status(&self.name_any(), &PatchParams::default(), &patch).await?;
+        let mut status = self.status.clone().unwrap_or_default();
+        status.database_reset_job = job_name;
+        self.status = Some(status);
+        Ok(())
+    }
+
+    /// The node is degraded once its network health has been below the threshold, or could not be queried, for longer than the configured window
+    fn is_degraded(&self, health_config: &HealthConfig) -> bool {
+        self.status
+            .as_ref()
+            .and_then(|status| status.conditions.as_ref())
+            .and_then(|conditions| conditions.iter().find(|condition| condition.type_ == constants::CONDITION_NETWORK_HEALTHY))
+            .is_some_and(|condition| condition.status != "True" && (Utc::now() - condition.last_transition_time.0).num_seconds() >= health_config.degraded_window as i64)
+    }
+
+    /// Queries the node API and records its network health, peers, channels and balances as status fields and conditions
+    async fn update_node_state(&self, context_data: Arc<ContextData>, status: &mut HoprdStatus) -> Result<(), Error> {
+        let hoprd_namespace: String = self.namespace().unwrap();
+        let min_network_health = &context_data.config.health.min_network_health;
+        let secret_api: Api<Secret> = Api::namespaced(context_data.client.clone(), &hoprd_namespace);
+        let token = secret_api
+            .get_opt(&format!("{}-env-vars", self.spec.identity_pool_name))
+            .await?
+            .and_then(|secret| secret.data)
+            .and_then(|data| data.get(constants::HOPRD_API_TOKEN).cloned())
+            .and_then(|token| String::from_utf8(token.0).ok());
+        let service_name = if self.spec.service.r#type.eq(&ServiceTypeEnum::ClusterIP) {
+            self.name_any()
+        } else {
+            format!("{}-p2p-tcp", self.name_any())
+        };
+        let base_url = format!("http://{}.{}.svc:{}", service_name, hoprd_namespace, constants::HOPRD_API_PORT);
+        let generation = self.metadata.generation;
+        let mut conditions = status.conditions.clone().unwrap_or_default();
+        match HoprdApiClient::new(base_url, token)?.get_node_state().await {
+            Ok(node_state) => {
+                utils::set_condition(&mut conditions, constants::CONDITION_API_REACHABLE, "True", "Reachable", "The node API is answering".to_owned(), generation);
+                let (healthy, message) = if node_state.is_network_healthy(min_network_health) {
+                    ("True", format!("The node is connected to {} peers", node_state.connected_peers))
+                } else {
+                    ("False", format!("The node reports a {} network health, below {}", node_state.network_health, min_network_health))
+                };
+                set_network_healthy_condition(&mut conditions, healthy, &node_state.network_health, message, generation);
+                status.network_health = Some(node_state.network_health);
+                status.connected_peers = Some(node_state.connected_peers);
+                status.incoming_channels = Some(node_state.incoming_channels);
+                status.outgoing_channels = Some(node_state.outgoing_channels);
+                status.balances = Some(node_state.balances);
+            }
+            Err(error) => {
+                debug!("Could not query the API of Hoprd node {}: {}", self.name_any(), error);
+                utils::set_condition(&mut conditions, constants::CONDITION_API_REACHABLE, "False", "Unreachable", error.to_string(), generation);
+                set_network_healthy_condition(&mut conditions, "Unknown", "ApiUnreachable", "The network health cannot be queried".to_owned(), generation);
+            }
+        }
+        status.conditions = Some(conditions);
+        Ok(())
+    }
+
+    /// Renders the configuration with the variables known before the node is created and validates it, returning the keys unknown to hoprd
+    async fn validate_config(&self, client: Client) -> Result<Vec<String>, Error> {
+        let config = hoprd_config::get_config(client.clone(), self).await?;
+        let identity_pool_api: Api<IdentityPool> = Api::namespaced(client, &self.namespace().unwrap());
+        let network = identity_pool_api.get_opt(&self.spec.identity_pool_name).await?.map(|identity_pool| identity_pool.spec.network).unwrap_or_default();
+        let variables = ConfigVariables::before_creation(self.name_any(), self.namespace().unwrap(), network, self.spec.service.ports_allocation);
+        hoprd_config::validate(&hoprd_config::render(&config, &variables), &self.spec.version)
+    }
+
+    /// Records whether the configuration of the node is valid, raising an event when a new problem is found.
+    /// Unknown keys do not block the node, as hoprd ignores them, so they keep the condition true with the `UnknownKeys` reason
+    async fn set_config_validity(&mut self, context_data: Arc<ContextData>, validation: Result<Vec<String>, String>) -> Result<(), Error> {
+        let mut status = self.status.clone().unwrap_or_default();
+        let mut conditions = status.conditions.clone().unwrap_or_default();
+        let (valid, reason, message) = config_validity_condition(&validation);
+        let current = conditions.iter().find(|condition| condition.type_ == constants::CONDITION_CONFIG_VALID);
+        if current.is_some_and(|condition| condition.status == valid && condition.message == message) {
+            return Ok(());
+        }
+        match validation {
+            Err(problem) => {
+                warn!("Hoprd node {} has an invalid configuration: {}", self.name_any(), problem);
+                context_data.send_event(self, HoprdEventEnum::InvalidConfig, Some(problem)).await;
+            }
+            Ok(unknown_keys) if !unknown_keys.is_empty() => {
+                warn!("Hoprd node {} has unknown configuration keys: {}", self.name_any(), unknown_keys.join(", "));
+                context_data.send_event(self, HoprdEventEnum::UnknownConfigKeys, Some(unknown_keys.join(", "))).await;
+            }
+            Ok(_) => {}
+        }
+        utils::set_condition(&mut conditions, constants::CONDITION_CONFIG_VALID, valid, reason, message, self.metadata.generation);
+        status.conditions = Some(conditions);
+        let api: Api<Hoprd> = Api::namespaced(context_data.client.clone(), &self.namespace().unwrap());
+        let patch = Patch::Merge(json!({ "status": { "conditions": status.conditions } }));
+        if let Err(error) = api.patch_status(&self.name_any(), &PatchParams::default(), &patch).await {
+            error!("Could not record the configuration validity of Hoprd node {}: {:?}", self.name_any(), error);
+        }
+        self.status = Some(status);
+        Ok(())
+    }
+
+    /// Copies the user defined labels of the node into its services, ingress and database volume, removing the ones dropped since the previous configuration
+    async fn propagate_labels(&self, context_data: Arc<ContextData>, identity: &IdentityHoprd) -> Result<(), Error> {
+        let labels = utils::user_labels(self.labels());
+        let previous_labels = self
+            .annotations()
+            .get(constants::ANNOTATION_LAST_CONFIGURATION)
+            .and_then(|previous_hoprd_text| serde_json::from_str::<Hoprd>(previous_hoprd_text).ok())
+            .map(|previous_hoprd| utils::user_labels(previous_hoprd.labels()))
+            .unwrap_or_default();
+        if labels.is_empty() && previous_labels.is_empty() {
+            return Ok(());
+        }
+        let client: Client = context_data.client.clone();
+        let hoprd_namespace: String = self.namespace().unwrap();
+        let hoprd_name: String = self.name_any();
+        for service_name in [hoprd_name.to_owned(), format!("{}-p2p-tcp", hoprd_name), format!("{}-p2p-udp", hoprd_name)] {
+            resource_generics::update_labels::<Service>(client.clone(), &service_name, &hoprd_namespace, &labels, &previous_labels).await?;
+        }
+        resource_generics::update_labels::<Ingress>(client.clone(), &hoprd_name, &hoprd_namespace, &labels, &previous_labels).await?;
+        resource_generics::update_labels::<PersistentVolumeClaim>(client, &identity.name_any(), &hoprd_namespace, &labels, &previous_labels).await?;
+        Ok(())
+    }
+
+    // Resolves again the digest of the node version, rolling the node if the registry serves a new image
+    pub async fn refresh_image(&mut self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        let client: Client = context_data.client.clone();
+        let hoprd_name: String = self.name_any();
+        let previous_image = self.get_image(&context_data.config.images);
+        self.resolve_image(context_data.clone(), true).await?;
+        let image = self.get_image(&context_data.config.images);
+        if image.ne(&previous_image) {
+            if let Some(identity) = self.get_identity(client.clone()).await? {
+                info!("Rolling Hoprd node {hoprd_name} from image {previous_image} to {image}");
+                hoprd_deployment::modify_deployment(context_data.clone(), self, &identity).await?;
+                context_data.send_event(self, HoprdEventEnum::Modified, None).await;
+            }
+        } else {
+            info!("Image {image} of Hoprd node {hoprd_name} is up to date");
+        }
+        let api: Api<Hoprd> = Api::namespaced(client, &self.namespace().unwrap());
+        let patch = Patch::Merge(json!({ "metadata": { "annotations": { constants::ANNOTATION_REFRESH_IMAGE: null } } }));
+        api.patch(&hoprd_name, &PatchParams::default(), &patch).await?;
+        Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+    }
+
+    /// Image run by the node. It stays pinned to the digest recorded in the status while the version and repository do not change
+    pub fn get_image(&self, images: &ImagesConfig) -> String {
+        let repository = self.spec.image.as_ref().unwrap_or(&images.hoprd);
+        match self.status.as_ref() {
+            Some(HoprdStatus { image: Some(image), version: Some(version), digest: Some(_), .. }) if version.eq(&self.spec.version) && image.starts_with(&format!("{}@", repository)) => image.to_owned(),
+            _ => format!("{}:{}", repository, self.spec.version),
+        }
+    }
+
+    /// Resolves the version of the node into a digest and records it in the status. Pinned versions are only resolved again when refreshing
+    async fn resolve_image(&mut self, context_data: Arc<ContextData>, refresh: bool) -> Result<(), Error> {
+        let images = &context_data.config.images;
+        if !images.resolve_digests || (!refresh && self.get_image(images).contains('@')) {
+            return Ok(());
+        }
+        let repository = self.spec.image.clone().unwrap_or(images.hoprd.to_owned());
+        let mut status = self.status.clone().unwrap_or_default();
+        status.version = Some(self.spec.version.to_owned());
+        let image = ImageReference::parse(&repository, &self.spec.version);
+        let credentials = image_registry::get_registry_credentials(context_data.client.clone(), &self.namespace().unwrap(), &images.pull_secrets, &image.registry).await;
+        match context_data.image_registry.resolve_digest(&image, credentials.as_ref()).await {
+            Ok(digest) => {
+                info!("Version {} of Hoprd node {} resolved to {}", self.spec.version, self.name_any(), digest);
+                status.image = Some(format!("{}@{}", repository, digest));
+                status.digest = Some(digest);
+            }
+            Err(error) => {
+                warn!("Could not resolve the digest of version {} for Hoprd node {}, using the tag instead: {}", self.spec.version, self.name_any(), error);
+                context_data.send_event(self, HoprdEventEnum::ImageNotResolved, Some(error.to_string())).await;
+                status.image = Some(format!("{}:{}", repository, self.spec.version));
+                status.digest = None;
+            }
+        }
+        let api: Api<Hoprd> = Api::namespaced(context_data.client.clone(), &self.namespace().unwrap());
+        let patch = Patch::Merge(json!({ "status": { "image": status.image, "version": status.version, "digest": status.digest } }));
+        if let Err(error) = api.patch_status(&self.name_any(), &PatchParams::default(), &patch).await {
+            error!("Could not record the image of Hoprd node {}: {:?}", self.name_any(), error);
+        }
+        self.status = Some(status);
+        Ok(())
+    }
+
     // Deletes all the related resources
     pub async fn delete(&self, context_data: Arc<ContextData>) -> Result<Action, Error> {
         let hoprd_name = self.name_any();
@@ -348,10 +860,12 @@ impl Hoprd {
 
         let api: Api<Hoprd> = Api::namespaced(client.clone(), &hoprd_namespace.to_owned());
         let mut status = self.status.as_ref().unwrap_or(&HoprdStatus::default()).to_owned();
+        if status.phase.ne(&phase) || status.last_transition_time.is_none() {
+            status.last_transition_time = Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+        }
         status.phase = phase;
         status.observed_generation = self.metadata.generation.unwrap_or(0);
-        let patch = Patch::Merge(json!({ "status": status }));
-        match api.patch_status(&hoprd_name, &PatchParams::default(), &patch).await {
+        match api.patch_status(&hoprd_name, &PatchParams::default(), &Patch::Merge(phase_status_patch(&status))).await {
             Ok(_) => Ok(()),
             Err(error) => Ok(error!("Could not update status on node {hoprd_name}: {:?}", error)),
         }
@@ -452,3 +966,216 @@ impl Hoprd {
         }
     }
 }
+
+/// Phase a node moves to when its network health changes: running nodes become degraded, and degraded nodes recover to running
+fn health_transition(phase: HoprdPhaseEnum, degraded: bool) -> Option<HoprdPhaseEnum> {
+    match phase {
+        HoprdPhaseEnum::Running if degraded => Some(HoprdPhaseEnum::Degraded),
+        HoprdPhaseEnum::Degraded if !degraded => Some(HoprdPhaseEnum::Running),
+        _ => None,
+    }
+}
+
+/// Sets the `NetworkHealthy` condition. A node flapping between an unhealthy network and an unreachable API keeps the time it became unhealthy,
+/// so that it is not recovered from the degraded phase, nor its degraded window started over, by the flaps
+fn set_network_healthy_condition(conditions: &mut Vec<Condition>, healthy: &str, reason: &str, message: String, generation: Option<i64>) {
+    let unhealthy_since = conditions
+        .iter()
+        .find(|condition| condition.type_ == constants::CONDITION_NETWORK_HEALTHY && condition.status != "True")
+        .map(|condition| condition.last_transition_time.clone());
+    utils::set_condition(conditions, constants::CONDITION_NETWORK_HEALTHY, healthy, reason, message, generation);
+    if let (Some(unhealthy_since), true) = (unhealthy_since, healthy != "True") {
+        conditions.iter_mut().filter(|condition| condition.type_ == constants::CONDITION_NETWORK_HEALTHY).for_each(|condition| condition.last_transition_time = unhealthy_since.clone());
+    }
+}
+
+/// Status, reason and message of the `ConfigValid` condition for the result of the configuration validation
+fn config_validity_condition(validation: &Result<Vec<String>, String>) -> (&'static str, &'static str, String) {
+    match validation {
+        Err(problem) => ("False", "InvalidConfig", problem.to_owned()),
+        Ok(unknown_keys) if !unknown_keys.is_empty() => ("True", "UnknownKeys", format!("The configuration is valid but has keys ignored by hoprd: {}", unknown_keys.join(", "))),
+        Ok(_) => ("True", "ValidConfig", "The configuration matches the schema of the hoprd version".to_owned()),
+    }
+}
+
+/// Records the name, the restarts of the hoprd container and the snapshot progress of the latest pod of the node
+fn read_pod_status(pod: Option<&Pod>, status: &mut HoprdStatus) {
+    status.pod_name = pod.map(|pod| pod.name_any());
+    status.restart_count = pod
+        .and_then(|pod| pod.status.as_ref())
+        .and_then(|pod_status| pod_status.container_statuses.as_ref())
+        .and_then(|container_statuses| container_statuses.iter().find(|container_status| container_status.name == "hoprd"))
+        .map(|container_status| container_status.restart_count);
+    status.snapshot = pod.and_then(hoprd_snapshot::snapshot_status);
+}
+
+/// Status patch of the runtime information of the node, leaving the phase to `update_status`
+fn runtime_status_patch(status: &HoprdStatus) -> serde_json::Value {
+    json!({
+        "status": {
+            "identityName": status.identity_name,
+            "nodeAddress": status.node_address,
+            "safeAddress": status.safe_address,
+            "p2pAddress": status.p2p_address,
+            "portRange": status.port_range,
+            "sessionPortRange": status.session_port_range,
+            "apiUrl": status.api_url,
+            "podName": status.pod_name,
+            "restartCount": status.restart_count,
+            "networkHealth": status.network_health,
+            "connectedPeers": status.connected_peers,
+            "incomingChannels": status.incoming_channels,
+            "outgoingChannels": status.outgoing_channels,
+            "balances": status.balances,
+            "conditions": status.conditions,
+            "snapshot": status.snapshot
+        }
+    })
+}
+
+/// Status patch of the phase. Only the phase related fields are patched, so the runtime information recorded by other reconciliations is kept
+fn phase_status_patch(status: &HoprdStatus) -> serde_json::Value {
+    json!({
+        "status": {
+            "phase": status.phase,
+            "observedGeneration": status.observed_generation,
+            "lastTransitionTime": status.last_transition_time
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    #[test]
+    fn test_runtime_status() {
+        let hoprd = Hoprd::default();
+        let deployment: Deployment = serde_json::from_value(json!({
+            "spec": {
+                "selector": {},
+                "template": { "spec": { "containers": [{
+                    "name": "hoprd",
+                    "env": [
+                        { "name": constants::HOPRD_HOST, "value": "1.2.3.4:9091" },
+                        { "name": constants::HOPRD_SESSION_PORT_RANGE, "value": "9092:9094" }
+                    ]
+                }] } }
+            }
+        }))
+        .unwrap();
+        let pod: Pod = serde_json::from_value(json!({
+            "metadata": { "name": "hoprd-core-rotsee-1-abcde" },
+            "status": { "containerStatuses": [{ "name": "hoprd", "restartCount": 3, "ready": true, "image": "hoprd", "imageID": "" }] }
+        }))
+        .unwrap();
+        let mut status = HoprdStatus {
+            phase: HoprdPhaseEnum::Running,
+            ..HoprdStatus::default()
+        };
+        hoprd.read_deployment_status(&deployment, &mut status);
+        read_pod_status(Some(&pod), &mut status);
+        assert_eq!(status.p2p_address.as_deref(), Some("1.2.3.4:9091"));
+        assert_eq!(status.port_range.as_deref(), Some(&*format!("9091:{}", 9091 + hoprd.spec.service.ports_allocation)));
+        assert_eq!(status.session_port_range.as_deref(), Some("9092:9094"));
+        assert_eq!(status.pod_name.as_deref(), Some("hoprd-core-rotsee-1-abcde"));
+        assert_eq!(status.restart_count, Some(3));
+        assert_eq!(status.snapshot, None);
+
+        let runtime_patch = runtime_status_patch(&status);
+        assert_eq!(runtime_patch["status"]["p2pAddress"], "1.2.3.4:9091");
+        assert_eq!(runtime_patch["status"]["sessionPortRange"], "9092:9094");
+        assert_eq!(runtime_patch["status"]["podName"], "hoprd-core-rotsee-1-abcde");
+        assert_eq!(runtime_patch["status"]["restartCount"], 3);
+        assert!(runtime_patch["status"].get("phase").is_none());
+
+        // The phase patch keeps the runtime information recorded by other reconciliations
+        let phase_patch = phase_status_patch(&status);
+        let mut keys: Vec<&String> = phase_patch["status"].as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["lastTransitionTime", "observedGeneration", "phase"]);
+        assert_eq!(phase_patch["status"]["phase"], "Running");
+
+        read_pod_status(None, &mut status);
+        assert_eq!(status.pod_name, None);
+        assert_eq!(status.restart_count, None);
+    }
+
+    fn hoprd_with_network_health(healthy: &str, seconds_since_transition: i64) -> Hoprd {
+        let mut conditions = Vec::new();
+        utils::set_condition(&mut conditions, constants::CONDITION_NETWORK_HEALTHY, healthy, "NetworkHealth", "Orange".to_owned(), None);
+        conditions[0].last_transition_time = Time(Utc::now() - chrono::Duration::seconds(seconds_since_transition));
+        Hoprd {
+            status: Some(HoprdStatus {
+                conditions: Some(conditions),
+                ..HoprdStatus::default()
+            }),
+            ..Hoprd::default()
+        }
+    }
+
+    #[test]
+    fn test_is_degraded() {
+        let health_config = HealthConfig {
+            degraded_window: 300,
+            ..HealthConfig::default()
+        };
+        assert!(!hoprd_with_network_health("False", 10).is_degraded(&health_config));
+        assert!(!hoprd_with_network_health("False", 299).is_degraded(&health_config));
+        assert!(hoprd_with_network_health("False", 300).is_degraded(&health_config));
+        assert!(hoprd_with_network_health("False", 3600).is_degraded(&health_config));
+        assert!(!hoprd_with_network_health("True", 3600).is_degraded(&health_config));
+        assert!(!Hoprd::default().is_degraded(&health_config));
+        // An unreachable API does not recover a degraded node
+        assert!(hoprd_with_network_health("Unknown", 3600).is_degraded(&health_config));
+        assert!(!hoprd_with_network_health("Unknown", 10).is_degraded(&health_config));
+    }
+
+    #[test]
+    fn test_network_healthy_flaps_keep_transition_time() {
+        let health_config = HealthConfig {
+            degraded_window: 300,
+            ..HealthConfig::default()
+        };
+        let mut hoprd = hoprd_with_network_health("False", 3600);
+        let conditions = hoprd.status.as_mut().unwrap().conditions.as_mut().unwrap();
+        let unhealthy_since = conditions[0].last_transition_time.clone();
+        set_network_healthy_condition(conditions, "Unknown", "ApiUnreachable", "The network health cannot be queried".to_owned(), None);
+        assert_eq!(conditions[0].status, "Unknown");
+        assert_eq!(conditions[0].last_transition_time, unhealthy_since);
+        set_network_healthy_condition(conditions, "False", "Orange", "Orange".to_owned(), None);
+        assert_eq!(conditions[0].last_transition_time, unhealthy_since);
+        assert!(hoprd.is_degraded(&health_config));
+
+        // Becoming healthy starts a new transition
+        let conditions = hoprd.status.as_mut().unwrap().conditions.as_mut().unwrap();
+        set_network_healthy_condition(conditions, "True", "Green", "Green".to_owned(), None);
+        assert_ne!(conditions[0].last_transition_time, unhealthy_since);
+        assert!(!hoprd.is_degraded(&health_config));
+    }
+
+    #[test]
+    fn test_health_transition() {
+        assert_eq!(health_transition(HoprdPhaseEnum::Running, true), Some(HoprdPhaseEnum::Degraded));
+        assert_eq!(health_transition(HoprdPhaseEnum::Running, false), None);
+        assert_eq!(health_transition(HoprdPhaseEnum::Degraded, false), Some(HoprdPhaseEnum::Running));
+        assert_eq!(health_transition(HoprdPhaseEnum::Degraded, true), None);
+        // Other phases are driven by the lifecycle of the node, not by its network health
+        assert_eq!(health_transition(HoprdPhaseEnum::Stopped, true), None);
+        assert_eq!(health_transition(HoprdPhaseEnum::Failed, false), None);
+    }
+
+    #[test]
+    fn test_config_validity_condition() {
+        assert_eq!(config_validity_condition(&Ok(Vec::new())).1, "ValidConfig");
+        let (valid, reason, message) = config_validity_condition(&Err("/hopr/chain/network: 1 is not of type \"string\"".to_owned()));
+        assert_eq!((valid, reason), ("False", "InvalidConfig"));
+        assert!(message.starts_with("/hopr/chain/network"));
+        // Unknown keys do not block the node but are listed with their path
+        let unknown_keys = hoprd_config::validate("hopr:\n  chain:\n    network: rotsee\n  stratgy:\n    on_fail_continue: true\n", "3.0.0").unwrap();
+        let (valid, reason, message) = config_validity_condition(&Ok(unknown_keys));
+        assert_eq!((valid, reason), ("True", "UnknownKeys"));
+        assert!(message.contains("/hopr/stratgy: unknown key 'stratgy'"));
+    }
+}
@@ -0,0 +1,153 @@
+use k8s_openapi::api::core::v1::{Container, EnvVar, Pod, SecurityContext, VolumeMount};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::operator_config::ImagesConfig;
+
+/// Name of the init container downloading the snapshot
+pub const SNAPSHOT_CONTAINER_NAME: &str = "snapshot";
+
+/// Logs snapshot downloaded into the database of the node before it starts, to speed up its synchronization
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdSnapshotSpec {
+    /// URL of the `tar.xz` snapshot
+    pub url: String,
+    /// Expected sha256 checksum of the snapshot. The snapshot is not verified if not set
+    pub sha256: Option<String>,
+    /// Maximum age in seconds of the snapshot, read from its `Last-Modified` header
+    pub max_age: Option<u64>,
+    /// Whether the node must not start without the snapshot. Otherwise the node starts syncing from scratch when the snapshot cannot be used
+    pub required: Option<bool>,
+    /// Download attempts before giving up
+    pub retries: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Copy)]
+pub enum HoprdSnapshotPhaseEnum {
+    /// The snapshot is being downloaded and verified
+    Downloading,
+    /// The database of the node contains the snapshot
+    Completed,
+    /// The optional snapshot could not be used and the node syncs from scratch
+    Skipped,
+    /// The required snapshot could not be used and the download is retried by restarting the container
+    Failed,
+}
+
+/// Progress of the snapshot download, read from the init container of the pod
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdSnapshotStatus {
+    pub phase: HoprdSnapshotPhaseEnum,
+    /// Times the download was restarted after failing
+    pub restarts: i32,
+    pub message: Option<String>,
+}
+
+/// Init container downloading the snapshot into the database volume before the node starts
+pub fn snapshot_container(snapshot: &HoprdSnapshotSpec, images: &ImagesConfig, volume_mounts: Vec<VolumeMount>, security_context: SecurityContext) -> Container {
+    let env_var = |name: &str, value: Option<String>| EnvVar {
+        name: name.to_owned(),
+        value,
+        ..EnvVar::default()
+    };
+    Container {
+        name: SNAPSHOT_CONTAINER_NAME.to_owned(),
+        image: Some(images.snapshot.to_owned()),
+        image_pull_policy: images.pull_policy.clone(),
+        env: Some(vec![
+            env_var("SNAPSHOT_URL", Some(snapshot.url.to_owned())),
+            env_var("SNAPSHOT_SHA256", snapshot.sha256.clone()),
+            env_var("SNAPSHOT_MAX_AGE", snapshot.max_age.map(|max_age| max_age.to_string())),
+            env_var("SNAPSHOT_REQUIRED", Some(snapshot.required.unwrap_or(false).to_string())),
+            env_var("SNAPSHOT_RETRIES", Some(snapshot.retries.unwrap_or(3).to_string())),
+        ]),
+        termination_message_policy: Some("FallbackToLogsOnError".to_owned()),
+        volume_mounts: Some(volume_mounts),
+        security_context: Some(security_context),
+        ..Container::default()
+    }
+}
+
+/// Reads the progress of the snapshot from the state of its init container. The container reports the outcome as `<phase>: <message>` in its termination message
+pub fn snapshot_status(pod: &Pod) -> Option<HoprdSnapshotStatus> {
+    let container_status = pod
+        .status
+        .as_ref()?
+        .init_container_statuses
+        .as_ref()?
+        .iter()
+        .find(|container_status| container_status.name == SNAPSHOT_CONTAINER_NAME)?;
+    let state = container_status.state.as_ref()?;
+    let (phase, message) = match state.terminated.as_ref() {
+        Some(terminated) => {
+            let termination_message = terminated.message.clone().unwrap_or_default();
+            match termination_message.trim().split_once(": ") {
+                Some(("Skipped", message)) => (HoprdSnapshotPhaseEnum::Skipped, Some(message.to_owned())),
+                Some((_, message)) if terminated.exit_code == 0 => (HoprdSnapshotPhaseEnum::Completed, Some(message.to_owned())),
+                Some((_, message)) => (HoprdSnapshotPhaseEnum::Failed, Some(message.to_owned())),
+                None if terminated.exit_code == 0 => (HoprdSnapshotPhaseEnum::Completed, None),
+                None => (HoprdSnapshotPhaseEnum::Failed, Some(format!("exited with code {}", terminated.exit_code))),
+            }
+        }
+        None => {
+            // A required snapshot that failed waits in a back-off before being downloaded again
+            let previous_message = container_status.last_state.as_ref().and_then(|last_state| last_state.terminated.as_ref()).and_then(|terminated| terminated.message.clone());
+            let phase = if state.waiting.is_some() && previous_message.is_some() { HoprdSnapshotPhaseEnum::Failed } else { HoprdSnapshotPhaseEnum::Downloading };
+            (phase, previous_message.map(|message| message.trim().trim_start_matches("Failed: ").to_owned()))
+        }
+    };
+    Some(HoprdSnapshotStatus {
+        phase,
+        restarts: container_status.restart_count,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pod(state: serde_json::Value, restart_count: i32) -> Pod {
+        serde_json::from_value(json!({
+            "metadata": { "name": "hoprd-1" },
+            "status": {
+                "initContainerStatuses": [{
+                    "name": "snapshot",
+                    "image": "snapshot",
+                    "imageID": "",
+                    "ready": false,
+                    "restartCount": restart_count,
+                    "state": state,
+                    "lastState": { "terminated": { "exitCode": 1, "message": "Failed: Could not download the snapshot after 3 attempts\n" } }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_status() {
+        let downloading = snapshot_status(&pod(json!({ "running": {} }), 2)).unwrap();
+        assert_eq!(downloading.phase, HoprdSnapshotPhaseEnum::Downloading);
+        assert_eq!(downloading.restarts, 2);
+        assert_eq!(downloading.message, Some("Could not download the snapshot after 3 attempts".to_owned()));
+        let backing_off = snapshot_status(&pod(json!({ "waiting": { "reason": "CrashLoopBackOff" } }), 3)).unwrap();
+        assert_eq!(backing_off.phase, HoprdSnapshotPhaseEnum::Failed);
+        let completed = snapshot_status(&pod(json!({ "terminated": { "exitCode": 0, "message": "Completed: Downloaded the snapshot in 1 attempts\n" } }), 0)).unwrap();
+        assert_eq!(completed.phase, HoprdSnapshotPhaseEnum::Completed);
+        assert_eq!(completed.message, Some("Downloaded the snapshot in 1 attempts".to_owned()));
+        let skipped = snapshot_status(&pod(json!({ "terminated": { "exitCode": 0, "message": "Skipped: The snapshot is 90000 seconds old, older than the maximum of 86400\n" } }), 0)).unwrap();
+        assert_eq!(skipped.phase, HoprdSnapshotPhaseEnum::Skipped);
+        let failed = snapshot_status(&pod(json!({ "terminated": { "exitCode": 1, "message": "Failed: The sha256 checksum of the snapshot does not match 0x01 after 3 attempts\n" } }), 0)).unwrap();
+        assert_eq!(failed.phase, HoprdSnapshotPhaseEnum::Failed);
+    }
+
+    #[test]
+    fn test_snapshot_container_tag_matches_version() {
+        let version = std::fs::read_to_string("snapshot-container/VERSION").unwrap();
+        assert_eq!(version.trim(), crate::constants::OPERATOR_SNAPSHOT_CONTAINER_TAG);
+    }
+}
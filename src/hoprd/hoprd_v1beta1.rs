@@ -0,0 +1,172 @@
+use crate::hoprd::{hoprd_config::HoprdConfigRef, hoprd_deployment_spec, hoprd_remediation::HoprdRemediationSpec, hoprd_snapshot::HoprdSnapshotSpec, hoprd_resource, hoprd_resource::HoprdStatus, hoprd_service::HoprdServiceSpec};
+use crate::model::Error;
+use k8s_openapi::api::core::v1::{Affinity, Container, EnvVar, PodSecurityContext, Probe, ResourceRequirements, SecurityContext, Toleration, TopologySpreadConstraint, Volume, VolumeMount};
+use std::collections::BTreeMap;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Struct corresponding to the Specification (`spec`) part of the `v1beta1` version of the `Hoprd` resource.
+/// This version is only served, the objects are stored as `v1alpha3` and translated by the conversion webhook.
+#[derive(CustomResource, Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[kube(group = "hoprnet.org", version = "v1beta1", kind = "Hoprd", plural = "hoprds", derive = "PartialEq", namespaced)]
+#[kube(status = "HoprdStatus", shortname = "hoprd")]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdSpec {
+    pub identity_pool_name: String,
+    pub identity_name: String,
+    pub version: String,
+    pub image: Option<String>,
+    #[serde(default)]
+    pub config: String,
+    pub config_ref: Option<HoprdConfigRef>,
+    pub enabled: Option<bool>,
+    pub delete_database: Option<bool>,
+    pub service: HoprdServiceSpec,
+    pub deployment: Option<HoprdDeploymentSpec>,
+    pub profiling_enabled: Option<bool>,
+    pub source_node_logs: Option<bool>,
+    pub dns_name: Option<String>,
+    pub remediation: Option<HoprdRemediationSpec>,
+    pub snapshot: Option<HoprdSnapshotSpec>,
+}
+
+/// Pod customizations of the hoprd node using the Kubernetes types
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HoprdDeploymentSpec {
+    pub env: Option<Vec<EnvVar>>,
+    pub resources: Option<ResourceRequirements>,
+    pub startup_probe: Option<Probe>,
+    pub liveness_probe: Option<Probe>,
+    pub readiness_probe: Option<Probe>,
+    pub extra_containers: Option<Vec<Container>>,
+    pub extra_init_containers: Option<Vec<Container>>,
+    pub extra_volumes: Option<Vec<Volume>>,
+    pub extra_volume_mounts: Option<Vec<VolumeMount>>,
+    pub node_selector: Option<BTreeMap<String, String>>,
+    pub tolerations: Option<Vec<Toleration>>,
+    pub affinity: Option<Affinity>,
+    pub topology_spread_constraints: Option<Vec<TopologySpreadConstraint>>,
+    pub priority_class_name: Option<String>,
+    pub pod_labels: Option<BTreeMap<String, String>>,
+    pub pod_annotations: Option<BTreeMap<String, String>>,
+    pub pod_security_context: Option<PodSecurityContext>,
+    pub security_context: Option<SecurityContext>,
+}
+
+impl TryFrom<hoprd_deployment_spec::HoprdDeploymentSpec> for HoprdDeploymentSpec {
+    type Error = Error;
+
+    fn try_from(deployment: hoprd_deployment_spec::HoprdDeploymentSpec) -> Result<Self, Error> {
+        Ok(Self {
+            env: deployment.env.as_deref().map(hoprd_deployment_spec::HoprdDeploymentSpec::parse_environment_variables).transpose()?,
+            resources: deployment.resources.as_deref().map(serde_yml::from_str).transpose()?,
+            startup_probe: deployment.startup_probe.as_deref().map(serde_yml::from_str).transpose()?,
+            liveness_probe: deployment.liveness_probe.as_deref().map(serde_yml::from_str).transpose()?,
+            readiness_probe: deployment.readiness_probe.as_deref().map(serde_yml::from_str).transpose()?,
+            extra_containers: deployment.extra_containers.as_deref().map(serde_yml::from_str).transpose()?,
+            extra_init_containers: deployment.extra_init_containers.as_deref().map(serde_yml::from_str).transpose()?,
+            extra_volumes: deployment.extra_volumes.as_deref().map(serde_yml::from_str).transpose()?,
+            extra_volume_mounts: deployment.extra_volume_mounts.as_deref().map(serde_yml::from_str).transpose()?,
+            node_selector: deployment.node_selector,
+            tolerations: deployment.tolerations.as_deref().map(serde_yml::from_str).transpose()?,
+            affinity: deployment.affinity.as_deref().map(serde_yml::from_str).transpose()?,
+            topology_spread_constraints: deployment.topology_spread_constraints.as_deref().map(serde_yml::from_str).transpose()?,
+            priority_class_name: deployment.priority_class_name,
+            pod_labels: deployment.pod_labels,
+            pod_annotations: deployment.pod_annotations,
+            pod_security_context: deployment.pod_security_context.as_deref().map(serde_yml::from_str).transpose()?,
+            security_context: deployment.security_context.as_deref().map(serde_yml::from_str).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<HoprdDeploymentSpec> for hoprd_deployment_spec::HoprdDeploymentSpec {
+    type Error = Error;
+
+    fn try_from(deployment: HoprdDeploymentSpec) -> Result<Self, Error> {
+        Ok(Self {
+            env: deployment.env.as_ref().map(serde_yml::to_string).transpose()?,
+            resources: deployment.resources.as_ref().map(serde_yml::to_string).transpose()?,
+            startup_probe: deployment.startup_probe.as_ref().map(serde_yml::to_string).transpose()?,
+            liveness_probe: deployment.liveness_probe.as_ref().map(serde_yml::to_string).transpose()?,
+            readiness_probe: deployment.readiness_probe.as_ref().map(serde_yml::to_string).transpose()?,
+            extra_containers: deployment.extra_containers.as_ref().map(serde_yml::to_string).transpose()?,
+            extra_init_containers: deployment.extra_init_containers.as_ref().map(serde_yml::to_string).transpose()?,
+            extra_volumes: deployment.extra_volumes.as_ref().map(serde_yml::to_string).transpose()?,
+            extra_volume_mounts: deployment.extra_volume_mounts.as_ref().map(serde_yml::to_string).transpose()?,
+            node_selector: deployment.node_selector,
+            tolerations: deployment.tolerations.as_ref().map(serde_yml::to_string).transpose()?,
+            affinity: deployment.affinity.as_ref().map(serde_yml::to_string).transpose()?,
+            topology_spread_constraints: deployment.topology_spread_constraints.as_ref().map(serde_yml::to_string).transpose()?,
+            priority_class_name: deployment.priority_class_name,
+            pod_labels: deployment.pod_labels,
+            pod_annotations: deployment.pod_annotations,
+            pod_security_context: deployment.pod_security_context.as_ref().map(serde_yml::to_string).transpose()?,
+            security_context: deployment.security_context.as_ref().map(serde_yml::to_string).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<hoprd_resource::HoprdSpec> for HoprdSpec {
+    type Error = Error;
+
+    fn try_from(spec: hoprd_resource::HoprdSpec) -> Result<Self, Error> {
+        Ok(Self {
+            identity_pool_name: spec.identity_pool_name,
+            identity_name: spec.identity_name,
+            version: spec.version,
+            image: spec.image,
+            config: spec.config,
+            enabled: spec.enabled,
+            delete_database: spec.delete_database,
+            service: spec.service,
+            deployment: spec.deployment.map(HoprdDeploymentSpec::try_from).transpose()?,
+            profiling_enabled: spec.profiling_enabled,
+            source_node_logs: spec.source_node_logs,
+            dns_name: spec.dns_name,
+            remediation: spec.remediation,
+            config_ref: spec.config_ref,
+            snapshot: spec.snapshot,
+        })
+    }
+}
+
+impl TryFrom<HoprdSpec> for hoprd_resource::HoprdSpec {
+    type Error = Error;
+
+    fn try_from(spec: HoprdSpec) -> Result<Self, Error> {
+        Ok(Self {
+            identity_pool_name: spec.identity_pool_name,
+            identity_name: spec.identity_name,
+            version: spec.version,
+            image: spec.image,
+            config: spec.config,
+            enabled: spec.enabled,
+            delete_database: spec.delete_database,
+            service: spec.service,
+            deployment: spec.deployment.map(hoprd_deployment_spec::HoprdDeploymentSpec::try_from).transpose()?,
+            profiling_enabled: spec.profiling_enabled,
+            source_node_logs: spec.source_node_logs,
+            dns_name: spec.dns_name,
+            remediation: spec.remediation,
+            config_ref: spec.config_ref,
+            snapshot: spec.snapshot,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+    use kube::CustomResourceExt;
+
+    #[test]
+    fn test_chart_schema_matches_generated_schema() {
+        let generated_schema = serde_json::to_value(&Hoprd::crd().spec.versions[0].schema).unwrap();
+        assert_eq!(utils::crd_schema_from_chart("charts/hoprd-crds/templates/hoprd.yaml", "v1beta1"), generated_schema);
+    }
+}
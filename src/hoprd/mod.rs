@@ -1,6 +1,11 @@
+pub mod hoprd_api;
+pub mod hoprd_config;
 pub mod hoprd_controller;
 pub mod hoprd_deployment;
 pub mod hoprd_deployment_spec;
 pub mod hoprd_ingress;
+pub mod hoprd_remediation;
 pub mod hoprd_resource;
 pub mod hoprd_service;
+pub mod hoprd_snapshot;
+pub mod hoprd_v1beta1;
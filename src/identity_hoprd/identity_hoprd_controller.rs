@@ -1,5 +1,5 @@
 use futures::StreamExt;
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Secret};
 use kube::{
     api::Api,
     client::Client,
@@ -16,7 +16,7 @@ use tracing::error;
 use crate::{
     constants::{self},
     context_data::ContextData,
-    identity_hoprd::identity_hoprd_resource::IdentityHoprd,
+    identity_hoprd::identity_hoprd_resource::{IdentityHoprd, IdentityHoprdPhaseEnum},
     model::Error,
 };
 
@@ -28,8 +28,10 @@ enum IdentityHoprdAction {
     Modify,
     /// Delete the IdentityHoprd resource
     Delete,
-    /// This `IdentityHoprd` resource is in desired state and requires no actions to be taken
-    NoOp,
+    /// Validate the data on-chain until it passes the checks
+    Validate,
+    /// Keep the Secret holding the identity file in sync
+    Sync,
 }
 
 /// Resources arrives into reconciliation queue in a certain state. This function looks at
@@ -46,10 +48,13 @@ fn determine_action(identity_hoprd: &IdentityHoprd) -> IdentityHoprdAction {
     } else {
         let current_generation = identity_hoprd.meta().generation.unwrap_or(0);
         let observed_generation = identity_hoprd.status.as_ref().map_or(0, |status| status.observed_generation);
+        let phase = identity_hoprd.status.as_ref().map(|status| status.phase);
         if observed_generation < current_generation {
             IdentityHoprdAction::Modify
+        } else if phase == Some(IdentityHoprdPhaseEnum::Validating) || phase == Some(IdentityHoprdPhaseEnum::Invalid) {
+            IdentityHoprdAction::Validate
         } else {
-            IdentityHoprdAction::NoOp
+            IdentityHoprdAction::Sync
         }
     };
 }
@@ -62,8 +67,8 @@ async fn reconciler(identity_hoprd: Arc<IdentityHoprd>, context: Arc<ContextData
         IdentityHoprdAction::Create => identity_hoprd_mutable.create(context.clone()).await,
         IdentityHoprdAction::Modify => identity_hoprd_mutable.modify(context.clone()).await,
         IdentityHoprdAction::Delete => identity_hoprd_mutable.delete(context.clone()).await,
-        // The resource is already in desired state, do nothing and re-check after 10 seconds
-        IdentityHoprdAction::NoOp => Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))),
+        IdentityHoprdAction::Validate => identity_hoprd_mutable.validate(context.clone()).await,
+        IdentityHoprdAction::Sync => identity_hoprd_mutable.sync(context.clone()).await,
     }
 }
 
@@ -84,9 +89,11 @@ pub fn on_error(identity_hoprd: Arc<IdentityHoprd>, error: &Error, _context: Arc
 pub async fn run(client: Client, context_data: Arc<ContextData>) {
     let owned_api: Api<IdentityHoprd> = Api::<IdentityHoprd>::all(client.clone());
     let pvc = Api::<PersistentVolumeClaim>::all(client.clone());
+    let secret = Api::<Secret>::all(client.clone());
 
     Controller::new(owned_api, Config::default())
         .owns(pvc, Config::default())
+        .owns(secret, Config::default())
         .shutdown_on_signal()
         .run(reconciler, on_error, context_data)
         .for_each(|reconciliation_result| async move {
@@ -0,0 +1,282 @@
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{Container, EnvVar, EnvVarSource, PodSpec, PodTemplateSpec, Secret, SecretKeySelector};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use k8s_openapi::ByteString;
+use kube::api::{DeleteParams, PostParams};
+use kube::core::ObjectMeta;
+use kube::runtime::controller::Action;
+use kube::{Api, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::context_data::ContextData;
+use crate::events::IdentityHoprdEventEnum;
+use crate::hoprd::hoprd_deployment_spec::HoprdDeploymentSpec;
+use crate::identity_hoprd::identity_hoprd_keystore;
+use crate::identity_hoprd::identity_hoprd_resource::{IdentityHoprd, IdentityHoprdSpec};
+use crate::identity_hoprd::identity_hoprd_secret;
+use crate::identity_hoprd::identity_hoprd_validation::{check_address, format_ether, RpcClient};
+use crate::identity_pool::identity_pool_cronjob_faucet;
+use crate::identity_pool::identity_pool_resource::IdentityPool;
+use crate::model::Error;
+use crate::{constants, utils};
+
+/// Key of the drain secret holding the private key of the node
+const NODE_PRIVATE_KEY_REF_KEY: &str = "NODE_PRIVATE_KEY";
+/// Gas used by a plain transfer of native tokens
+const TRANSFER_GAS_LIMIT: u128 = 21000;
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// Amounts swept from the node and its safe, in wei
+#[derive(Debug, PartialEq, Default)]
+struct DrainPlan {
+    node_native_amount: Option<u128>,
+    safe_native_amount: Option<u128>,
+    safe_token_amount: Option<u128>,
+    gas_price: u128,
+}
+
+impl DrainPlan {
+    fn is_empty(&self) -> bool {
+        self.node_native_amount.is_none() && self.safe_native_amount.is_none() && self.safe_token_amount.is_none()
+    }
+}
+
+/// Sweeps the native and HOPR balances of the node and its safe to the drain address of the pool, before the identity is deleted.
+/// Returns the action to requeue the deletion with while the drain is not finished, unless it is skipped with an annotation
+pub async fn drain_funds(context_data: Arc<ContextData>, identity_hoprd: &IdentityHoprd, identity_pool: &IdentityPool) -> Result<Option<Action>, Error> {
+    let Some(drain) = identity_pool.spec.drain.as_ref() else {
+        return Ok(None);
+    };
+    let identity_name = identity_hoprd.name_any();
+    let namespace = identity_hoprd.namespace().unwrap();
+    let job_name = format!("drain-{}", identity_name);
+    if identity_hoprd.annotations().contains_key(constants::ANNOTATION_SKIP_DRAIN) {
+        warn!("Skipping the drain of the funds of identity {identity_name}");
+        delete_node_key_secret(context_data.clone(), &namespace, &job_name).await?;
+        return Ok(None);
+    }
+    let job_api: Api<Job> = Api::namespaced(context_data.client.clone(), &namespace);
+    if let Some(job) = job_api.get_opt(&job_name).await? {
+        let job_status = job.status.unwrap_or_default();
+        return if job_status.succeeded.unwrap_or(0) > 0 {
+            info!("Funds of identity {identity_name} drained to {}", drain.address);
+            job_api.delete(&job_name, &DeleteParams::background()).await?;
+            delete_node_key_secret(context_data.clone(), &namespace, &job_name).await?;
+            Ok(None)
+        } else if job_status.failed.unwrap_or(0) > 0 {
+            // The job is kept for inspection, while the node key does not outlive it
+            delete_node_key_secret(context_data.clone(), &namespace, &job_name).await?;
+            let reason = format!("job {} failed, delete it to retry or annotate the identity with {}", job_name, constants::ANNOTATION_SKIP_DRAIN);
+            context_data.send_event(identity_hoprd, IdentityHoprdEventEnum::DrainFailed, Some(reason)).await;
+            Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY))))
+        } else {
+            Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))))
+        };
+    }
+
+    let Some(network) = context_data.config.networks.get(&identity_pool.spec.network) else {
+        let reason = format!("network {} is not configured in the operator, annotate the identity with {} to delete it", identity_pool.spec.network, constants::ANNOTATION_SKIP_DRAIN);
+        context_data.send_event(identity_hoprd, IdentityHoprdEventEnum::DrainFailed, Some(reason)).await;
+        return Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY))));
+    };
+    let rpc_provider_url = context_data.config.rpc_provider_url(&identity_pool.spec.network);
+    let rpc_client = RpcClient::new(rpc_provider_url.to_owned())?;
+    // Doubling the gas price keeps the transfer of the node from being stuck when the price rises meanwhile
+    let gas_price = rpc_client.get_gas_price().await? * 2;
+    let plan = DrainPlan {
+        node_native_amount: rpc_client.get_balance(&identity_hoprd.spec.node_address).await?.checked_sub(gas_price * TRANSFER_GAS_LIMIT).filter(|amount| *amount > 0),
+        safe_native_amount: Some(rpc_client.get_balance(&identity_hoprd.spec.safe_address).await?).filter(|amount| *amount > 0),
+        safe_token_amount: Some(rpc_client.get_token_balance(&network.token_address, &identity_hoprd.spec.safe_address).await?).filter(|amount| *amount > 0),
+        gas_price,
+    };
+    if plan.is_empty() {
+        info!("Identity {identity_name} has no funds to drain");
+        return Ok(None);
+    }
+    let script = match build_script(&rpc_provider_url, &network.token_address, &drain.address, &identity_hoprd.spec, &plan) {
+        Ok(script) => script,
+        Err(Error::UserInputError(reason)) => {
+            let reason = format!("{}, annotate the identity with {} to delete it", reason, constants::ANNOTATION_SKIP_DRAIN);
+            context_data.send_event(identity_hoprd, IdentityHoprdEventEnum::DrainFailed, Some(reason)).await;
+            return Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY))));
+        }
+        Err(error) => return Err(error),
+    };
+    if plan.node_native_amount.is_some() {
+        match create_node_key_secret(context_data.clone(), identity_hoprd, identity_pool, &job_name).await {
+            Ok(()) => {}
+            Err(Error::IdentityIssue(reason)) => {
+                let reason = format!("the node key cannot be read: {}, annotate the identity with {} to delete it", reason, constants::ANNOTATION_SKIP_DRAIN);
+                context_data.send_event(identity_hoprd, IdentityHoprdEventEnum::DrainFailed, Some(reason)).await;
+                return Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY))));
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    if let Err(error) = create_job(context_data.clone(), identity_hoprd, identity_pool, &job_name, script).await {
+        delete_node_key_secret(context_data.clone(), &namespace, &job_name).await?;
+        return Err(error);
+    }
+    context_data.send_event(identity_hoprd, IdentityHoprdEventEnum::Draining, Some(drain.address.to_owned())).await;
+    Ok(Some(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY))))
+}
+
+/// Stores the chain key of the node, decrypted with the password of the pool, in a secret read by the drain job
+async fn create_node_key_secret(context_data: Arc<ContextData>, identity_hoprd: &IdentityHoprd, identity_pool: &IdentityPool, secret_name: &str) -> Result<(), Error> {
+    let password = identity_hoprd_keystore::get_identity_password(context_data.client.clone(), identity_pool)
+        .await?
+        .ok_or_else(|| Error::IdentityIssue(format!("the secret {} has no IDENTITY_PASSWORD", identity_pool.spec.secret_name)))?;
+    let identity_file = identity_hoprd_secret::get_identity_file(context_data.client.clone(), identity_hoprd).await?;
+    let chain_key = identity_hoprd_keystore::decrypt_chain_key(&identity_file, &password).await?;
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(secret_name.to_owned()),
+            namespace: identity_hoprd.namespace(),
+            labels: Some(utils::common_lables(identity_pool.name_any(), Some(identity_hoprd.name_any()), Some("drain".to_owned()))),
+            owner_references: Some(vec![identity_hoprd.controller_owner_ref(&()).unwrap()]),
+            ..ObjectMeta::default()
+        },
+        data: Some(BTreeMap::from([(NODE_PRIVATE_KEY_REF_KEY.to_owned(), ByteString(format!("0x{}", hex::encode(chain_key)).into_bytes()))])),
+        ..Secret::default()
+    };
+    let api: Api<Secret> = Api::namespaced(context_data.client.clone(), &identity_hoprd.namespace().unwrap());
+    if api.get_opt(secret_name).await?.is_none() {
+        api.create(&PostParams::default(), &secret).await?;
+    }
+    Ok(())
+}
+
+async fn delete_node_key_secret(context_data: Arc<ContextData>, namespace: &str, secret_name: &str) -> Result<(), Error> {
+    let api: Api<Secret> = Api::namespaced(context_data.client.clone(), namespace);
+    if api.get_opt(secret_name).await?.is_some() {
+        api.delete(secret_name, &DeleteParams::default()).await?;
+        info!("Secret {secret_name} with the node key of the drain deleted");
+    }
+    Ok(())
+}
+
+/// Script sweeping the balances of the plan with cast. The safe transfers are executed by its owner, the wallet of the pool,
+/// with a pre-validated signature, and the node pays the exact fee of its own transfer
+fn build_script(rpc_provider_url: &str, token_address: &str, address: &str, identity: &IdentityHoprdSpec, plan: &DrainPlan) -> Result<String, Error> {
+    for address in [token_address, address, &identity.node_address, &identity.safe_address] {
+        check_address(address)?;
+    }
+    let exec_transaction = |to: &str, value: u128, data: &str| {
+        format!(
+            "cast send {} 'execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)' {} {} {} 0 0 0 0 {ZERO_ADDRESS} {ZERO_ADDRESS} ${{SIGNATURE}} --private-key ${{PRIVATE_KEY}} --rpc-url {}\n",
+            identity.safe_address, to, value, data, rpc_provider_url
+        )
+    };
+    let mut script = format!(
+        "set -e\nexport PATH=${{PATH}}:/app/hoprnet/.foundry/bin/\nOWNER=$(cast wallet address --private-key ${{PRIVATE_KEY}})\nSIGNATURE=0x000000000000000000000000${{OWNER#0x}}{}01\n",
+        "0".repeat(64)
+    );
+    if let Some(amount) = plan.safe_token_amount {
+        script.push_str(&format!("echo 'Draining {} HOPR from safe {}'\n", format_ether(amount), identity.safe_address));
+        script.push_str(&exec_transaction(token_address, 0, &format!("$(cast calldata 'transfer(address,uint256)' {} {})", address, amount)));
+    }
+    if let Some(amount) = plan.safe_native_amount {
+        script.push_str(&format!("echo 'Draining {} native tokens from safe {}'\n", format_ether(amount), identity.safe_address));
+        script.push_str(&exec_transaction(address, amount, "0x"));
+    }
+    if let Some(amount) = plan.node_native_amount {
+        script.push_str(&format!("echo 'Draining {} native tokens from node {}'\n", format_ether(amount), identity.node_address));
+        script.push_str(&format!(
+            "cast send {} --value {} --gas-limit {} --gas-price {} --legacy --private-key ${{{}}} --rpc-url {}\n",
+            address, amount, TRANSFER_GAS_LIMIT, plan.gas_price, NODE_PRIVATE_KEY_REF_KEY, rpc_provider_url
+        ));
+    }
+    Ok(script)
+}
+
+async fn create_job(context_data: Arc<ContextData>, identity_hoprd: &IdentityHoprd, identity_pool: &IdentityPool, job_name: &str, script: String) -> Result<Job, Error> {
+    let namespace = identity_hoprd.namespace().unwrap();
+    let owner_references: Option<Vec<OwnerReference>> = Some(vec![identity_hoprd.controller_owner_ref(&()).unwrap()]);
+    let labels: BTreeMap<String, String> = utils::common_lables(identity_pool.name_any(), Some(identity_hoprd.name_any()), Some("drain".to_owned()));
+    let mut env = identity_pool_cronjob_faucet::get_env_var(identity_pool.spec.secret_name.to_owned()).await;
+    env.push(EnvVar {
+        name: NODE_PRIVATE_KEY_REF_KEY.to_owned(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                key: NODE_PRIVATE_KEY_REF_KEY.to_owned(),
+                name: job_name.to_owned(),
+                // The secret is only created when the node has funds to drain
+                optional: Some(true),
+            }),
+            ..EnvVarSource::default()
+        }),
+        ..EnvVar::default()
+    });
+    let job = Job {
+        metadata: ObjectMeta {
+            name: Some(job_name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            owner_references,
+            labels: Some(labels.clone()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(JobSpec {
+            // Transactions are not retried, as a partial run already moved funds
+            backoff_limit: Some(0),
+            active_deadline_seconds: Some(constants::OPERATOR_JOB_TIMEOUT as i64),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..ObjectMeta::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![Container {
+                        name: "drain".to_owned(),
+                        image: Some(context_data.config.hopli_image.to_owned()),
+                        image_pull_policy: context_data.config.images.pull_policy.clone().or_else(|| Some("Always".to_owned())),
+                        command: Some(vec!["/bin/bash".to_owned(), "-c".to_owned()]),
+                        args: Some(vec![script]),
+                        env: Some(env),
+                        resources: Some(HoprdDeploymentSpec::get_resource_requirements(None)),
+                        ..Container::default()
+                    }],
+                    service_account: Some(identity_pool.name_any()),
+                    image_pull_secrets: context_data.config.images.get_pull_secrets(),
+                    restart_policy: Some("Never".to_owned()),
+                    ..PodSpec::default()
+                }),
+            },
+            ..JobSpec::default()
+        }),
+        ..Job::default()
+    };
+    let api: Api<Job> = Api::namespaced(context_data.client.clone(), &namespace);
+    let job = api.create(&PostParams::default(), &job).await?;
+    info!("Job {job_name} draining the funds of identity {} created in namespace {namespace}", identity_hoprd.name_any());
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_script() {
+        let identity = IdentityHoprdSpec {
+            node_address: "0x1111111111111111111111111111111111111111".to_owned(),
+            safe_address: "0x2222222222222222222222222222222222222222".to_owned(),
+            ..IdentityHoprdSpec::default()
+        };
+        let plan = DrainPlan {
+            node_native_amount: Some(5_000_000_000_000_000),
+            safe_token_amount: Some(10_000_000_000_000_000_000),
+            gas_price: 2,
+            ..DrainPlan::default()
+        };
+        let script = build_script("http://rpc", "0x5555555555555555555555555555555555555555", "0x9999999999999999999999999999999999999999", &identity, &plan).unwrap();
+        assert!(script.contains("echo 'Draining 10 HOPR from safe 0x2222222222222222222222222222222222222222'"), "{}", script);
+        assert!(script.contains("0x5555555555555555555555555555555555555555 0 $(cast calldata 'transfer(address,uint256)' 0x9999999999999999999999999999999999999999 10000000000000000000)"), "{}", script);
+        assert!(script.contains("cast send 0x9999999999999999999999999999999999999999 --value 5000000000000000 --gas-limit 21000 --gas-price 2 --legacy"), "{}", script);
+        assert!(!script.contains("native tokens from safe"), "{}", script);
+        assert!(DrainPlan::default().is_empty());
+        assert!(build_script("http://rpc", "0x5555555555555555555555555555555555555555", "0x99; curl evil", &identity, &plan).is_err());
+    }
+}
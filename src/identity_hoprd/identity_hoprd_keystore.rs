@@ -0,0 +1,204 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client, ResourceExt};
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+
+use crate::constants;
+use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprd;
+use crate::identity_hoprd::identity_hoprd_secret;
+use crate::identity_pool::identity_pool_resource::IdentityPool;
+use crate::model::Error;
+use tokio::sync::Semaphore;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Ceiling of the scrypt parameters read from the identity files, which are those of the standard keystores.
+/// Their memory usage of 128 * n * r bytes reaches 256 MB, so larger values are treated as a corrupted file
+const MAX_SCRYPT_LOG_N: u32 = 18;
+const MAX_SCRYPT_R: u32 = 8;
+const MAX_SCRYPT_P: u32 = 4;
+const MAX_SCRYPT_DKLEN: usize = 64;
+
+/// Decryptions running at once, as each of them holds the memory of scrypt
+static DECRYPTIONS: Semaphore = Semaphore::const_new(1);
+
+/// Ethereum keystore (version 3) in which hoprd stores its keys
+#[derive(Deserialize)]
+struct Keystore {
+    crypto: KeystoreCrypto,
+}
+
+#[derive(Deserialize)]
+struct KeystoreCrypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: ScryptParams,
+    mac: String,
+}
+
+#[derive(Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+#[derive(Deserialize)]
+struct ScryptParams {
+    dklen: usize,
+    n: u32,
+    p: u32,
+    r: u32,
+    salt: String,
+}
+
+/// Keys encrypted in the keystore. Older identities only hold the chain key as raw bytes
+#[derive(Deserialize)]
+struct PrivateKeys {
+    chain_key: String,
+}
+
+fn corrupted(reason: impl std::fmt::Display) -> Error {
+    Error::IdentityIssue(format!("the identity file is corrupted: {}", reason))
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|e| corrupted(format!("{} is not hexadecimal: {}", field, e)))
+}
+
+/// Decrypts the identity file and returns the chain key of the node. The key derivation runs on a blocking thread
+pub async fn decrypt_chain_key(identity_file: &[u8], password: &str) -> Result<[u8; 32], Error> {
+    let _permit = DECRYPTIONS.acquire().await.expect("The decryption semaphore is never closed");
+    let (identity_file, password) = (identity_file.to_vec(), password.to_owned());
+    tokio::task::spawn_blocking(move || decrypt_chain_key_blocking(&identity_file, &password))
+        .await
+        .map_err(|e| Error::IdentityIssue(format!("the identity file could not be decrypted: {}", e)))?
+}
+
+fn decrypt_chain_key_blocking(identity_file: &[u8], password: &str) -> Result<[u8; 32], Error> {
+    let keystore: Keystore = serde_json::from_slice(identity_file).map_err(|e| corrupted(format!("it is not a keystore: {}", e)))?;
+    let crypto = keystore.crypto;
+    if crypto.cipher != "aes-128-ctr" || crypto.kdf != "scrypt" {
+        return Err(corrupted(format!("unsupported cipher {} or key derivation {}", crypto.cipher, crypto.kdf)));
+    }
+    let kdf = &crypto.kdfparams;
+    if !kdf.n.is_power_of_two() || kdf.dklen < 32 {
+        return Err(corrupted("invalid scrypt parameters"));
+    }
+    if kdf.n.trailing_zeros() > MAX_SCRYPT_LOG_N || kdf.r > MAX_SCRYPT_R || kdf.p > MAX_SCRYPT_P || kdf.dklen > MAX_SCRYPT_DKLEN {
+        return Err(corrupted(format!("scrypt parameters n={}, r={}, p={} exceed the supported ones", kdf.n, kdf.r, kdf.p)));
+    }
+    let params = scrypt::Params::new(kdf.n.trailing_zeros() as u8, kdf.r, kdf.p, kdf.dklen).map_err(|e| corrupted(format!("invalid scrypt parameters: {}", e)))?;
+    let mut derived_key = vec![0u8; kdf.dklen];
+    scrypt::scrypt(password.as_bytes(), &decode_hex("salt", &kdf.salt)?, &params, &mut derived_key).map_err(|e| corrupted(format!("invalid scrypt parameters: {}", e)))?;
+
+    let mut keys = decode_hex("ciphertext", &crypto.ciphertext)?;
+    let mac = Keccak256::new().chain_update(&derived_key[16..32]).chain_update(&keys).finalize();
+    if mac.as_slice() != decode_hex("mac", &crypto.mac)?.as_slice() {
+        return Err(Error::IdentityIssue("the identity file cannot be decrypted with the IDENTITY_PASSWORD of the pool".to_owned()));
+    }
+    let iv = decode_hex("iv", &crypto.cipherparams.iv)?;
+    let mut cipher = Aes128Ctr::new_from_slices(&derived_key[..16], &iv).map_err(|e| corrupted(format!("invalid iv: {}", e)))?;
+    cipher.apply_keystream(&mut keys);
+
+    let chain_key = if keys.len() == 32 {
+        keys
+    } else {
+        let private_keys: PrivateKeys = serde_json::from_slice(&keys).map_err(|e| corrupted(format!("unexpected content: {}", e)))?;
+        decode_hex("chain_key", &private_keys.chain_key)?
+    };
+    chain_key.try_into().map_err(|_| corrupted("the chain key is not 32 bytes long"))
+}
+
+/// Ethereum address of the node owning the chain key
+pub fn node_address(chain_key: &[u8; 32]) -> Result<String, Error> {
+    let secret_key = k256::SecretKey::from_slice(chain_key).map_err(|e| corrupted(format!("invalid chain key: {}", e)))?;
+    let public_key = secret_key.public_key().to_encoded_point(false);
+    let hash = Keccak256::digest(&public_key.as_bytes()[1..]);
+    Ok(format!("0x{}", hex::encode(&hash[12..])))
+}
+
+/// Password encrypting the identities of the pool, if its secret holds one
+pub async fn get_identity_password(client: Client, identity_pool: &IdentityPool) -> Result<Option<String>, Error> {
+    let api: Api<Secret> = Api::namespaced(client, &identity_pool.namespace().unwrap());
+    let password = api
+        .get_opt(&identity_pool.spec.secret_name)
+        .await?
+        .and_then(|secret| secret.data)
+        .and_then(|data| data.get(constants::IDENTITY_POOL_IDENTITY_PASSWORD_REF_KEY).cloned());
+    password
+        .map(|password| String::from_utf8(password.0).map_err(|_| Error::IdentityIssue(format!("the IDENTITY_PASSWORD of the pool {} is not valid UTF-8", identity_pool.name_any()))))
+        .transpose()
+}
+
+/// Checks that the identity file decrypts with the password of the pool and belongs to the node address of the identity.
+/// Identities of pools whose secret holds no password are not checked
+pub async fn verify_identity_file(client: Client, identity_hoprd: &IdentityHoprd, identity_pool: &IdentityPool) -> Result<(), Error> {
+    let Some(password) = get_identity_password(client.clone(), identity_pool).await? else {
+        return Ok(());
+    };
+    let identity_file = identity_hoprd_secret::get_identity_file(client, identity_hoprd).await?;
+    let derived_address = node_address(&decrypt_chain_key(&identity_file, &password).await?)?;
+    if !derived_address.eq_ignore_ascii_case(&identity_hoprd.spec.node_address) {
+        return Err(Error::IdentityIssue(format!("the identity file belongs to node {} instead of {}", derived_address, identity_hoprd.spec.node_address)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // First account of the anvil and hardhat test mnemonic
+    const CHAIN_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const NODE_ADDRESS: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+
+    /// Encrypts the keys the way hoprd does, with cheap scrypt parameters
+    fn keystore(keys: &[u8], password: &str) -> Vec<u8> {
+        let salt = [7u8; 32];
+        let iv = [9u8; 16];
+        let mut derived_key = [0u8; 32];
+        scrypt::scrypt(password.as_bytes(), &salt, &scrypt::Params::new(4, 8, 1, 32).unwrap(), &mut derived_key).unwrap();
+        let mut ciphertext = keys.to_vec();
+        Aes128Ctr::new_from_slices(&derived_key[..16], &iv).unwrap().apply_keystream(&mut ciphertext);
+        let mac = Keccak256::new().chain_update(&derived_key[16..]).chain_update(&ciphertext).finalize();
+        serde_json::to_vec(&json!({
+            "crypto": {
+                "cipher": "aes-128-ctr",
+                "cipherparams": { "iv": hex::encode(iv) },
+                "ciphertext": hex::encode(ciphertext),
+                "kdf": "scrypt",
+                "kdfparams": { "dklen": 32, "n": 16, "p": 1, "r": 8, "salt": hex::encode(salt) },
+                "mac": hex::encode(mac)
+            },
+            "id": "b73780dc-d70d-4cf2-a14f-be8e56700e41",
+            "version": 3
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_decrypt_identity_file() {
+        let keys = json!({ "chain_key": CHAIN_KEY, "packet_key": "11".repeat(32), "version": 2 }).to_string();
+        let identity_file = keystore(keys.as_bytes(), "SuperSecret");
+        let chain_key = decrypt_chain_key(&identity_file, "SuperSecret").await.unwrap();
+        assert_eq!(node_address(&chain_key).unwrap(), NODE_ADDRESS);
+
+        let legacy_identity_file = keystore(&hex::decode(CHAIN_KEY).unwrap(), "SuperSecret");
+        assert_eq!(decrypt_chain_key_blocking(&legacy_identity_file, "SuperSecret").unwrap(), chain_key);
+
+        let bad_password = decrypt_chain_key_blocking(&identity_file, "Secret").unwrap_err().to_string();
+        assert!(bad_password.contains("cannot be decrypted with the IDENTITY_PASSWORD"), "{}", bad_password);
+        let corrupted = decrypt_chain_key_blocking(b"{\"id\": 1}", "SuperSecret").unwrap_err().to_string();
+        assert!(corrupted.contains("the identity file is corrupted"), "{}", corrupted);
+        let wrong_content = decrypt_chain_key_blocking(&keystore(b"{\"version\": 2}", "SuperSecret"), "SuperSecret").unwrap_err().to_string();
+        assert!(wrong_content.contains("unexpected content"), "{}", wrong_content);
+        let mut expensive_identity_file: serde_json::Value = serde_json::from_slice(&identity_file).unwrap();
+        expensive_identity_file["crypto"]["kdfparams"]["n"] = json!(1 << 24);
+        let expensive = decrypt_chain_key_blocking(&serde_json::to_vec(&expensive_identity_file).unwrap(), "SuperSecret").unwrap_err().to_string();
+        assert!(expensive.contains("exceed the supported ones"), "{}", expensive);
+    }
+}
@@ -2,8 +2,9 @@ use crate::events::{IdentityHoprdEventEnum, IdentityPoolEventEnum};
 use crate::identity_pool::identity_pool_resource::{IdentityPool, IdentityPoolPhaseEnum, IdentityPoolStatus};
 use crate::model::Error;
 use crate::{constants, context_data::ContextData};
-use crate::{identity_hoprd::identity_hoprd_persistence, resource_generics};
-use k8s_openapi::api::core::v1::PersistentVolumeClaim;
+use crate::identity_hoprd::identity_hoprd_validation::{self, IdentityHoprdValidationStatus, RpcClient};
+use crate::{identity_hoprd::identity_hoprd_drain, identity_hoprd::identity_hoprd_keystore, identity_hoprd::identity_hoprd_persistence, identity_hoprd::identity_hoprd_secret, resource_generics};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, SecretKeySelector};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
 use kube::api::DeleteParams;
 use kube::core::ObjectMeta;
@@ -33,7 +34,11 @@ use tracing::{debug, error, info, warn};
 #[serde(rename_all = "camelCase")]
 pub struct IdentityHoprdSpec {
     pub identity_pool_name: String,
+    /// Contents of the identity file encoded in base64. Not needed when `identity_file_secret_ref` is set
+    #[serde(default)]
     pub identity_file: String,
+    /// Key of a Secret holding the identity file, as an alternative to the inline `identity_file`
+    pub identity_file_secret_ref: Option<SecretKeySelector>,
     pub node_address: String,
     pub safe_address: String,
     pub module_address: String,
@@ -46,6 +51,8 @@ pub struct IdentityHoprdStatus {
     pub phase: IdentityHoprdPhaseEnum,
     pub observed_generation: i64,
     pub hoprd_node_name: Option<String>,
+    /// Outcome of the last on-chain validation
+    pub validation: Option<IdentityHoprdValidationStatus>,
 }
 
 impl Default for IdentityHoprdStatus {
@@ -54,6 +61,7 @@ impl Default for IdentityHoprdStatus {
             phase: IdentityHoprdPhaseEnum::Initialized,
             observed_generation: 0,
             hoprd_node_name: None,
+            validation: None,
         }
     }
 }
@@ -64,6 +72,10 @@ pub enum IdentityHoprdPhaseEnum {
     Initialized,
     /// The IdentityHoprd is failed
     Failed,
+    /// The IdentityHoprd is being validated on-chain
+    Validating,
+    /// The IdentityHoprd did not pass the on-chain validation, which is retried periodically
+    Invalid,
     // The IdentityHoprd is ready to be used
     Ready,
     // The IdentityHoprd is being used
@@ -77,6 +89,8 @@ impl Display for IdentityHoprdPhaseEnum {
         match self {
             IdentityHoprdPhaseEnum::Initialized => write!(f, "Initialized"),
             IdentityHoprdPhaseEnum::Failed => write!(f, "Failed"),
+            IdentityHoprdPhaseEnum::Validating => write!(f, "Validating"),
+            IdentityHoprdPhaseEnum::Invalid => write!(f, "Invalid"),
             IdentityHoprdPhaseEnum::Ready => write!(f, "Ready"),
             IdentityHoprdPhaseEnum::InUse => write!(f, "InUse"),
             IdentityHoprdPhaseEnum::Deleting => write!(f, "Deleting"),
@@ -103,18 +117,15 @@ impl IdentityHoprd {
         let identity_pool_name: String = self.spec.identity_pool_name.to_owned();
         self.check_identity_pool(context_data.clone()).await?;
         self.check_pvc(context_data.clone()).await?;
+        identity_hoprd_secret::get_identity_file(client.clone(), self).await?;
 
         info!("Starting to create identity {identity_name} in namespace {identity_namespace}");
         resource_generics::add_finalizer(client.clone(), self).await;
         self.add_owner_reference(client.clone()).await?;
         identity_hoprd_persistence::create_pvc(context_data.clone(), self).await?;
+        identity_hoprd_secret::sync_secret(context_data.clone(), self).await?;
         context_data.send_event(self, IdentityHoprdEventEnum::Initialized, None).await;
         self.update_phase(client.clone(), IdentityHoprdPhaseEnum::Initialized, None).await?;
-        // TODO: Validate data
-        // - Is registered in network
-        // - Is funded (safe and node)
-        // - SafeAddress is correct
-        // - ModuleAddress is correct
 
         // Update pool to decrease identities
         let mut updated = false;
@@ -138,8 +149,7 @@ impl IdentityHoprd {
             .await;
         if updated {
             // These instructions need to be done out of the context_data lock
-            context_data.send_event(self, IdentityHoprdEventEnum::Ready, None).await;
-            self.update_phase(client.clone(), IdentityHoprdPhaseEnum::Ready, None).await?;
+            self.become_ready(context_data.clone()).await?;
             info!("IdentityHoprd {identity_name} in namespace {identity_namespace} successfully created");
         } else {
             error!("Identity pool {} not exists in namespace {}", identity_pool_name, &self.namespace().unwrap());
@@ -164,6 +174,10 @@ impl IdentityHoprd {
                             self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Failed, None).await?;
                             Err(Error::HoprdConfigError(format!("IdentityHoprd {} in namespace {} has been modified with changes in immutable fields", self.name_any(), self.namespace().unwrap())))
                         } else if self.spec != previous_cluster_hoprd.spec {
+                            identity_hoprd_secret::sync_secret(context_data.clone(), self).await?;
+                            if status.phase.eq(&IdentityHoprdPhaseEnum::Ready) {
+                                self.become_ready(context_data.clone()).await?;
+                            }
                             info!("IdentityHoprd {} in namespace {} has been successfully modified", self.name_any(), self.namespace().unwrap());
                             Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
                         } else {
@@ -185,10 +199,14 @@ impl IdentityHoprd {
             }
         } else if status.phase.eq(&IdentityHoprdPhaseEnum::Failed) {
             // Assumes that the next modification of the resource is to recover to a good state
-            context_data.send_event(self, IdentityHoprdEventEnum::Ready, None).await;
-            self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Ready, None).await?;
+            self.become_ready(context_data.clone()).await?;
             warn!("Detected a change in IdentityHoprd {identity_name}. Automatically recovering to a Ready phase");
             Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+        } else if status.phase.eq(&IdentityHoprdPhaseEnum::Validating) || status.phase.eq(&IdentityHoprdPhaseEnum::Invalid) {
+            // The modification might fix the addresses, so the identity is validated again
+            identity_hoprd_secret::sync_secret(context_data.clone(), self).await?;
+            self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Validating, None).await?;
+            Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
         } else {
             error!("The IdentityHoprd {} in namespace {} cannot be modified", self.name_any(), self.namespace().unwrap());
             Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
@@ -202,8 +220,13 @@ impl IdentityHoprd {
         let client: Client = context_data.client.clone();
         if let Some(status) = self.status.as_ref() {
             if !status.phase.eq(&IdentityHoprdPhaseEnum::InUse) {
-                context_data.send_event(self, IdentityHoprdEventEnum::Deleting, None).await;
-                self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Deleting, None).await?;
+                if !status.phase.eq(&IdentityHoprdPhaseEnum::Deleting) {
+                    context_data.send_event(self, IdentityHoprdEventEnum::Deleting, None).await;
+                    self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Deleting, None).await?;
+                }
+                if let Some(action) = identity_hoprd_drain::drain_funds(context_data.clone(), self, &self.get_identity_pool(client.clone()).await?).await? {
+                    return Ok(action);
+                }
                 info!("Starting to delete identity {identity_name} from namespace {identity_namespace}");
                 { // Delete PVC
                     let pvc_api: Api<PersistentVolumeClaim> = Api::namespaced(client.clone(), &self.namespace().unwrap());
@@ -235,7 +258,6 @@ impl IdentityHoprd {
                         Some(identity_name.to_owned()),
                     )
                     .await;
-                // TODO Drain funds
                 resource_generics::delete_finalizer(client.clone(), self).await?;
                 info!("Identity {identity_name} in namespace {identity_namespace} has been successfully deleted");
                 Ok(Action::await_change()) // Makes no sense to delete after a successful delete, as the resource is gone
@@ -250,6 +272,71 @@ impl IdentityHoprd {
         }
     }
 
+    /// Keeps the identity Secret in sync with the identity file, which might be read from another Secret
+    pub async fn sync(&self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        let phase = self.status.as_ref().map(|status| status.phase);
+        if phase == Some(IdentityHoprdPhaseEnum::Ready) || phase == Some(IdentityHoprdPhaseEnum::InUse) {
+            if let Err(error) = identity_hoprd_secret::sync_secret(context_data.clone(), self).await {
+                context_data.send_event(self, IdentityHoprdEventEnum::Failed, Some(error.to_string())).await;
+                return Err(error);
+            }
+        }
+        Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+    }
+
+    /// Moves the identity to the Ready phase, or to the Validating phase when its network is validated on-chain.
+    /// Identities whose file does not decrypt into their node address are failed instead
+    async fn become_ready(&self, context_data: Arc<ContextData>) -> Result<(), Error> {
+        let identity_pool = self.get_identity_pool(context_data.client.clone()).await?;
+        match identity_hoprd_keystore::verify_identity_file(context_data.client.clone(), self, &identity_pool).await {
+            Err(Error::IdentityIssue(reason)) => {
+                warn!("IdentityHoprd {} has an invalid identity file: {reason}", self.name_any());
+                context_data.send_event(self, IdentityHoprdEventEnum::Failed, Some(reason)).await;
+                return self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Failed, None).await;
+            }
+            Err(error) => return Err(error),
+            Ok(()) => {}
+        }
+        if context_data.config.networks.contains_key(&identity_pool.spec.network) {
+            context_data.send_event(self, IdentityHoprdEventEnum::Validating, None).await;
+            self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Validating, None).await
+        } else {
+            context_data.send_event(self, IdentityHoprdEventEnum::Ready, None).await;
+            self.update_phase(context_data.client.clone(), IdentityHoprdPhaseEnum::Ready, None).await
+        }
+    }
+
+    /// Validates the identity on-chain, keeping it out of the pool until the node is registered, linked to its safe and module, and funded
+    pub async fn validate(&self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        let client: Client = context_data.client.clone();
+        let identity_name = self.name_any();
+        let phase = self.status.as_ref().map_or(IdentityHoprdPhaseEnum::Validating, |status| status.phase);
+        let identity_pool = self.get_identity_pool(client.clone()).await?;
+        let Some(network) = context_data.config.networks.get(&identity_pool.spec.network) else {
+            info!("Network {} is no longer validated on-chain, IdentityHoprd {identity_name} is ready", identity_pool.spec.network);
+            context_data.send_event(self, IdentityHoprdEventEnum::Ready, None).await;
+            self.update_phase(client.clone(), IdentityHoprdPhaseEnum::Ready, None).await?;
+            return Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)));
+        };
+        let rpc_client = RpcClient::new(context_data.config.rpc_provider_url(&identity_pool.spec.network))?;
+        let validation = identity_hoprd_validation::validate(&rpc_client, network, &self.spec).await?;
+        let api: Api<IdentityHoprd> = Api::namespaced(client.clone(), &self.namespace().unwrap());
+        api.patch_status(&identity_name, &PatchParams::default(), &Patch::Merge(json!({ "status": { "validation": validation } }))).await?;
+        if validation.is_valid() {
+            info!("IdentityHoprd {identity_name} passed the on-chain validation");
+            context_data.send_event(self, IdentityHoprdEventEnum::Ready, None).await;
+            self.update_phase(client.clone(), IdentityHoprdPhaseEnum::Ready, None).await?;
+            Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+        } else {
+            if phase != IdentityHoprdPhaseEnum::Invalid {
+                context_data.send_event(self, IdentityHoprdEventEnum::Invalid, Some(validation.problems.join(", "))).await;
+                self.update_phase(client.clone(), IdentityHoprdPhaseEnum::Invalid, None).await?;
+            }
+            warn!("IdentityHoprd {identity_name} did not pass the on-chain validation: {}", validation.problems.join(", "));
+            Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY)))
+        }
+    }
+
     /// Check the fileds that cannot be modifed
     fn changed_inmutable_fields(&self, spec: &IdentityHoprdSpec) -> bool {
         if !self.spec.identity_pool_name.eq(&spec.identity_pool_name) {
@@ -273,6 +360,7 @@ impl IdentityHoprd {
                 phase,
                 observed_generation: self.metadata.generation.unwrap_or(0),
                 hoprd_node_name: hoprd_name,
+                validation: self.status.as_ref().and_then(|status| status.validation.clone()),
             };
             let patch = Patch::Merge(json!({ "status": status }));
 
@@ -342,7 +430,8 @@ impl IdentityHoprd {
         let identity_namespace = self.namespace().unwrap();
         let api: Api<IdentityPool> = Api::namespaced(context_data.client.clone(), &identity_namespace);
         if let Some(identity_pool) = api.get_opt(&self.spec.identity_pool_name).await? {
-            if identity_pool.status.is_some() && identity_pool.status.as_ref().unwrap().phase.eq(&IdentityPoolPhaseEnum::Ready) {
+            // Identities are still accepted by pools lacking identities, as they bring them back in sync
+            if identity_pool.status.as_ref().is_some_and(|status| matches!(status.phase, IdentityPoolPhaseEnum::Ready | IdentityPoolPhaseEnum::OutOfSync)) {
                 Ok(())
             } else {
                 warn!(
@@ -0,0 +1,133 @@
+use crate::context_data::ContextData;
+use base64::{engine::general_purpose, Engine as _};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use k8s_openapi::ByteString;
+use kube::api::{ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Resource, ResourceExt};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprd;
+use crate::model::Error;
+use crate::{constants, utils};
+
+/// Name of the Secret holding the identity file of an IdentityHoprd, mounted into the pods of the node using it
+pub fn secret_name(identity_hoprd: &IdentityHoprd) -> String {
+    format!("{}-identity", identity_hoprd.name_any())
+}
+
+/// Reads the identity file, either from the referenced Secret key or decoding the inline `identityFile`
+pub async fn get_identity_file(client: Client, identity_hoprd: &IdentityHoprd) -> Result<Vec<u8>, Error> {
+    let referenced_secret = match identity_hoprd.spec.identity_file_secret_ref.as_ref() {
+        Some(selector) => {
+            let api: Api<Secret> = Api::namespaced(client, &identity_hoprd.namespace().unwrap());
+            api.get_opt(&selector.name).await?
+        }
+        None => None,
+    };
+    read_identity_file(identity_hoprd, referenced_secret)
+}
+
+/// Extracts the identity file from the Secret referenced by the IdentityHoprd, if any, or from its inline `identityFile`
+fn read_identity_file(identity_hoprd: &IdentityHoprd, referenced_secret: Option<Secret>) -> Result<Vec<u8>, Error> {
+    let identity_name = identity_hoprd.name_any();
+    match identity_hoprd.spec.identity_file_secret_ref.as_ref() {
+        Some(selector) => referenced_secret
+            .and_then(|secret| secret.data)
+            .and_then(|data| data.get(&selector.key).cloned())
+            .map(|identity_file| identity_file.0)
+            .ok_or_else(|| Error::IdentityHoprdSecretError(format!("The key '{}' of Secret '{}' referenced by IdentityHoprd {} does not exist", selector.key, selector.name, identity_name))),
+        None if identity_hoprd.spec.identity_file.is_empty() => Err(Error::IdentityHoprdSecretError(format!("IdentityHoprd {} defines neither an identityFile nor an identityFileSecretRef", identity_name))),
+        None => general_purpose::STANDARD
+            .decode(&identity_hoprd.spec.identity_file)
+            .map_err(|e| Error::IdentityHoprdSecretError(format!("The identityFile of IdentityHoprd {} is not valid base64: {}", identity_name, e))),
+    }
+}
+
+/// Creates or updates the Secret owned by the IdentityHoprd with its identity file
+pub async fn sync_secret(context: Arc<ContextData>, identity_hoprd: &IdentityHoprd) -> Result<(), Error> {
+    let namespace: String = identity_hoprd.namespace().unwrap();
+    let name: String = secret_name(identity_hoprd);
+    let identity_file = get_identity_file(context.client.clone(), identity_hoprd).await?;
+    let data: BTreeMap<String, ByteString> = BTreeMap::from([(constants::IDENTITY_HOPRD_SECRET_KEY.to_owned(), ByteString(identity_file))]);
+    let api: Api<Secret> = Api::namespaced(context.client.clone(), &namespace);
+    match api.get_opt(&name).await? {
+        Some(secret) if secret.data.as_ref() == Some(&data) => Ok(()),
+        Some(_) => {
+            info!("Updating the identity Secret {name} in namespace {namespace}");
+            api.patch(&name, &PatchParams::default(), &Patch::Merge(json!({ "data": data }))).await?;
+            Ok(())
+        }
+        None => {
+            info!("Creating the identity Secret {name} in namespace {namespace}");
+            let owner_references: Option<Vec<OwnerReference>> = Some(vec![identity_hoprd.controller_owner_ref(&()).unwrap()]);
+            let labels: Option<BTreeMap<String, String>> = Some(utils::common_lables(identity_hoprd.spec.identity_pool_name.to_owned(), Some(identity_hoprd.name_any()), None));
+            let secret = Secret {
+                metadata: ObjectMeta {
+                    name: Some(name),
+                    namespace: Some(namespace),
+                    labels,
+                    owner_references,
+                    ..ObjectMeta::default()
+                },
+                data: Some(data),
+                type_: Some("Opaque".to_owned()),
+                ..Secret::default()
+            };
+            api.create(&PostParams::default(), &secret).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprdSpec;
+    use k8s_openapi::api::core::v1::SecretKeySelector;
+
+    fn identity_hoprd(identity_file: &str, identity_file_secret_ref: Option<SecretKeySelector>) -> IdentityHoprd {
+        IdentityHoprd::new(
+            "core-rotsee-1",
+            IdentityHoprdSpec {
+                identity_file: identity_file.to_owned(),
+                identity_file_secret_ref,
+                ..IdentityHoprdSpec::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_read_identity_file() {
+        let inline = identity_hoprd(&general_purpose::STANDARD.encode("{\"crypto\":{}}"), None);
+        assert_eq!(read_identity_file(&inline, None).unwrap(), b"{\"crypto\":{}}");
+
+        let selector = SecretKeySelector {
+            name: "core-rotsee-1-keystore".to_owned(),
+            key: "identity".to_owned(),
+            ..SecretKeySelector::default()
+        };
+        // The referenced Secret takes precedence over the inline identity file
+        let referencing = identity_hoprd(&general_purpose::STANDARD.encode("inline"), Some(selector));
+        let secret = Secret {
+            data: Some(BTreeMap::from([("identity".to_owned(), ByteString(b"referenced".to_vec()))])),
+            ..Secret::default()
+        };
+        assert_eq!(read_identity_file(&referencing, Some(secret)).unwrap(), b"referenced");
+        let missing_key = Secret {
+            data: Some(BTreeMap::from([("other".to_owned(), ByteString(b"referenced".to_vec()))])),
+            ..Secret::default()
+        };
+        assert!(matches!(read_identity_file(&referencing, Some(missing_key)), Err(Error::IdentityHoprdSecretError(message)) if message.contains("'identity' of Secret 'core-rotsee-1-keystore'")));
+        assert!(read_identity_file(&referencing, None).is_err());
+
+        let invalid_base64 = identity_hoprd("not base64!", None);
+        assert!(matches!(read_identity_file(&invalid_base64, None), Err(Error::IdentityHoprdSecretError(message)) if message.contains("not valid base64")));
+
+        let neither = identity_hoprd("", None);
+        assert!(matches!(read_identity_file(&neither, None), Err(Error::IdentityHoprdSecretError(message)) if message.contains("neither an identityFile nor an identityFileSecretRef")));
+    }
+}
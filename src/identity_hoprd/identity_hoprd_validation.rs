@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use tracing::debug;
+
+use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprdSpec;
+use crate::operator_config::NetworkConfig;
+use crate::{constants, model::Error};
+
+const WEI_DECIMALS: usize = 18;
+/// First entry of the linked list of modules of a safe
+const SENTINEL_MODULES: &str = "0x0000000000000000000000000000000000000001";
+/// Page size when listing the modules of a safe, which hold a single module when deployed by hopli
+const MAX_SAFE_MODULES: u32 = 10;
+
+/// Outcome of the last on-chain validation of an identity
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityHoprdValidationStatus {
+    /// Whether the node is registered with its safe in the node safe registry
+    pub registered: bool,
+    /// Whether the module is enabled in the safe and includes the node
+    pub module_linked: bool,
+    /// Native balance of the node, in ether
+    pub node_native_balance: String,
+    /// HOPR balance of the safe, in ether
+    pub safe_token_balance: String,
+    /// Reasons why the identity cannot be used yet
+    pub problems: Vec<String>,
+    /// Time of the validation, in RFC 3339 format
+    pub time: String,
+}
+
+impl IdentityHoprdValidationStatus {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// JSON-RPC client reading the on-chain state of the identities
+pub struct RpcClient {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl RpcClient {
+    pub fn new(url: String) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(constants::OPERATOR_RPC_TIMEOUT))
+            .build()
+            .map_err(|e| Error::RpcError(format!("Could not build the client for {}: {}", url, e)))?;
+        Ok(RpcClient { client, url })
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<String, Error> {
+        debug!("Querying {} on RPC provider {}", method, self.url);
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::RpcError(format!("Request {} to {} failed: {}", method, self.url, e)))?
+            .json()
+            .await
+            .map_err(|e| Error::RpcError(format!("Invalid response to {} from {}: {}", method, self.url, e)))?;
+        if let Some(error) = response.get("error") {
+            return Err(Error::RpcError(format!("Request {} to {} failed: {}", method, self.url, error)));
+        }
+        response
+            .get("result")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| Error::RpcError(format!("Response to {} from {} has no result", method, self.url)))
+    }
+
+    /// Native balance of the address, in wei
+    pub async fn get_balance(&self, address: &str) -> Result<u128, Error> {
+        let result = self.request("eth_getBalance", json!([address, "latest"])).await?;
+        u128::from_str_radix(result.trim_start_matches("0x"), 16).map_err(|e| Error::RpcError(format!("Invalid balance {}: {}", result, e)))
+    }
+
+    /// Token balance of the address, in wei
+    pub async fn get_token_balance(&self, token: &str, address: &str) -> Result<u128, Error> {
+        Ok(decode_u128(&self.call(token, "balanceOf(address)", &[address]).await?))
+    }
+
+    /// Current gas price of the network, in wei
+    pub async fn get_gas_price(&self) -> Result<u128, Error> {
+        let result = self.request("eth_gasPrice", json!([])).await?;
+        u128::from_str_radix(result.trim_start_matches("0x"), 16).map_err(|e| Error::RpcError(format!("Invalid gas price {}: {}", result, e)))
+    }
+
+    /// Calls a view function whose arguments are all addresses, returning the raw output. Calls to addresses without code return no output
+    pub async fn call(&self, to: &str, signature: &str, arguments: &[&str]) -> Result<Vec<u8>, Error> {
+        self.call_data(to, signature, encode_call(signature, arguments)?).await
+    }
+
+    async fn call_data(&self, to: &str, signature: &str, data: String) -> Result<Vec<u8>, Error> {
+        let result = self.request("eth_call", json!([{ "to": to, "data": data }, "latest"])).await?;
+        hex::decode(result.trim_start_matches("0x")).map_err(|e| Error::RpcError(format!("Invalid output of {}: {}", signature, e)))
+    }
+
+    /// Safe with which the node is registered in the node safe registry
+    pub async fn get_node_safe(&self, node_safe_registry_address: &str, node_address: &str) -> Result<Option<String>, Error> {
+        let safe_address = decode_address(&self.call(node_safe_registry_address, "nodeToSafe(address)", &[node_address]).await?);
+        Ok(Some(safe_address).filter(|safe_address| *safe_address != format!("0x{}", "0".repeat(40))))
+    }
+
+    /// Modules enabled in the safe
+    pub async fn get_safe_modules(&self, safe_address: &str) -> Result<Vec<String>, Error> {
+        let signature = "getModulesPaginated(address,uint256)";
+        let data = format!("{}{:064x}", encode_call(signature, &[SENTINEL_MODULES])?, MAX_SAFE_MODULES);
+        let output = self.call_data(safe_address, signature, data).await?;
+        // The output holds the offset of the array of modules, followed by the next page
+        let offset = decode_u128(&output) as usize;
+        let modules = output.get(offset..).unwrap_or_default();
+        let length = decode_u128(modules) as usize;
+        Ok((0..length.min(MAX_SAFE_MODULES as usize)).map(|index| decode_address(modules.get(32 * (index + 1)..).unwrap_or_default())).collect())
+    }
+}
+
+/// Encodes the call data of a function taking addresses as arguments
+fn encode_call(signature: &str, arguments: &[&str]) -> Result<String, Error> {
+    let mut data = Keccak256::digest(signature.as_bytes())[..4].to_vec();
+    for argument in arguments {
+        let address = hex::decode(argument.trim_start_matches("0x")).ok().filter(|address| address.len() == 20).ok_or_else(|| Error::IdentityIssue(format!("{} is not a valid address", argument)))?;
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&address);
+    }
+    Ok(format!("0x{}", hex::encode(data)))
+}
+
+/// First word of an output, which is all zeros when the call returned nothing
+fn first_word(output: &[u8]) -> [u8; 32] {
+    output.get(..32).and_then(|word| word.try_into().ok()).unwrap_or([0u8; 32])
+}
+
+fn decode_address(output: &[u8]) -> String {
+    format!("0x{}", hex::encode(&first_word(output)[12..]))
+}
+
+fn decode_bool(output: &[u8]) -> bool {
+    first_word(output)[31] != 0
+}
+
+fn decode_u128(output: &[u8]) -> u128 {
+    let word = first_word(output);
+    if word[..16].iter().any(|byte| *byte != 0) {
+        u128::MAX
+    } else {
+        u128::from_be_bytes(word[16..].try_into().unwrap())
+    }
+}
+
+/// Parses an amount in ether, such as `0.01`, into wei
+pub fn parse_ether(amount: &str) -> Result<u128, Error> {
+    let invalid = || Error::UserInputError(format!("'{}' is not a valid amount in ether", amount));
+    let (integer, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+    if integer.is_empty() && fraction.is_empty() || fraction.len() > WEI_DECIMALS || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let integer: u128 = if integer.is_empty() { 0 } else { integer.parse().map_err(|_| invalid())? };
+    let fraction: u128 = format!("{:0<width$}", fraction, width = WEI_DECIMALS).parse().map_err(|_| invalid())?;
+    integer.checked_mul(10u128.pow(WEI_DECIMALS as u32)).and_then(|wei| wei.checked_add(fraction)).ok_or_else(invalid)
+}
+
+/// Formats an amount in wei as ether, without trailing zeros
+pub fn format_ether(wei: u128) -> String {
+    let unit = 10u128.pow(WEI_DECIMALS as u32);
+    let fraction = format!("{:0>width$}", wei % unit, width = WEI_DECIMALS);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (wei / unit).to_string()
+    } else {
+        format!("{}.{}", wei / unit, fraction)
+    }
+}
+
+/// Checks that the value is an address, as addresses end up in the scripts of the jobs
+pub fn check_address(address: &str) -> Result<(), Error> {
+    if address.len() == 42 && address.starts_with("0x") && address[2..].chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(Error::UserInputError(format!("'{}' is not a valid address", address)))
+    }
+}
+
+/// Checks that the node is registered with its safe, that the module links both, and that the node and the safe are funded
+pub async fn validate(rpc: &RpcClient, network: &NetworkConfig, identity: &IdentityHoprdSpec) -> Result<IdentityHoprdValidationStatus, Error> {
+    let mut problems: Vec<String> = Vec::new();
+
+    let registered_safe = decode_address(&rpc.call(&network.node_safe_registry_address, "nodeToSafe(address)", &[&identity.node_address]).await?);
+    let registered = registered_safe.eq_ignore_ascii_case(&identity.safe_address);
+    if registered_safe == format!("0x{}", "0".repeat(40)) {
+        problems.push(format!("Node {} is not registered in the node safe registry", identity.node_address));
+    } else if !registered {
+        problems.push(format!("Node {} is registered with safe {} instead of {}", identity.node_address, registered_safe, identity.safe_address));
+    }
+
+    let module_enabled = decode_bool(&rpc.call(&identity.safe_address, "isModuleEnabled(address)", &[&identity.module_address]).await?);
+    if !module_enabled {
+        problems.push(format!("Module {} is not enabled in safe {}", identity.module_address, identity.safe_address));
+    }
+    let node_included = decode_bool(&rpc.call(&identity.module_address, "isNode(address)", &[&identity.node_address]).await?);
+    if !node_included {
+        problems.push(format!("Node {} is not included in module {}", identity.node_address, identity.module_address));
+    }
+
+    let node_native_balance = rpc.get_balance(&identity.node_address).await?;
+    let safe_token_balance = rpc.get_token_balance(&network.token_address, &identity.safe_address).await?;
+    for (name, balance, minimum) in [
+        ("Native balance of the node", node_native_balance, &network.min_node_native_balance),
+        ("HOPR balance of the safe", safe_token_balance, &network.min_safe_token_balance),
+    ] {
+        if let Some(minimum) = minimum {
+            if balance < parse_ether(minimum)? {
+                problems.push(format!("{} is {}, below the minimum of {}", name, format_ether(balance), minimum));
+            }
+        }
+    }
+
+    Ok(IdentityHoprdValidationStatus {
+        registered,
+        module_linked: module_enabled && node_included,
+        node_native_balance: format_ether(node_native_balance),
+        safe_token_balance: format_ether(safe_token_balance),
+        problems,
+        time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Json, Router};
+
+    const NODE: &str = "0x1111111111111111111111111111111111111111";
+    const SAFE: &str = "0x2222222222222222222222222222222222222222";
+    const MODULE: &str = "0x3333333333333333333333333333333333333333";
+    const REGISTRY: &str = "0x4444444444444444444444444444444444444444";
+    const TOKEN: &str = "0x5555555555555555555555555555555555555555";
+
+    fn word(value: &str) -> String {
+        format!("0x{:0>64}", value.trim_start_matches("0x"))
+    }
+
+    /// Mocks a chain where the node is registered and linked, funded with 0.5 xDai and whose safe holds 10 HOPR
+    async fn rpc(Json(request): Json<Value>) -> Json<Value> {
+        let params = &request["params"];
+        let result = match request["method"].as_str().unwrap() {
+            "eth_getBalance" => format!("0x{:x}", parse_ether("0.5").unwrap()),
+            "eth_call" => {
+                let to = params[0]["to"].as_str().unwrap();
+                let data = params[0]["data"].as_str().unwrap();
+                let selector = &data[..10];
+                match to {
+                    REGISTRY if data == encode_call("nodeToSafe(address)", &[NODE]).unwrap() => word(SAFE),
+                    SAFE if selector == &encode_call("getModulesPaginated(address,uint256)", &[]).unwrap()[..10] => {
+                        format!("{}{}{}{}", word("40"), &word("1")[2..], &word("1")[2..], &word(MODULE)[2..])
+                    }
+                    SAFE => word("1"),
+                    MODULE => word("1"),
+                    TOKEN => word(&format!("{:x}", parse_ether("10").unwrap())),
+                    _ => "0x".to_owned(),
+                }
+            }
+            method => panic!("unexpected method {}", method),
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+    }
+
+    fn identity(safe_address: &str) -> IdentityHoprdSpec {
+        IdentityHoprdSpec {
+            node_address: NODE.to_owned(),
+            safe_address: safe_address.to_owned(),
+            module_address: MODULE.to_owned(),
+            ..IdentityHoprdSpec::default()
+        }
+    }
+
+    #[test]
+    fn test_ether_amounts() {
+        assert_eq!(parse_ether("1").unwrap(), 1_000_000_000_000_000_000);
+        assert_eq!(parse_ether("0.01").unwrap(), 10_000_000_000_000_000);
+        assert_eq!(parse_ether(".5").unwrap(), 500_000_000_000_000_000);
+        assert!(parse_ether("1.0000000000000000001").is_err());
+        assert!(parse_ether("1e18").is_err());
+        assert_eq!(format_ether(10_000_000_000_000_000), "0.01");
+        assert_eq!(format_ether(2_000_000_000_000_000_000), "2");
+        assert!(check_address(SAFE).is_ok());
+        assert!(check_address("0x22222222222222222222222222222222222222$(id)").is_err());
+        assert!(check_address("2222222222222222222222222222222222222222").is_err());
+        assert_eq!(encode_call("balanceOf(address)", &[SAFE]).unwrap(), format!("0x70a08231{}", word(SAFE).trim_start_matches("0x")));
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, Router::new().route("/", post(rpc))).await.unwrap() });
+        let client = RpcClient::new(format!("http://{}/", address)).unwrap();
+        let network = NetworkConfig {
+            node_safe_registry_address: REGISTRY.to_owned(),
+            token_address: TOKEN.to_owned(),
+            min_node_native_balance: Some("0.1".to_owned()),
+            min_safe_token_balance: Some("20".to_owned()),
+            ..NetworkConfig::default()
+        };
+
+        let validation = validate(&client, &network, &identity(SAFE)).await.unwrap();
+        assert!(validation.registered);
+        assert!(validation.module_linked);
+        assert_eq!(validation.node_native_balance, "0.5");
+        assert_eq!(validation.problems, vec!["HOPR balance of the safe is 10, below the minimum of 20".to_owned()]);
+        assert_eq!(client.get_node_safe(REGISTRY, NODE).await.unwrap(), Some(SAFE.to_owned()));
+        assert_eq!(client.get_node_safe(REGISTRY, MODULE).await.unwrap(), None);
+        assert_eq!(client.get_safe_modules(SAFE).await.unwrap(), vec![MODULE.to_owned()]);
+
+        let other_safe = "0x6666666666666666666666666666666666666666";
+        let validation = validate(&client, &network, &identity(other_safe)).await.unwrap();
+        assert!(!validation.registered);
+        assert!(!validation.module_linked);
+        assert_eq!(validation.problems[0], format!("Node {} is registered with safe {} instead of {}", NODE, SAFE, other_safe));
+    }
+
+    /// Runs against a local anvil chain, e.g. `anvil & ANVIL_RPC_URL=http://localhost:8545 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_validate_anvil() {
+        let client = RpcClient::new(std::env::var("ANVIL_RPC_URL").unwrap_or("http://localhost:8545".to_owned())).unwrap();
+        // First prefunded account of anvil, with no contracts deployed
+        let mut identity = identity(SAFE);
+        identity.node_address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_owned();
+        let network = NetworkConfig {
+            node_safe_registry_address: REGISTRY.to_owned(),
+            token_address: TOKEN.to_owned(),
+            min_node_native_balance: Some("1".to_owned()),
+            ..NetworkConfig::default()
+        };
+        let validation = validate(&client, &network, &identity).await.unwrap();
+        assert!(!validation.registered);
+        assert_eq!(validation.node_native_balance, "10000");
+        assert_eq!(validation.safe_token_balance, "0");
+        assert_eq!(validation.problems.len(), 3);
+    }
+}
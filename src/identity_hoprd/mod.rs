@@ -1,3 +1,7 @@
 pub mod identity_hoprd_controller;
+mod identity_hoprd_drain;
+pub mod identity_hoprd_keystore;
 mod identity_hoprd_persistence;
+pub mod identity_hoprd_secret;
+pub mod identity_hoprd_validation;
 pub mod identity_hoprd_resource;
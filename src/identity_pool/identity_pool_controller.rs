@@ -1,4 +1,5 @@
 use futures::StreamExt;
+use k8s_openapi::api::batch::v1::Job;
 use kube::{
     api::Api,
     client::Client,
@@ -53,6 +54,8 @@ fn determine_action(identity_pool: &IdentityPool) -> IdentityPoolAction {
         let observed_generation = identity_pool.status.as_ref().map_or(0, |status| status.observed_generation);
         if observed_generation < current_generation {
             IdentityPoolAction::Modify
+        } else if !identity_pool.status.as_ref().unwrap().phase.eq(&IdentityPoolPhaseEnum::Failed) && (identity_pool.spec.provisioning.is_some() || identity_pool.spec.funding.as_ref().is_some_and(|funding| funding.has_thresholds())) {
+            IdentityPoolAction::Sync
         } else {
             IdentityPoolAction::NoOp
         }
@@ -91,10 +94,12 @@ pub async fn run(client: Client, context_data: Arc<ContextData>) {
     let owned_api: Api<IdentityPool> = Api::<IdentityPool>::all(client.clone());
     let service_monitor = Api::<ServiceMonitor>::all(client.clone());
     let identity_hoprd = Api::<IdentityHoprd>::all(client.clone());
+    let job = Api::<Job>::all(client.clone());
 
     Controller::new(owned_api, Config::default())
         .owns(service_monitor, Config::default())
         .owns(identity_hoprd, Config::default())
+        .owns(job, Config::default())
         .shutdown_on_signal()
         .run(reconciler, on_error, context_data)
         .for_each(|reconciliation_result| async move {
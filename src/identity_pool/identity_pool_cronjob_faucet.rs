@@ -58,7 +58,7 @@ pub async fn create_cron_job(context_data: Arc<ContextData>, identity_pool: &Ide
 async fn build_args_line(context_data: Arc<ContextData>, identity_pool: &IdentityPool) -> Option<Vec<String>> {
     let native_amount: String = identity_pool.spec.funding.clone().unwrap().native_amount.to_string();
     let network: String = identity_pool.spec.network.to_owned();
-    let rpc_provider_url: String = context_data.config.hopli_rpc_provider_url.to_owned();
+    let rpc_provider_url: String = context_data.config.rpc_provider_url(&network);
     let command_line: String = format!("PATH=${{PATH}}:/app/hoprnet/.foundry/bin/ /bin/hopli faucet --provider-url {} --network {} --hopr-amount 0 --native-amount \"{}\" --address $(cat /data/addresses.txt)", rpc_provider_url, network, native_amount);
     Some(vec![command_line])
 }
@@ -90,8 +90,8 @@ async fn get_job_template(context_data: Arc<ContextData>, identity_pool: &Identi
             spec: Some(PodSpec {
                 init_containers: Some(vec![Container {
                     name: "kubectl".to_owned(),
-                    image: Some("registry.hub.docker.com/bitnamilegacy/kubectl:1.33".to_owned()),
-                    image_pull_policy: Some("IfNotPresent".to_owned()),
+                    image: Some(context_data.config.images.kubectl.to_owned()),
+                    image_pull_policy: context_data.config.images.pull_policy.clone().or_else(|| Some("IfNotPresent".to_owned())),
                     command: Some(vec!["/bin/bash".to_owned(), "-c".to_owned()]),
                     args: kubectl_args,
                     volume_mounts: Some(volume_mounts.to_owned()),
@@ -101,7 +101,7 @@ async fn get_job_template(context_data: Arc<ContextData>, identity_pool: &Identi
                 containers: vec![Container {
                     name: "hopli".to_owned(),
                     image: Some(context_data.config.hopli_image.to_owned()),
-                    image_pull_policy: Some("Always".to_owned()),
+                    image_pull_policy: context_data.config.images.pull_policy.clone().or_else(|| Some("Always".to_owned())),
                     command: Some(vec!["/bin/bash".to_owned(), "-c".to_owned()]),
                     args: build_args_line(context_data.clone(), identity_pool).await,
                     env: Some(get_env_var(identity_pool.spec.secret_name.to_owned()).await),
@@ -110,6 +110,7 @@ async fn get_job_template(context_data: Arc<ContextData>, identity_pool: &Identi
                     ..Container::default()
                 }],
                 service_account: Some(identity_pool.name_any()),
+                image_pull_secrets: context_data.config.images.get_pull_secrets(),
                 volumes: Some(volumes),
                 restart_policy: Some("Never".to_owned()),
                 ..PodSpec::default()
@@ -119,7 +120,7 @@ async fn get_job_template(context_data: Arc<ContextData>, identity_pool: &Identi
     }
 }
 
-async fn get_env_var(secret_name: String) -> Vec<EnvVar> {
+pub async fn get_env_var(secret_name: String) -> Vec<EnvVar> {
     vec![
         EnvVar {
             name: constants::IDENTITY_POOL_WALLET_DEPLOYER_PRIVATE_KEY_REF_KEY.to_owned(),
@@ -0,0 +1,429 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use croner::Cron;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{Container, PodSpec, PodTemplateSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{DeleteParams, Patch, PatchParams, PostParams};
+use kube::core::ObjectMeta;
+use kube::{Api, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+const FUNDING_CONTAINER_NAME: &str = "funding";
+/// Kubernetes truncates the termination message of a container to 4096 bytes
+const TERMINATION_MESSAGE_MAX_LENGTH: usize = 4096;
+/// Longest result line written by the funding script, with an exit code up to 255
+const FUNDING_RESULT_MAX_LENGTH: usize = "0x0000000000000000000000000000000000000000 native 255\n".len();
+/// Top-ups run by a funding job, so that all their results fit in its termination message
+const FUNDING_JOB_MAX_ACTIONS: usize = TERMINATION_MESSAGE_MAX_LENGTH / FUNDING_RESULT_MAX_LENGTH;
+
+use crate::context_data::ContextData;
+use crate::events::IdentityPoolEventEnum;
+use crate::hoprd::hoprd_deployment_spec::HoprdDeploymentSpec;
+use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprdPhaseEnum;
+use crate::identity_hoprd::identity_hoprd_validation::{check_address, format_ether, parse_ether, RpcClient};
+use crate::identity_pool::identity_pool_cronjob_faucet;
+use crate::identity_pool::identity_pool_provisioning::get_termination_message;
+use crate::identity_pool::identity_pool_resource::{IdentityPool, IdentityPoolFunding, IdentityPoolStatus};
+use crate::model::Error;
+use crate::{constants, utils};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Copy)]
+pub enum IdentityPoolFundingPhaseEnum {
+    /// The top-up waits for the funding job of the previous ones to finish
+    Pending,
+    /// The funding job is transferring the tokens
+    Running,
+    /// The tokens were transferred
+    Succeeded,
+    /// The transfer failed or was not run by the funding job
+    Failed,
+}
+
+/// Top-up of the native or HOPR tokens of a node or safe address below threshold
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolFundingAction {
+    pub identity_name: String,
+    pub address: String,
+    /// Native tokens transferred, in ether
+    pub native_amount: String,
+    /// HOPR tokens transferred, in ether
+    pub token_amount: String,
+    pub phase: IdentityPoolFundingPhaseEnum,
+    /// Time of the last phase change, in RFC 3339 format
+    pub time: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolFundingStatus {
+    /// Time of the last check of the balances, in RFC 3339 format
+    pub last_check: Option<String>,
+    /// Name of the job running the current top-ups. The top-ups of a check are split into consecutive jobs
+    pub job_name: Option<String>,
+    /// Latest top-ups
+    pub actions: Vec<IdentityPoolFundingAction>,
+    /// Native tokens transferred by the succeeded top-ups, in ether
+    pub native_spent: String,
+    /// HOPR tokens transferred by the succeeded top-ups, in ether
+    pub token_spent: String,
+}
+
+/// Parses the cron schedule of the funding
+pub fn parse_schedule(schedule: &str) -> Result<Cron, Error> {
+    Cron::new(schedule).parse().map_err(|e| Error::UserInputError(format!("Invalid funding schedule '{}': {}", schedule, e)))
+}
+
+/// Whether the schedule of the funding fired since the last check of the balances
+fn is_due(schedule: &str, last_check: Option<&str>, now: DateTime<Utc>) -> Result<bool, Error> {
+    let Some(last_check) = last_check.and_then(|time| DateTime::parse_from_rfc3339(time).ok()) else {
+        return Ok(true);
+    };
+    let cron = parse_schedule(schedule)?;
+    Ok(cron.find_next_occurrence(&last_check.with_timezone(&Utc), false).is_ok_and(|next| next <= now))
+}
+
+/// Adds an amount in wei to a total in ether
+fn add_ether(total: &str, amount: &str) -> String {
+    format_ether(parse_ether(total).unwrap_or(0).saturating_add(parse_ether(amount).unwrap_or(0)))
+}
+
+/// Checks the balances of the identities of the pool on the funding schedule, and funds the addresses below threshold with a job
+pub async fn sync_funding(context_data: Arc<ContextData>, identity_pool: &mut IdentityPool) -> Result<(), Error> {
+    let Some(funding) = identity_pool.spec.funding.clone().filter(|funding| funding.has_thresholds()) else {
+        return Ok(());
+    };
+    let namespace = identity_pool.namespace().unwrap();
+    let status = identity_pool.status.clone().unwrap_or_default();
+    let mut funding_status = status.funding.clone().unwrap_or_default();
+    let mut changed = false;
+
+    if let Some(job_name) = funding_status.job_name.clone() {
+        let Some(results) = check_job(context_data.clone(), &namespace, &job_name).await? else {
+            return Ok(());
+        };
+        let mut failed_addresses: Vec<String> = Vec::new();
+        for action in funding_status.actions.iter_mut().filter(|action| action.phase == IdentityPoolFundingPhaseEnum::Running) {
+            // Transfers without a result were not run, as the job stopped before them
+            let succeeded = results.get(&(action.address.to_owned(), action.is_native())).copied().unwrap_or(false);
+            action.time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            if succeeded {
+                action.phase = IdentityPoolFundingPhaseEnum::Succeeded;
+                funding_status.native_spent = add_ether(&funding_status.native_spent, &action.native_amount);
+                funding_status.token_spent = add_ether(&funding_status.token_spent, &action.token_amount);
+            } else {
+                action.phase = IdentityPoolFundingPhaseEnum::Failed;
+                failed_addresses.push(action.address.to_owned());
+            }
+        }
+        if !failed_addresses.is_empty() {
+            context_data
+                .send_event(identity_pool, IdentityPoolEventEnum::FundingFailed, Some(format!("job {} failed to fund {}", job_name, failed_addresses.join(", "))))
+                .await;
+        }
+        funding_status.job_name = None;
+        changed = true;
+    }
+
+    // The balances are checked again once the top-ups of the previous check are all run
+    let now = Utc::now();
+    let pending = funding_status.actions.iter().any(|action| action.phase == IdentityPoolFundingPhaseEnum::Pending);
+    if !pending && is_due(&funding.schedule, funding_status.last_check.as_deref(), now)? {
+        funding_status.last_check = Some(now.to_rfc3339_opts(SecondsFormat::Secs, true));
+        changed = true;
+        if let Some(network) = context_data.config.networks.get(&identity_pool.spec.network) {
+            let rpc_provider_url = context_data.config.rpc_provider_url(&identity_pool.spec.network);
+            let actions = plan_actions(context_data.clone(), identity_pool, &funding, &RpcClient::new(rpc_provider_url.to_owned())?, &network.token_address).await?;
+            if actions.is_empty() {
+                info!("IdentityPool {} has no identity below the funding thresholds", identity_pool.name_any());
+            } else {
+                let mut addresses: Vec<String> = actions.iter().map(|action| action.address.to_owned()).collect();
+                addresses.dedup();
+                context_data.send_event(identity_pool, IdentityPoolEventEnum::FundingStarted, Some(addresses.join(", "))).await;
+                funding_status.actions.extend(actions);
+            }
+        } else {
+            warn!("IdentityPool {} cannot check the balances of its identities, as network {} is not configured in the operator", identity_pool.name_any(), identity_pool.spec.network);
+        }
+    }
+
+    // The jobs run one after the other, as they transfer from the same wallet
+    if funding_status.job_name.is_none() && funding_status.actions.iter().any(|action| action.phase == IdentityPoolFundingPhaseEnum::Pending) {
+        if let Some(network) = context_data.config.networks.get(&identity_pool.spec.network) {
+            let rpc_provider_url = context_data.config.rpc_provider_url(&identity_pool.spec.network);
+            let mut batch: Vec<&mut IdentityPoolFundingAction> = funding_status
+                .actions
+                .iter_mut()
+                .filter(|action| action.phase == IdentityPoolFundingPhaseEnum::Pending)
+                .take(FUNDING_JOB_MAX_ACTIONS)
+                .collect();
+            let script = build_script(&rpc_provider_url, &network.token_address, &batch.iter().map(|action| (**action).clone()).collect::<Vec<_>>())?;
+            let job_name = create_job(context_data.clone(), identity_pool, script).await?;
+            for action in batch.iter_mut() {
+                action.phase = IdentityPoolFundingPhaseEnum::Running;
+                action.time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            }
+            funding_status.job_name = Some(job_name);
+            changed = true;
+        }
+    }
+
+    if changed {
+        // Only the finished top-ups are dropped from the history, the others are still to be accounted
+        let mut dropped = funding_status
+            .actions
+            .iter()
+            .filter(|action| matches!(action.phase, IdentityPoolFundingPhaseEnum::Succeeded | IdentityPoolFundingPhaseEnum::Failed))
+            .count()
+            .saturating_sub(constants::IDENTITY_POOL_FUNDING_HISTORY_SIZE);
+        funding_status.actions.retain(|action| {
+            let finished = matches!(action.phase, IdentityPoolFundingPhaseEnum::Succeeded | IdentityPoolFundingPhaseEnum::Failed);
+            if finished && dropped > 0 {
+                dropped -= 1;
+                return false;
+            }
+            true
+        });
+        let api: Api<IdentityPool> = Api::namespaced(context_data.client.clone(), &namespace);
+        api.patch_status(&identity_pool.name_any(), &PatchParams::default(), &Patch::Merge(json!({ "status": { "funding": funding_status } }))).await?;
+        identity_pool.status = Some(IdentityPoolStatus {
+            funding: Some(funding_status),
+            ..status
+        });
+        context_data.state.write().await.update_identity_pool(identity_pool.to_owned());
+    }
+    Ok(())
+}
+
+impl IdentityPoolFundingAction {
+    /// Whether the top-up transfers native tokens, instead of HOPR tokens
+    fn is_native(&self) -> bool {
+        parse_ether(&self.native_amount).unwrap_or(0) > 0
+    }
+}
+
+/// Reads the balances of the nodes and safes of the pool, and returns the top-ups of the addresses below threshold
+async fn plan_actions(context_data: Arc<ContextData>, identity_pool: &IdentityPool, funding: &IdentityPoolFunding, rpc_client: &RpcClient, token_address: &str) -> Result<Vec<IdentityPoolFundingAction>, Error> {
+    let mut actions: Vec<IdentityPoolFundingAction> = Vec::new();
+    let identities = identity_pool.get_pool_identities(context_data.client.clone()).await;
+    // Identities being deleted are drained instead of funded
+    for identity in identities.iter().filter(|identity| identity.metadata.deletion_timestamp.is_none() && identity.status.as_ref().is_none_or(|status| status.phase != IdentityHoprdPhaseEnum::Deleting)) {
+        for (address, native_threshold, token_threshold) in [
+            (&identity.spec.node_address, &funding.node_native, &funding.node_token),
+            (&identity.spec.safe_address, &funding.safe_native, &funding.safe_token),
+        ] {
+            if let Err(error) = check_address(address) {
+                warn!("IdentityPool {} cannot fund identity {}: {}", identity_pool.name_any(), identity.name_any(), error);
+                continue;
+            }
+            let native_amount = match native_threshold {
+                Some(threshold) => threshold.top_up(rpc_client.get_balance(address).await?)?,
+                None => None,
+            };
+            let token_amount = match token_threshold {
+                Some(threshold) => threshold.top_up(rpc_client.get_token_balance(token_address, address).await?)?,
+                None => None,
+            };
+            // Each transfer is a separate action, so that its result is accounted on its own
+            for (native_amount, token_amount) in [(native_amount, Some(0)), (Some(0), token_amount)] {
+                if let (Some(native_amount), Some(token_amount)) = (native_amount, token_amount) {
+                    actions.push(IdentityPoolFundingAction {
+                        identity_name: identity.name_any(),
+                        address: address.to_owned(),
+                        native_amount: format_ether(native_amount),
+                        token_amount: format_ether(token_amount),
+                        phase: IdentityPoolFundingPhaseEnum::Pending,
+                        time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+                    });
+                }
+            }
+        }
+    }
+    Ok(actions)
+}
+
+/// Returns the results of the transfers of a finished funding job, deleting it
+async fn check_job(context_data: Arc<ContextData>, namespace: &str, job_name: &str) -> Result<Option<BTreeMap<(String, bool), bool>>, Error> {
+    let api: Api<Job> = Api::namespaced(context_data.client.clone(), namespace);
+    let Some(job_status) = api.get_opt(job_name).await?.map(|job| job.status.unwrap_or_default()) else {
+        return Ok(Some(BTreeMap::new()));
+    };
+    if job_status.succeeded.unwrap_or(0) == 0 && job_status.failed.unwrap_or(0) == 0 {
+        return Ok(None);
+    }
+    let termination_message = get_termination_message(context_data.clone(), namespace, job_name, FUNDING_CONTAINER_NAME).await?;
+    api.delete(job_name, &DeleteParams::background()).await?;
+    Ok(Some(parse_results(&termination_message.unwrap_or_default())))
+}
+
+/// Parses the `<address> <native|token> <exit code>` lines written by the funding script, by address and whether the transfer is native
+fn parse_results(termination_message: &str) -> BTreeMap<(String, bool), bool> {
+    termination_message
+        .lines()
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+            [address, kind, exit_code] if kind == "native" || kind == "token" => Some(((address.to_owned(), kind == "native"), exit_code == "0")),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Script transferring the top-ups with cast from the wallet of the pool, recording the result of each transfer in the termination message
+fn build_script(rpc_provider_url: &str, token_address: &str, actions: &[IdentityPoolFundingAction]) -> Result<String, Error> {
+    check_address(token_address)?;
+    // A failed transfer does not stop the next ones, the job fails at the end instead
+    let mut script = "export PATH=${PATH}:/app/hoprnet/.foundry/bin/\nfailed=0\n".to_owned();
+    for action in actions {
+        check_address(&action.address)?;
+        let (kind, command) = if action.is_native() {
+            ("native", format!("cast send {} --value {} --private-key ${{PRIVATE_KEY}} --rpc-url {}", action.address, parse_ether(&action.native_amount)?, rpc_provider_url))
+        } else {
+            (
+                "token",
+                format!(
+                    "cast send {} 'transfer(address,uint256)' {} {} --private-key ${{PRIVATE_KEY}} --rpc-url {}",
+                    token_address,
+                    action.address,
+                    parse_ether(&action.token_amount)?,
+                    rpc_provider_url
+                ),
+            )
+        };
+        script.push_str(&format!(
+            "echo 'Funding {} of identity {} with {} native and {} HOPR tokens'\n{}\nresult=$?\necho \"{} {} ${{result}}\" >> /dev/termination-log\n[ ${{result}} -eq 0 ] || failed=1\n",
+            action.address, action.identity_name, action.native_amount, action.token_amount, command, action.address, kind
+        ));
+    }
+    script.push_str("exit ${failed}\n");
+    Ok(script)
+}
+
+/// Creates the job funding the identities below threshold, returning its name
+async fn create_job(context_data: Arc<ContextData>, identity_pool: &IdentityPool, script: String) -> Result<String, Error> {
+    let namespace = identity_pool.namespace().unwrap();
+    let job_name = format!("funding-{}-{}", identity_pool.name_any(), Utc::now().timestamp());
+    let owner_references: Option<Vec<OwnerReference>> = Some(vec![identity_pool.controller_owner_ref(&()).unwrap()]);
+    let labels: BTreeMap<String, String> = utils::common_lables(identity_pool.name_any(), None, Some("funding".to_owned()));
+    let job = Job {
+        metadata: ObjectMeta {
+            name: Some(job_name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            owner_references,
+            labels: Some(labels.clone()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(JobSpec {
+            // Transfers are not retried, as a partial run already spent funds
+            backoff_limit: Some(0),
+            active_deadline_seconds: Some(constants::OPERATOR_JOB_TIMEOUT as i64),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..ObjectMeta::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![Container {
+                        name: FUNDING_CONTAINER_NAME.to_owned(),
+                        image: Some(context_data.config.hopli_image.to_owned()),
+                        image_pull_policy: context_data.config.images.pull_policy.clone().or_else(|| Some("Always".to_owned())),
+                        command: Some(vec!["/bin/bash".to_owned(), "-c".to_owned()]),
+                        args: Some(vec![script]),
+                        env: Some(identity_pool_cronjob_faucet::get_env_var(identity_pool.spec.secret_name.to_owned()).await),
+                        resources: Some(HoprdDeploymentSpec::get_resource_requirements(None)),
+                        ..Container::default()
+                    }],
+                    service_account: Some(identity_pool.name_any()),
+                    image_pull_secrets: context_data.config.images.get_pull_secrets(),
+                    restart_policy: Some("Never".to_owned()),
+                    ..PodSpec::default()
+                }),
+            },
+            ..JobSpec::default()
+        }),
+        ..Job::default()
+    };
+    let api: Api<Job> = Api::namespaced(context_data.client.clone(), &namespace);
+    api.create(&PostParams::default(), &job).await?;
+    info!("Job {job_name} funding the identities of pool {} created in namespace {namespace}", identity_pool.name_any());
+    Ok(job_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity_pool::identity_pool_resource::IdentityPoolFundingThreshold;
+
+    #[test]
+    fn test_funding_thresholds() {
+        let threshold = IdentityPoolFundingThreshold {
+            min_balance: "0.01".to_owned(),
+            target_balance: "0.05".to_owned(),
+        };
+        assert_eq!(threshold.top_up(parse_ether("0.02").unwrap()).unwrap(), None);
+        assert_eq!(threshold.top_up(parse_ether("0.004").unwrap()).unwrap(), Some(parse_ether("0.046").unwrap()));
+        assert_eq!(add_ether("", "0.046"), "0.046");
+        assert_eq!(add_ether("1.5", "0.5"), "2");
+
+        let now = DateTime::parse_from_rfc3339("2024-01-08T02:00:00Z").unwrap().with_timezone(&Utc);
+        // Mondays at 1:00, as in kubernetes cron jobs
+        assert!(is_due("0 1 * * 1", Some("2024-01-07T12:00:00Z"), now).unwrap());
+        assert!(!is_due("0 1 * * 1", Some("2024-01-08T01:30:00Z"), now).unwrap());
+        assert!(is_due("0 1 * * 1", None, now).unwrap());
+    }
+
+    #[test]
+    fn test_funding_script_results() {
+        let address = "0x9e820e68f8c024779ebcb6cd2edda1885e1dbe1f";
+        let action = |native_amount: &str, token_amount: &str| IdentityPoolFundingAction {
+            identity_name: "identity-1".to_owned(),
+            address: address.to_owned(),
+            native_amount: native_amount.to_owned(),
+            token_amount: token_amount.to_owned(),
+            phase: IdentityPoolFundingPhaseEnum::Running,
+            time: String::new(),
+        };
+        let actions = vec![action("0.046", "0"), action("0", "10")];
+        let script = build_script("http://rpc", address, &actions).unwrap();
+        assert!(!script.contains("set -e"));
+        assert!(script.contains(&format!("echo \"{} native ${{result}}\" >> /dev/termination-log", address)));
+        assert!(script.contains(&format!("echo \"{} token ${{result}}\" >> /dev/termination-log", address)));
+        assert!(build_script("http://rpc", "0x1234", &actions).is_err());
+        let invalid_action = IdentityPoolFundingAction {
+            address: "0x1234; rm -rf /".to_owned(),
+            ..action("0.046", "0")
+        };
+        assert!(build_script("http://rpc", address, &[invalid_action]).is_err());
+
+        let results = parse_results(&format!("{} native 0\n{} token 1\n", address, address));
+        assert_eq!(results.get(&(address.to_owned(), true)), Some(&true));
+        assert_eq!(results.get(&(address.to_owned(), false)), Some(&false));
+        assert!(parse_results("").is_empty());
+    }
+
+    #[test]
+    fn test_funding_job_results_fit_termination_message() {
+        let actions: Vec<IdentityPoolFundingAction> = (0..FUNDING_JOB_MAX_ACTIONS)
+            .map(|index| IdentityPoolFundingAction {
+                identity_name: format!("identity-{}", index),
+                address: format!("0x{:040x}", index),
+                native_amount: "0.046".to_owned(),
+                token_amount: "0".to_owned(),
+                phase: IdentityPoolFundingPhaseEnum::Pending,
+                time: String::new(),
+            })
+            .collect();
+        let script = build_script("http://rpc", "0x9e820e68f8c024779ebcb6cd2edda1885e1dbe1f", &actions).unwrap();
+        // The longest output of the job, with every transfer failing with the highest exit code
+        let output: usize = script
+            .lines()
+            .filter_map(|line| line.strip_suffix(" >> /dev/termination-log"))
+            .map(|echo| echo.trim_start_matches("echo \"").trim_end_matches('"').replace("${result}", "255").len() + 1)
+            .sum();
+        assert_eq!(script.matches("/dev/termination-log").count(), FUNDING_JOB_MAX_ACTIONS);
+        assert!(output <= TERMINATION_MESSAGE_MAX_LENGTH, "{} bytes", output);
+    }
+}
@@ -0,0 +1,428 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, SecondsFormat, Utc};
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{Container, EmptyDirVolumeSource, EnvVar, EnvVarSource, Pod, PodSpec, PodTemplateSpec, Secret, SecretKeySelector, Volume, VolumeMount};
+use k8s_openapi::ByteString;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
+use kube::core::ObjectMeta;
+use kube::{Api, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::context_data::ContextData;
+use crate::events::IdentityPoolEventEnum;
+use crate::hoprd::hoprd_deployment_spec::HoprdDeploymentSpec;
+use crate::identity_hoprd::identity_hoprd_keystore;
+use crate::identity_hoprd::identity_hoprd_validation::{format_ether, parse_ether, RpcClient};
+use crate::identity_hoprd::identity_hoprd_resource::{IdentityHoprd, IdentityHoprdSpec};
+use crate::identity_pool::identity_pool_cronjob_faucet;
+use crate::identity_pool::identity_pool_resource::{IdentityPool, IdentityPoolStatus};
+use crate::model::Error;
+use crate::{constants, utils};
+
+/// Name of the container running hopli in the provisioning jobs
+const PROVISIONING_CONTAINER_NAME: &str = "hopli";
+/// Key of the secret holding the output of a succeeded provisioning job
+const PROVISIONING_OUTPUT_KEY: &str = "output";
+
+/// Identities created by the operator to keep the pool at the requested size
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolProvisioning {
+    /// Number of identities held by the pool
+    pub desired_size: Option<i32>,
+    /// Minimum number of identities not used by any node
+    pub min_free_identities: Option<i32>,
+    /// HOPR tokens transferred to the safe of each new identity
+    pub hopr_amount: String,
+    /// Native tokens transferred to each new node
+    pub native_amount: String,
+}
+
+impl IdentityPoolProvisioning {
+    /// Number of identities the pool should hold, given the ones locked by nodes
+    pub fn target_size(&self, locked: i32) -> i32 {
+        self.desired_size.unwrap_or(0).max(locked + self.min_free_identities.unwrap_or(0))
+    }
+
+    /// Checks the amounts, which are passed to hopli in the script of the jobs
+    pub fn validate(&self) -> Result<(), Error> {
+        parse_ether(&self.hopr_amount)?;
+        parse_ether(&self.native_amount)?;
+        if self.desired_size.unwrap_or(0) < 0 || self.min_free_identities.unwrap_or(0) < 0 {
+            return Err(Error::UserInputError("Provisioning sizes cannot be negative".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Copy)]
+pub enum IdentityPoolProvisioningPhaseEnum {
+    /// The job is creating the identity on-chain
+    Running,
+    /// The identity was created and registered in the pool
+    Succeeded,
+    /// The job failed and the identity was not created
+    Failed,
+}
+
+/// Job provisioning one identity of the pool
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolProvisioningJob {
+    pub name: String,
+    /// Name of the IdentityHoprd created by the job
+    pub identity_name: String,
+    pub phase: IdentityPoolProvisioningPhaseEnum,
+    pub message: Option<String>,
+    /// Time of the last phase change, in RFC 3339 format
+    pub time: String,
+}
+
+/// Output of the provisioning job, written in the termination message of its container.
+/// The safe and module of the identity are read from the chain rather than from the logs of hopli
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct ProvisioningOutput {
+    identity_file: String,
+}
+
+/// First free identity name of the form `<pool>-<index>`
+fn next_identity_name(identity_pool_name: &str, taken_names: &[String]) -> String {
+    (1..).map(|index| format!("{}-{}", identity_pool_name, index)).find(|name| !taken_names.contains(name)).unwrap()
+}
+
+/// Creates one identity at a time with hopli until the pool reaches its target size, and registers the identities created by the finished jobs
+pub async fn sync_provisioning(context_data: Arc<ContextData>, identity_pool: &mut IdentityPool) -> Result<(), Error> {
+    let Some(provisioning) = identity_pool.spec.provisioning.clone() else {
+        return Ok(());
+    };
+    let client = context_data.client.clone();
+    let namespace = identity_pool.namespace().unwrap();
+    let status = identity_pool.status.clone().unwrap_or_default();
+    let mut jobs: Vec<IdentityPoolProvisioningJob> = status.provisioning_jobs.clone().unwrap_or_default();
+    let mut changed = false;
+
+    for job in jobs.iter_mut().filter(|job| job.phase == IdentityPoolProvisioningPhaseEnum::Running) {
+        if let Some((phase, message)) = check_job(context_data.clone(), identity_pool, job).await? {
+            if phase == IdentityPoolProvisioningPhaseEnum::Failed {
+                context_data.send_event(identity_pool, IdentityPoolEventEnum::ProvisioningFailed, message.clone()).await;
+            }
+            job.phase = phase;
+            job.message = message;
+            job.time = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+            changed = true;
+        }
+    }
+
+    let running = jobs.iter().any(|job| job.phase == IdentityPoolProvisioningPhaseEnum::Running);
+    // Failed jobs are not retried right away, as they might have spent funds
+    let backing_off = jobs
+        .last()
+        .filter(|job| job.phase == IdentityPoolProvisioningPhaseEnum::Failed)
+        .and_then(|job| DateTime::parse_from_rfc3339(&job.time).ok())
+        .is_some_and(|time| Utc::now().signed_duration_since(time).num_seconds() < constants::OPERATOR_JOB_TIMEOUT as i64);
+    let pool_size = identity_pool.get_pool_identities(client.clone()).await.len() as i32;
+    let target_size = provisioning.target_size(status.locked);
+    if !running && !backing_off && pool_size < target_size {
+        if identity_hoprd_keystore::get_identity_password(client.clone(), identity_pool).await?.is_none() {
+            warn!("IdentityPool {} cannot provision identities without an IDENTITY_PASSWORD in secret {}", identity_pool.name_any(), identity_pool.spec.secret_name);
+        } else if !context_data.config.networks.contains_key(&identity_pool.spec.network) {
+            warn!("IdentityPool {} cannot provision identities, as network {} is not configured in the operator", identity_pool.name_any(), identity_pool.spec.network);
+        } else {
+            let api: Api<IdentityHoprd> = Api::namespaced(client.clone(), &namespace);
+            let mut taken_names: Vec<String> = api.list(&ListParams::default()).await?.iter().map(|identity| identity.name_any()).collect();
+            taken_names.extend(jobs.iter().map(|job| job.identity_name.to_owned()));
+            let identity_name = next_identity_name(&identity_pool.name_any(), &taken_names);
+            info!("IdentityPool {} holds {pool_size} of {target_size} identities, provisioning identity {identity_name}", identity_pool.name_any());
+            let job_name = create_job(context_data.clone(), identity_pool, &provisioning, &identity_name).await?;
+            context_data.send_event(identity_pool, IdentityPoolEventEnum::ProvisioningStarted, Some(identity_name.to_owned())).await;
+            jobs.push(IdentityPoolProvisioningJob {
+                name: job_name,
+                identity_name,
+                phase: IdentityPoolProvisioningPhaseEnum::Running,
+                message: None,
+                time: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            });
+            changed = true;
+        }
+    }
+
+    if changed {
+        let first_kept = jobs.len().saturating_sub(constants::IDENTITY_POOL_PROVISIONING_HISTORY_SIZE);
+        jobs.drain(..first_kept);
+        let api: Api<IdentityPool> = Api::namespaced(client.clone(), &namespace);
+        api.patch_status(&identity_pool.name_any(), &PatchParams::default(), &Patch::Merge(json!({ "status": { "provisioningJobs": jobs } }))).await?;
+        identity_pool.status = Some(IdentityPoolStatus {
+            provisioning_jobs: Some(jobs),
+            ..status
+        });
+        context_data.state.write().await.update_identity_pool(identity_pool.to_owned());
+    }
+    Ok(())
+}
+
+/// Returns the outcome of a finished job, registering the identity it created
+async fn check_job(context_data: Arc<ContextData>, identity_pool: &IdentityPool, job: &IdentityPoolProvisioningJob) -> Result<Option<(IdentityPoolProvisioningPhaseEnum, Option<String>)>, Error> {
+    let namespace = identity_pool.namespace().unwrap();
+    let api: Api<Job> = Api::namespaced(context_data.client.clone(), &namespace);
+    let Some(job_status) = api.get_opt(&job.name).await?.map(|provisioning_job| provisioning_job.status.unwrap_or_default()) else {
+        return Ok(Some((IdentityPoolProvisioningPhaseEnum::Failed, Some(format!("Job {} no longer exists", job.name)))));
+    };
+    if job_status.succeeded.unwrap_or(0) > 0 {
+        // The output holds the only copy of the key of an identity already funded on-chain,
+        // so it is saved before anything can fail and the job is only deleted once the IdentityHoprd exists
+        let output = match get_output_secret(context_data.clone(), &namespace, &job.name).await? {
+            Some(output) => output,
+            None => {
+                let output = get_termination_message(context_data.clone(), &namespace, &job.name, PROVISIONING_CONTAINER_NAME).await?.unwrap_or_default();
+                create_output_secret(context_data.clone(), identity_pool, job, &output).await?;
+                output
+            }
+        };
+        let created = match serde_json::from_str::<ProvisioningOutput>(output.trim()) {
+            Ok(output) => create_identity(context_data.clone(), identity_pool, &job.identity_name, output).await,
+            Err(error) => Err(Error::IdentityIssue(format!("unexpected output of the job: {}", error))),
+        };
+        return match created {
+            Ok(node_address) => {
+                api.delete(&job.name, &DeleteParams::background()).await?;
+                let secret_api: Api<Secret> = Api::namespaced(context_data.client.clone(), &namespace);
+                secret_api.delete(&job.name, &DeleteParams::default()).await?;
+                Ok(Some((IdentityPoolProvisioningPhaseEnum::Succeeded, Some(format!("Created node {}", node_address)))))
+            }
+            Err(Error::IdentityIssue(reason)) => Ok(Some((
+                IdentityPoolProvisioningPhaseEnum::Failed,
+                Some(format!("{}, the output of the job is kept in secret {} to recover the identity", reason, job.name)),
+            ))),
+            Err(error) => Err(error),
+        };
+    }
+    let outcome = if job_status.failed.unwrap_or(0) > 0 {
+        let termination_message = get_termination_message(context_data.clone(), &namespace, &job.name, PROVISIONING_CONTAINER_NAME).await?;
+        let reason = job_status.conditions.unwrap_or_default().into_iter().find(|condition| condition.type_ == "Failed").and_then(|condition| condition.message);
+        (IdentityPoolProvisioningPhaseEnum::Failed, termination_message.or(reason).map(|message| message.trim().to_owned()))
+    } else {
+        return Ok(None);
+    };
+    api.delete(&job.name, &DeleteParams::background()).await?;
+    Ok(Some(outcome))
+}
+
+/// Output of a succeeded job saved by a previous reconciliation
+async fn get_output_secret(context_data: Arc<ContextData>, namespace: &str, job_name: &str) -> Result<Option<String>, Error> {
+    let api: Api<Secret> = Api::namespaced(context_data.client.clone(), namespace);
+    Ok(api
+        .get_opt(job_name)
+        .await?
+        .and_then(|secret| secret.data)
+        .and_then(|data| data.get(PROVISIONING_OUTPUT_KEY).cloned())
+        .map(|output| String::from_utf8_lossy(&output.0).into_owned()))
+}
+
+/// Saves the output of a succeeded job in a secret owned by the pool, named after the job
+async fn create_output_secret(context_data: Arc<ContextData>, identity_pool: &IdentityPool, job: &IdentityPoolProvisioningJob, output: &str) -> Result<(), Error> {
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(job.name.to_owned()),
+            namespace: identity_pool.namespace(),
+            labels: Some(utils::common_lables(identity_pool.name_any(), Some(job.identity_name.to_owned()), Some("provisioning".to_owned()))),
+            owner_references: Some(vec![identity_pool.controller_owner_ref(&()).unwrap()]),
+            ..ObjectMeta::default()
+        },
+        data: Some(BTreeMap::from([(PROVISIONING_OUTPUT_KEY.to_owned(), ByteString(output.as_bytes().to_vec()))])),
+        ..Secret::default()
+    };
+    let api: Api<Secret> = Api::namespaced(context_data.client.clone(), &identity_pool.namespace().unwrap());
+    api.create(&PostParams::default(), &secret).await?;
+    Ok(())
+}
+
+/// Termination message of a container of the job, which falls back to the end of its logs when it failed
+pub async fn get_termination_message(context_data: Arc<ContextData>, namespace: &str, job_name: &str, container_name: &str) -> Result<Option<String>, Error> {
+    let api: Api<Pod> = Api::namespaced(context_data.client.clone(), namespace);
+    let pods = api.list(&ListParams::default().labels(&format!("job-name={}", job_name))).await?;
+    Ok(pods
+        .iter()
+        .filter_map(|pod| pod.status.as_ref()?.container_statuses.as_ref())
+        .flatten()
+        .filter(|container_status| container_status.name == container_name)
+        .filter_map(|container_status| container_status.state.as_ref()?.terminated.as_ref()?.message.clone())
+        .last())
+}
+
+/// Creates the IdentityHoprd of the identity provisioned by a job, returning its node address
+async fn create_identity(context_data: Arc<ContextData>, identity_pool: &IdentityPool, identity_name: &str, output: ProvisioningOutput) -> Result<String, Error> {
+    let password = identity_hoprd_keystore::get_identity_password(context_data.client.clone(), identity_pool)
+        .await?
+        .ok_or_else(|| Error::IdentityIssue(format!("the secret {} has no IDENTITY_PASSWORD", identity_pool.spec.secret_name)))?;
+    let api: Api<IdentityHoprd> = Api::namespaced(context_data.client.clone(), &identity_pool.namespace().unwrap());
+    if let Some(identity_hoprd) = api.get_opt(identity_name).await? {
+        // Created by a previous reconciliation which could not delete the job
+        return Ok(identity_hoprd.spec.node_address);
+    }
+    let identity_file = general_purpose::STANDARD.decode(&output.identity_file).map_err(|e| Error::IdentityIssue(format!("the identity file is not valid base64: {}", e)))?;
+    let node_address = identity_hoprd_keystore::node_address(&identity_hoprd_keystore::decrypt_chain_key(&identity_file, &password).await?)?;
+    let network = context_data
+        .config
+        .networks
+        .get(&identity_pool.spec.network)
+        .ok_or_else(|| Error::IdentityIssue(format!("network {} is not configured in the operator", identity_pool.spec.network)))?;
+    let rpc_client = RpcClient::new(context_data.config.rpc_provider_url(&identity_pool.spec.network))?;
+    let safe_address = rpc_client
+        .get_node_safe(&network.node_safe_registry_address, &node_address)
+        .await?
+        .ok_or_else(|| Error::IdentityIssue(format!("node {} is not registered in the node safe registry", node_address)))?;
+    let module_address = rpc_client
+        .get_safe_modules(&safe_address)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::IdentityIssue(format!("safe {} has no module enabled", safe_address)))?;
+    let owner_references: Option<Vec<OwnerReference>> = Some(vec![identity_pool.controller_owner_ref(&()).unwrap()]);
+    let labels: BTreeMap<String, String> = utils::common_lables(identity_pool.name_any(), Some(identity_name.to_owned()), Some("identity".to_owned()));
+    let mut identity_hoprd = IdentityHoprd {
+        metadata: ObjectMeta {
+            name: Some(identity_name.to_owned()),
+            namespace: identity_pool.namespace(),
+            labels: Some(labels),
+            owner_references,
+            ..ObjectMeta::default()
+        },
+        spec: IdentityHoprdSpec {
+            identity_pool_name: identity_pool.name_any(),
+            identity_file: output.identity_file,
+            identity_file_secret_ref: None,
+            node_address: node_address.to_owned(),
+            safe_address,
+            module_address,
+        },
+        status: None,
+    };
+    let identity_last_configuration = serde_json::to_string(&identity_hoprd).unwrap();
+    identity_hoprd.metadata.annotations = Some(BTreeMap::from([(constants::ANNOTATION_LAST_CONFIGURATION.to_owned(), identity_last_configuration)]));
+    api.create(&PostParams::default(), &identity_hoprd).await?;
+    info!("IdentityHoprd {identity_name} provisioned for node {node_address}");
+    Ok(node_address)
+}
+
+/// Script creating an identity, deploying its safe and module, registering the node and funding it.
+/// The identity file is written as JSON in the termination message
+fn build_script(rpc_provider_url: &str, network: &str, provisioning: &IdentityPoolProvisioning, identity_name: &str) -> Result<String, Error> {
+    // Amounts are formatted again from their parsed value, so that nothing else from the resource reaches the shell
+    let hopr_amount = format_ether(parse_ether(&provisioning.hopr_amount)?);
+    let native_amount = format_ether(parse_ether(&provisioning.native_amount)?);
+    Ok(format!(
+        "set -e\n\
+        export PATH=${{PATH}}:/app/hoprnet/.foundry/bin/\n\
+        mkdir -p /data/identity\n\
+        /bin/hopli identity create --identity-directory /data/identity --identity-prefix {identity_name} --number 1\n\
+        /bin/hopli safe-module create --network {network} --provider-url {rpc_provider_url} --identity-directory /data/identity --hopr-amount {hopr_amount} --native-amount {native_amount}\n\
+        printf '{{\"identityFile\":\"%s\"}}' \"$(base64 -w0 /data/identity/*.id)\" > /dev/termination-log"
+    ))
+}
+
+/// Creates the job provisioning an identity, returning its name
+async fn create_job(context_data: Arc<ContextData>, identity_pool: &IdentityPool, provisioning: &IdentityPoolProvisioning, identity_name: &str) -> Result<String, Error> {
+    let namespace = identity_pool.namespace().unwrap();
+    let job_name = format!("provision-{}", identity_name);
+    let rpc_provider_url = context_data.config.rpc_provider_url(&identity_pool.spec.network);
+    let script = build_script(&rpc_provider_url, &identity_pool.spec.network, provisioning, identity_name)?;
+    let owner_references: Option<Vec<OwnerReference>> = Some(vec![identity_pool.controller_owner_ref(&()).unwrap()]);
+    let labels: BTreeMap<String, String> = utils::common_lables(identity_pool.name_any(), Some(identity_name.to_owned()), Some("provisioning".to_owned()));
+    let mut env = identity_pool_cronjob_faucet::get_env_var(identity_pool.spec.secret_name.to_owned()).await;
+    env.push(EnvVar {
+        name: constants::IDENTITY_POOL_IDENTITY_PASSWORD_REF_KEY.to_owned(),
+        value_from: Some(EnvVarSource {
+            secret_key_ref: Some(SecretKeySelector {
+                key: constants::IDENTITY_POOL_IDENTITY_PASSWORD_REF_KEY.to_owned(),
+                name: identity_pool.spec.secret_name.to_owned(),
+                ..SecretKeySelector::default()
+            }),
+            ..EnvVarSource::default()
+        }),
+        ..EnvVar::default()
+    });
+    let job = Job {
+        metadata: ObjectMeta {
+            name: Some(job_name.to_owned()),
+            namespace: Some(namespace.to_owned()),
+            owner_references,
+            labels: Some(labels.clone()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(JobSpec {
+            // On-chain transactions are not retried, as a partial run might already have spent funds
+            backoff_limit: Some(0),
+            active_deadline_seconds: Some(constants::OPERATOR_JOB_TIMEOUT as i64),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..ObjectMeta::default()
+                }),
+                spec: Some(PodSpec {
+                    containers: vec![Container {
+                        name: PROVISIONING_CONTAINER_NAME.to_owned(),
+                        image: Some(context_data.config.hopli_image.to_owned()),
+                        image_pull_policy: context_data.config.images.pull_policy.clone().or_else(|| Some("Always".to_owned())),
+                        command: Some(vec!["/bin/bash".to_owned(), "-c".to_owned()]),
+                        args: Some(vec![script]),
+                        env: Some(env),
+                        termination_message_policy: Some("FallbackToLogsOnError".to_owned()),
+                        volume_mounts: Some(vec![VolumeMount {
+                            name: "data".to_owned(),
+                            mount_path: "/data".to_owned(),
+                            ..VolumeMount::default()
+                        }]),
+                        resources: Some(HoprdDeploymentSpec::get_resource_requirements(None)),
+                        ..Container::default()
+                    }],
+                    service_account: Some(identity_pool.name_any()),
+                    image_pull_secrets: context_data.config.images.get_pull_secrets(),
+                    volumes: Some(vec![Volume {
+                        name: "data".to_owned(),
+                        empty_dir: Some(EmptyDirVolumeSource::default()),
+                        ..Volume::default()
+                    }]),
+                    restart_policy: Some("Never".to_owned()),
+                    ..PodSpec::default()
+                }),
+            },
+            ..JobSpec::default()
+        }),
+        ..Job::default()
+    };
+    let api: Api<Job> = Api::namespaced(context_data.client.clone(), &namespace);
+    api.create(&PostParams::default(), &job).await?;
+    info!("Job {job_name} provisioning identity {identity_name} created in namespace {namespace}");
+    Ok(job_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_size() {
+        let provisioning = IdentityPoolProvisioning {
+            desired_size: Some(5),
+            min_free_identities: Some(2),
+            ..IdentityPoolProvisioning::default()
+        };
+        assert_eq!(provisioning.target_size(1), 5);
+        assert_eq!(provisioning.target_size(4), 6);
+        let taken_names = vec!["pool-1".to_owned(), "pool-3".to_owned()];
+        assert_eq!(next_identity_name("pool", &taken_names), "pool-2");
+        let output: ProvisioningOutput = serde_json::from_str(r#"{"identityFile":"e30="}"#).unwrap();
+        assert_eq!(output.identity_file, "e30=");
+        let script = build_script("http://rpc", "rotsee", &IdentityPoolProvisioning { hopr_amount: "10.0".to_owned(), native_amount: "0.01".to_owned(), ..provisioning.clone() }, "pool-2").unwrap();
+        assert!(script.contains("--hopr-amount 10 --native-amount 0.01\n"), "{}", script);
+        let injected = IdentityPoolProvisioning { hopr_amount: "$(env)".to_owned(), ..provisioning };
+        assert!(build_script("http://rpc", "rotsee", &injected, "pool-2").is_err());
+    }
+}
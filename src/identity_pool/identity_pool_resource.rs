@@ -1,9 +1,12 @@
 use crate::events::IdentityPoolEventEnum;
 use crate::identity_hoprd::identity_hoprd_resource::{IdentityHoprd, IdentityHoprdPhaseEnum};
+use crate::identity_hoprd::identity_hoprd_validation::{check_address, parse_ether};
+use crate::identity_pool::identity_pool_funding::IdentityPoolFundingStatus;
+use crate::identity_pool::identity_pool_provisioning::{IdentityPoolProvisioning, IdentityPoolProvisioningJob};
 use crate::model::Error;
 use crate::{constants, context_data::ContextData};
 use crate::{
-    identity_pool::{identity_pool_cronjob_faucet, identity_pool_service_account, identity_pool_service_monitor},
+    identity_pool::{identity_pool_cronjob_faucet, identity_pool_funding, identity_pool_provisioning, identity_pool_service_account, identity_pool_service_monitor},
     resource_generics,
 };
 use k8s_openapi::api::batch::v1::CronJob;
@@ -32,13 +35,75 @@ pub struct IdentityPoolSpec {
     pub network: String,
     pub secret_name: String,
     pub funding: Option<IdentityPoolFunding>,
+    pub provisioning: Option<IdentityPoolProvisioning>,
+    pub drain: Option<IdentityPoolDrain>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Hash, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct IdentityPoolFunding {
     pub schedule: String,
+    /// Native tokens sent to every node on each run of the cron job, when no threshold is set
+    #[serde(default)]
     pub native_amount: String,
+    pub node_native: Option<IdentityPoolFundingThreshold>,
+    pub node_token: Option<IdentityPoolFundingThreshold>,
+    pub safe_native: Option<IdentityPoolFundingThreshold>,
+    pub safe_token: Option<IdentityPoolFundingThreshold>,
+}
+
+impl IdentityPoolFunding {
+    /// Whether the operator only funds the identities below a threshold, instead of running the cron job
+    pub fn has_thresholds(&self) -> bool {
+        self.node_native.is_some() || self.node_token.is_some() || self.safe_native.is_some() || self.safe_token.is_some()
+    }
+
+    /// Checks the schedule and the thresholds checked by the operator
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.has_thresholds() {
+            identity_pool_funding::parse_schedule(&self.schedule)?;
+        }
+        [&self.node_native, &self.node_token, &self.safe_native, &self.safe_token].into_iter().flatten().try_for_each(|threshold| threshold.validate())
+    }
+}
+
+/// Balance under which an address is topped up to the target balance, both in ether
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolFundingThreshold {
+    pub min_balance: String,
+    pub target_balance: String,
+}
+
+impl IdentityPoolFundingThreshold {
+    /// Minimum and target balances in wei
+    fn balances(&self) -> Result<(u128, u128), Error> {
+        let min_balance = parse_ether(&self.min_balance)?;
+        let target_balance = parse_ether(&self.target_balance)?;
+        if target_balance < min_balance {
+            return Err(Error::UserInputError(format!("Funding target balance {} is below the minimum balance {}", self.target_balance, self.min_balance)));
+        }
+        Ok((min_balance, target_balance))
+    }
+
+    /// Checks that both balances are amounts in ether and that the target is not below the minimum
+    pub fn validate(&self) -> Result<(), Error> {
+        self.balances().map(|_| ())
+    }
+
+    /// Amount in wei to transfer to an address holding the given balance
+    pub fn top_up(&self, balance: u128) -> Result<Option<u128>, Error> {
+        let (min_balance, target_balance) = self.balances()?;
+        Ok(Some(target_balance - balance).filter(|_| balance < min_balance))
+    }
+}
+
+/// Sweeps the funds of the identities before they are deleted
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, JsonSchema, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityPoolDrain {
+    /// Address receiving the native and HOPR tokens of the node and safe
+    pub address: String,
 }
 
 /// The status object of `Hoprd`
@@ -49,6 +114,10 @@ pub struct IdentityPoolStatus {
     pub size: i32,
     pub locked: i32,
     pub observed_generation: i64,
+    /// Latest jobs provisioning identities for the pool
+    pub provisioning_jobs: Option<Vec<IdentityPoolProvisioningJob>>,
+    /// Funding of the identities below the thresholds
+    pub funding: Option<IdentityPoolFundingStatus>,
 }
 
 impl Default for IdentityPoolStatus {
@@ -58,6 +127,8 @@ impl Default for IdentityPoolStatus {
             size: 0,
             locked: 0,
             observed_generation: 0,
+            provisioning_jobs: None,
+            funding: None,
         }
     }
 }
@@ -117,7 +188,7 @@ impl IdentityPool {
         let identity_pool_namespace: String = self.namespace().unwrap();
         let identity_pool_name: String = self.name_any();
         let owner_references: Option<Vec<OwnerReference>> = Some(vec![self.controller_owner_ref(&()).unwrap()]);
-        if !self.check_wallet(client.clone()).await.unwrap() {
+        if !self.check_wallet(client.clone()).await.unwrap() || self.invalid_spec() {
             context_data.send_event(self, IdentityPoolEventEnum::Failed, None).await;
             return Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY)));
         }
@@ -125,7 +196,7 @@ impl IdentityPool {
         resource_generics::add_finalizer(client.clone(), self).await;
         identity_pool_service_monitor::create_service_monitor(context_data.clone(), &identity_pool_name, &identity_pool_namespace, owner_references.to_owned()).await?;
         identity_pool_service_account::create_rbac(context_data.clone(), &identity_pool_namespace, &identity_pool_name, owner_references.to_owned()).await?;
-        if self.spec.funding.is_some() {
+        if self.spec.funding.as_ref().is_some_and(|funding| !funding.has_thresholds()) {
             identity_pool_cronjob_faucet::create_cron_job(context_data.clone(), self).await.expect("Could not create Cronjob");
         }
         context_data.send_event(self, IdentityPoolEventEnum::Initialized, None).await;
@@ -159,6 +230,8 @@ impl IdentityPool {
                         identity_pool_name, identity_pool_namespace
                     );
                 }
+            } else if status.phase.eq(&IdentityPoolPhaseEnum::Failed) && self.invalid_spec() {
+                self.update_status(context_data.client.clone(), IdentityPoolPhaseEnum::Failed).await?;
             } else if status.phase.eq(&IdentityPoolPhaseEnum::Failed) {
                 context_data.send_event(self, IdentityPoolEventEnum::Ready, None).await;
                 self.update_status(context_data.client.clone(), IdentityPoolPhaseEnum::Ready).await?;
@@ -179,13 +252,13 @@ impl IdentityPool {
         let client: Client = context_data.client.clone();
         let identity_pool_namespace: String = self.namespace().unwrap();
         let identity_pool_name: String = self.name_any();
-        if self.changed_inmutable_fields(&previous_identity_pool.spec) {
+        if self.changed_inmutable_fields(&previous_identity_pool.spec) || self.invalid_spec() {
             context_data.send_event(self, IdentityPoolEventEnum::Failed, None).await;
             self.update_status(client.clone(), IdentityPoolPhaseEnum::Failed).await?;
         } else {
             let api: Api<CronJob> = Api::namespaced(context_data.client.clone(), &identity_pool_namespace);
             let cron_job_name = format!("auto-funding-{}", identity_pool_name);
-            match self.spec.funding.as_ref() {
+            match self.spec.funding.as_ref().filter(|funding| !funding.has_thresholds()) {
                 Some(_) => {
                     if api.get_opt(&cron_job_name).await?.is_none() {
                         identity_pool_cronjob_faucet::create_cron_job(context_data.clone(), self).await.expect("Could not create Cronjob");
@@ -235,9 +308,17 @@ impl IdentityPool {
         }
     }
 
-    pub async fn sync(&mut self, _context_data: Arc<ContextData>) -> Result<Action, Error> {
-        warn!("IdentityPool {} in namespace {} requires more identities", self.name_any(), self.namespace().unwrap());
-        Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY)))
+    pub async fn sync(&mut self, context_data: Arc<ContextData>) -> Result<Action, Error> {
+        if self.status.as_ref().is_some_and(|status| status.phase.eq(&IdentityPoolPhaseEnum::OutOfSync)) {
+            warn!("IdentityPool {} in namespace {} requires more identities", self.name_any(), self.namespace().unwrap());
+        }
+        if self.spec.provisioning.is_some() || self.spec.funding.as_ref().is_some_and(|funding| funding.has_thresholds()) {
+            identity_pool_provisioning::sync_provisioning(context_data.clone(), self).await?;
+            identity_pool_funding::sync_funding(context_data, self).await?;
+            Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_SHORT_FREQUENCY)))
+        } else {
+            Ok(Action::requeue(Duration::from_secs(constants::RECONCILE_LONG_FREQUENCY)))
+        }
     }
 
     fn changed_inmutable_fields(&self, previous_identity: &IdentityPoolSpec) -> bool {
@@ -252,6 +333,27 @@ impl IdentityPool {
         }
     }
 
+    /// Checks the fields of the spec which are passed to the scripts of the jobs
+    fn invalid_spec(&self) -> bool {
+        let validation = if !self.spec.network.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            Err(Error::UserInputError(format!("'{}' is not a valid network name", self.spec.network)))
+        } else {
+            self.spec
+                .provisioning
+                .as_ref()
+                .map_or(Ok(()), |provisioning| provisioning.validate())
+                .and_then(|()| self.spec.funding.as_ref().map_or(Ok(()), |funding| funding.validate()))
+                .and_then(|()| self.spec.drain.as_ref().map_or(Ok(()), |drain| check_address(&drain.address)))
+        };
+        match validation {
+            Ok(()) => false,
+            Err(error) => {
+                error!("Configuration is invalid on {}: {}", self.name_any(), error);
+                true
+            }
+        }
+    }
+
     /// Updates the status of IdentityPool
     pub async fn update_status(&mut self, client: Client, phase: IdentityPoolPhaseEnum) -> Result<(), Error> {
         let identity_hoprd_name = self.metadata.name.as_ref().unwrap().to_owned();
@@ -1,5 +1,7 @@
 pub mod identity_pool_controller;
-mod identity_pool_cronjob_faucet;
+pub mod identity_pool_cronjob_faucet;
+pub mod identity_pool_funding;
+pub mod identity_pool_provisioning;
 pub mod identity_pool_resource;
 mod identity_pool_service_account;
 mod identity_pool_service_monitor;
@@ -0,0 +1,280 @@
+use base64::{engine::general_purpose, Engine as _};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client};
+use reqwest::{header, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tracing::{debug, warn};
+
+use crate::model::Error;
+
+const DOCKER_HUB_REGISTRY: &str = "registry-1.docker.io";
+/// Hosts under which Docker Hub credentials are stored in docker config files
+const DOCKER_HUB_ALIASES: [&str; 4] = ["registry-1.docker.io", "docker.io", "index.docker.io", "registry.hub.docker.com"];
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.index.v1+json, application/vnd.docker.distribution.manifest.list.v2+json, application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Location of an image tag within a registry following the OCI distribution API
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImageReference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: String,
+}
+
+impl ImageReference {
+    /// Splits an image repository such as `europe-west3-docker.pkg.dev/hoprassociation/docker-images/hoprd` into registry and repository path.
+    /// Repositories without a registry host are resolved against Docker Hub.
+    pub fn parse(image: &str, tag: &str) -> Self {
+        let (registry, repository) = match image.split_once('/') {
+            Some((host, path)) if host.contains('.') || host.contains(':') || host == "localhost" => (host.to_owned(), path.to_owned()),
+            Some(_) => (DOCKER_HUB_REGISTRY.to_owned(), image.to_owned()),
+            None => (DOCKER_HUB_REGISTRY.to_owned(), format!("library/{}", image)),
+        };
+        let registry = if registry == "docker.io" { DOCKER_HUB_REGISTRY.to_owned() } else { registry };
+        ImageReference { registry, repository, tag: tag.to_owned() }
+    }
+}
+
+/// Username and password of a registry, read from a `kubernetes.io/dockerconfigjson` pull secret
+#[derive(Debug, PartialEq, Clone)]
+pub struct RegistryCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: BTreeMap<String, DockerConfigAuth>,
+}
+
+#[derive(Deserialize)]
+struct DockerConfigAuth {
+    username: Option<String>,
+    password: Option<String>,
+    auth: Option<String>,
+}
+
+impl RegistryCredentials {
+    /// Finds the credentials of a registry in the content of a `.dockerconfigjson` key. Entries may be keyed by host or by URL
+    pub fn from_docker_config(docker_config: &[u8], registry: &str) -> Option<Self> {
+        let docker_config: DockerConfig = serde_json::from_slice(docker_config).ok()?;
+        let same_registry = |host: &str| host == registry || (DOCKER_HUB_ALIASES.contains(&host) && DOCKER_HUB_ALIASES.contains(&registry));
+        let auth = docker_config.auths.iter().find_map(|(key, auth)| {
+            let host = key.trim_start_matches("https://").trim_start_matches("http://").split('/').next().unwrap_or_default();
+            Some(auth).filter(|_| same_registry(host))
+        })?;
+        match (&auth.username, &auth.password, &auth.auth) {
+            (Some(username), Some(password), _) => Some(RegistryCredentials { username: username.to_owned(), password: password.to_owned() }),
+            (_, _, Some(encoded)) => {
+                let decoded = String::from_utf8(general_purpose::STANDARD.decode(encoded).ok()?).ok()?;
+                let (username, password) = decoded.split_once(':')?;
+                Some(RegistryCredentials { username: username.to_owned(), password: password.to_owned() })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reads the credentials of a registry from the first pull secret of the namespace holding them
+pub async fn get_registry_credentials(client: Client, namespace: &str, pull_secrets: &[String], registry: &str) -> Option<RegistryCredentials> {
+    let api: Api<Secret> = Api::namespaced(client, namespace);
+    for pull_secret in pull_secrets {
+        match api.get_opt(pull_secret).await {
+            Ok(secret) => {
+                let docker_config = secret.and_then(|secret| secret.data).and_then(|data| data.get(".dockerconfigjson").cloned());
+                if let Some(credentials) = docker_config.and_then(|docker_config| RegistryCredentials::from_docker_config(&docker_config.0, registry)) {
+                    return Some(credentials);
+                }
+            }
+            Err(error) => warn!("Could not read the pull secret {} in namespace {}: {}", pull_secret, namespace, error),
+        }
+    }
+    None
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+enum Authorization {
+    Bearer(String),
+    Basic(RegistryCredentials),
+}
+
+/// Client resolving image tags into digests using the registry API
+#[derive(Clone)]
+pub struct ImageRegistryClient {
+    client: reqwest::Client,
+    insecure_registries: Vec<String>,
+}
+
+impl ImageRegistryClient {
+    pub fn new(insecure_registries: Vec<String>) -> Self {
+        ImageRegistryClient {
+            client: reqwest::Client::new(),
+            insecure_registries,
+        }
+    }
+
+    fn manifest_url(&self, image: &ImageReference) -> String {
+        let scheme = if self.insecure_registries.contains(&image.registry) { "http" } else { "https" };
+        format!("{}://{}/v2/{}/manifests/{}", scheme, image.registry, image.repository, image.tag)
+    }
+
+    /// Returns the `sha256:` digest the registry currently serves for the image tag. Without credentials, an anonymous pull token is requested
+    pub async fn resolve_digest(&self, image: &ImageReference, credentials: Option<&RegistryCredentials>) -> Result<String, Error> {
+        let url = self.manifest_url(image);
+        debug!("Resolving digest of {}", url);
+        let mut response = self.head_manifest(&url, None).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let challenge = response
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| Error::ImageRegistryError(format!("Registry {} requires authentication without a challenge", image.registry)))?
+                .to_owned();
+            let authorization = match (challenge.starts_with("Basic"), credentials) {
+                (true, Some(credentials)) => Authorization::Basic(credentials.to_owned()),
+                (true, None) => return Err(Error::ImageRegistryError(format!("Registry {} requires credentials, none was found in the pull secrets", image.registry))),
+                (false, credentials) => Authorization::Bearer(self.request_token(&challenge, credentials).await?),
+            };
+            response = self.head_manifest(&url, Some(&authorization)).await?;
+        }
+        if !response.status().is_success() {
+            return Err(Error::ImageRegistryError(format!("Registry returned {} for {}", response.status(), url)));
+        }
+        match response.headers().get("Docker-Content-Digest").and_then(|value| value.to_str().ok()) {
+            Some(digest) if digest.starts_with("sha256:") => Ok(digest.to_owned()),
+            Some(digest) => Err(Error::ImageRegistryError(format!("Unsupported digest {} for {}", digest, url))),
+            None => Err(Error::ImageRegistryError(format!("Registry did not return a digest for {}", url))),
+        }
+    }
+
+    async fn head_manifest(&self, url: &str, authorization: Option<&Authorization>) -> Result<reqwest::Response, Error> {
+        let mut request = self.client.head(url).header(header::ACCEPT, MANIFEST_MEDIA_TYPES);
+        match authorization {
+            Some(Authorization::Bearer(token)) => request = request.bearer_auth(token),
+            Some(Authorization::Basic(credentials)) => request = request.basic_auth(&credentials.username, Some(&credentials.password)),
+            None => {}
+        }
+        request.send().await.map_err(|e| Error::ImageRegistryError(format!("Could not reach {}: {}", url, e)))
+    }
+
+    /// Requests a pull token following a `Bearer realm="...",service="...",scope="..."` challenge, authenticated with the credentials if any
+    async fn request_token(&self, challenge: &str, credentials: Option<&RegistryCredentials>) -> Result<String, Error> {
+        let parameters = challenge
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| Error::ImageRegistryError(format!("Unsupported authentication challenge: {}", challenge)))?;
+        let mut realm: Option<String> = None;
+        let mut query: Vec<(String, String)> = vec![];
+        for parameter in parameters.split(',') {
+            if let Some((key, value)) = parameter.trim().split_once('=') {
+                let value = value.trim_matches('"').to_owned();
+                match key {
+                    "realm" => realm = Some(value),
+                    "service" | "scope" => query.push((key.to_owned(), value)),
+                    _ => {}
+                }
+            }
+        }
+        let realm = realm.ok_or_else(|| Error::ImageRegistryError(format!("Missing realm in authentication challenge: {}", challenge)))?;
+        let mut request = self.client.get(&realm).query(&query);
+        if let Some(credentials) = credentials {
+            request = request.basic_auth(&credentials.username, Some(&credentials.password));
+        }
+        let token_response: TokenResponse = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::ImageRegistryError(format!("Could not get a pull token from {}: {}", realm, e)))?
+            .json()
+            .await
+            .map_err(|e| Error::ImageRegistryError(format!("Invalid token response from {}: {}", realm, e)))?;
+        token_response
+            .token
+            .or(token_response.access_token)
+            .ok_or_else(|| Error::ImageRegistryError(format!("Token response from {} does not contain a token", realm)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::State,
+        http::{HeaderMap, HeaderValue},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use serde_json::json;
+
+    const DIGEST: &str = "sha256:3f1b7c0e8d4a5b6c7d8e9f00112233445566778899aabbccddeeff0011223344";
+
+    async fn manifest(State(realm): State<String>, headers: HeaderMap) -> impl IntoResponse {
+        let mut response_headers = HeaderMap::new();
+        if headers.get(header::AUTHORIZATION) == Some(&HeaderValue::from_static("Bearer test-token")) {
+            response_headers.insert("Docker-Content-Digest", HeaderValue::from_static(DIGEST));
+            (StatusCode::OK, response_headers)
+        } else {
+            let challenge = format!("Bearer realm=\"{}\",service=\"local\",scope=\"repository:hoprassociation/hoprd:pull\"", realm);
+            response_headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_str(&challenge).unwrap());
+            (StatusCode::UNAUTHORIZED, response_headers)
+        }
+    }
+
+    /// Only the credentials of the pull secret get a token allowed to read the manifest
+    async fn token(headers: HeaderMap) -> Json<serde_json::Value> {
+        let authorized = headers.get(header::AUTHORIZATION) == Some(&HeaderValue::from_str(&format!("Basic {}", general_purpose::STANDARD.encode("robot:secret"))).unwrap());
+        Json(json!({ "token": if authorized { "test-token" } else { "anonymous-token" } }))
+    }
+
+    #[test]
+    fn test_parse_image_reference() {
+        let image = ImageReference::parse("europe-west3-docker.pkg.dev/hoprassociation/docker-images/hoprd", "latest");
+        assert_eq!(image.registry, "europe-west3-docker.pkg.dev");
+        assert_eq!(image.repository, "hoprassociation/docker-images/hoprd");
+        assert_eq!(ImageReference::parse("alpine", "3").repository, "library/alpine");
+        assert_eq!(ImageReference::parse("bitnami/kubectl", "1.33").registry, DOCKER_HUB_REGISTRY);
+        assert_eq!(ImageReference::parse("localhost:5000/hoprd", "latest").registry, "localhost:5000");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_digest_from_local_registry() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let registry = listener.local_addr().unwrap().to_string();
+        let app = Router::new()
+            .route("/v2/hoprassociation/hoprd/manifests/latest", get(manifest))
+            .route("/token", get(token))
+            .with_state(format!("http://{}/token", registry));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = ImageRegistryClient::new(vec![registry.clone()]);
+        let image = ImageReference::parse(&format!("{}/hoprassociation/hoprd", registry), "latest");
+        let docker_config = json!({ "auths": { format!("http://{}/v2/", registry): { "auth": general_purpose::STANDARD.encode("robot:secret") } } }).to_string();
+        let credentials = RegistryCredentials::from_docker_config(docker_config.as_bytes(), &registry);
+        assert!(client.resolve_digest(&image, None).await.is_err());
+        assert_eq!(client.resolve_digest(&image, credentials.as_ref()).await.unwrap(), DIGEST);
+        let missing_tag = ImageReference { tag: "missing".to_owned(), ..image };
+        assert!(client.resolve_digest(&missing_tag, credentials.as_ref()).await.is_err());
+    }
+
+    #[test]
+    fn test_credentials_from_docker_config() {
+        let docker_config = json!({ "auths": {
+            "https://index.docker.io/v1/": { "username": "hopr", "password": "hub" },
+            "europe-west3-docker.pkg.dev": { "auth": general_purpose::STANDARD.encode("_json_key:{\"type\":\"service_account\"}") },
+        } })
+        .to_string();
+        let hub = RegistryCredentials::from_docker_config(docker_config.as_bytes(), DOCKER_HUB_REGISTRY).unwrap();
+        assert_eq!((hub.username.as_str(), hub.password.as_str()), ("hopr", "hub"));
+        let artifact_registry = RegistryCredentials::from_docker_config(docker_config.as_bytes(), "europe-west3-docker.pkg.dev").unwrap();
+        assert_eq!(artifact_registry.username, "_json_key");
+        assert_eq!(artifact_registry.password, "{\"type\":\"service_account\"}");
+        assert_eq!(RegistryCredentials::from_docker_config(docker_config.as_bytes(), "ghcr.io"), None);
+        assert_eq!(RegistryCredentials::from_docker_config(b"not json", "ghcr.io"), None);
+    }
+}
@@ -1,6 +1,6 @@
 use kube::{Client, Result};
 use rustls::crypto::ring;
-use std::{env, sync::Arc};
+use std::{env, net::{IpAddr, SocketAddr}, sync::Arc};
 
 mod bootstrap_operator;
 mod cluster;
@@ -9,20 +9,28 @@ mod context_data;
 mod events;
 mod hoprd;
 mod identity_hoprd;
+mod image_registry;
 mod identity_pool;
 pub mod model;
 mod operator_config;
+mod operator_health;
 mod resource_generics;
 mod servicemonitor;
 mod utils;
 mod webhook_server;
 
-use crate::{context_data::ContextData, operator_config::OperatorConfig};
+use crate::{
+    context_data::ContextData,
+    operator_config::OperatorConfig,
+    operator_health::{OperatorHealth, COMPONENT_CONTROLLERS},
+};
+use axum_server::Handle;
 use futures::{
-    future::FutureExt, // for `.fuse()`
+    future::{FusedFuture, FutureExt}, // for `.fuse()`
     pin_mut,
     select,
 };
+use tokio::{signal, time::Duration};
 use tracing::{info};
 use tracing_subscriber::{Layer, layer::SubscriberExt};
 
@@ -37,24 +45,66 @@ async fn main() -> Result<()> {
     let operator_config = load_operator_config().await.expect("Failed to load operator configuration");
 
     // 3. Determine operator mode and start appropriate components  
+    ring::default_provider().install_default().expect("failed to install rustls ring CryptoProvider");
+    let health = Arc::new(OperatorHealth::default());
     let mode = std::env::var("OPERATOR_MODE").unwrap_or_else(|_| "controller".into());
+    let bind_address: IpAddr = operator_config.webhook.bind_address.parse().expect("Invalid webhook bind address");
+    tokio::spawn(operator_health::run_health_server(SocketAddr::new(bind_address, operator_config.webhook.health_port), health.clone()));
     match mode.as_str() {
         "webhook" => {
             info!("Starting in Webhook mode");
-            webhook_server::run_webhook_server(operator_config.webhook).await;
+            let handle = Handle::new();
+            tokio::spawn(shutdown_webhook_on_signal(handle.clone()));
+            webhook_server::run_webhook_server(operator_config.webhook, health, handle).await;
         }
         "controller" => {
             info!("Starting in Controller mode");
-            start_controllers(operator_config.clone()).await;
+            start_controllers(operator_config.clone(), health).await;
+        }
+        "all" => {
+            info!("Starting in All mode");
+            let handle = Handle::new();
+            tokio::spawn(shutdown_webhook_on_signal(handle.clone()));
+            let webhook = webhook_server::run_webhook_server(operator_config.webhook.clone(), health.clone(), handle.clone()).fuse();
+            let controllers = start_controllers(operator_config.clone(), health.clone()).fuse();
+            pin_mut!(webhook, controllers);
+            select! {
+                () = webhook => info!("Webhook server exited"),
+                () = controllers => info!("Controllers exited"),
+            }
+            // Whichever component stops first brings down the other one
+            if !webhook.is_terminated() {
+                handle.graceful_shutdown(Some(Duration::from_secs(constants::OPERATOR_SHUTDOWN_TIMEOUT)));
+                webhook.await;
+            }
         }
         _ => {
-            panic!("Invalid OPERATOR_MODE: {}. Must be either 'webhook' or 'controller'", mode);
+            panic!("Invalid OPERATOR_MODE: {}. Must be either 'webhook', 'controller' or 'all'", mode);
         }
     }
 
     Ok(())
 }
 
+/// Stops the webhook server gracefully upon SIGINT or SIGTERM, as the controllers do
+async fn shutdown_webhook_on_signal(handle: Handle) {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("Failed to listen for SIGINT");
+    };
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    tokio::select! {
+        () = ctrl_c => {},
+        _ = terminate => {},
+    }
+    info!("Shutdown signal received, stopping webhook server");
+    handle.graceful_shutdown(Some(Duration::from_secs(constants::OPERATOR_SHUTDOWN_TIMEOUT)));
+}
+
 /// Load operator configuration from file based on environment
 async fn load_operator_config() -> Result<OperatorConfig, String> {
     let operator_environment = env::var(constants::OPERATOR_ENVIRONMENT).expect("The OPERATOR_ENVIRONMENT environment variable is not set");
@@ -76,10 +126,10 @@ async fn load_operator_config() -> Result<OperatorConfig, String> {
 }
 
 // Start all Kubernetes controllers
-async fn start_controllers(operator_config: operator_config::OperatorConfig) {
+async fn start_controllers(operator_config: operator_config::OperatorConfig, health: Arc<OperatorHealth>) {
     // ⭐ 4. Initialize Kubernetes client and context data
     info!("Initializing Context Data...");
-    ring::default_provider().install_default().expect("failed to install rustls ring CryptoProvider");
+    health.set(COMPONENT_CONTROLLERS, false);
     let client: Client = Client::try_default().await.expect("Failed to create kube Client");
     let context_data: Arc<ContextData> = Arc::new(ContextData::new(client.clone(), operator_config).await);
     context_data.sync_identities().await.expect("Failed to sync identities");
@@ -94,12 +144,14 @@ async fn start_controllers(operator_config: operator_config::OperatorConfig) {
     let controller_cluster = cluster::cluster_controller::run(client.clone(), context_data.clone()).fuse();
 
     pin_mut!(controller_identity_pool, controller_identity_hoprd, controller_hoprd, controller_cluster);
+    health.set(COMPONENT_CONTROLLERS, true);
     select! {
         () = controller_identity_pool => println!("Controller IdentityPool exited"),
         () = controller_identity_hoprd => println!("Controller IdentityHoprd exited"),
         () = controller_hoprd => println!("Controller Hoprd exited"),
         () = controller_cluster => println!("Controller ClusterHoprd exited"),
     }
+    health.set(COMPONENT_CONTROLLERS, false);
 }
 
 
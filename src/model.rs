@@ -17,6 +17,9 @@ pub enum Error {
     #[error("IdentityHoprdPVC error: {0}")]
     IdentityHoprdPVCError(String),
 
+    #[error("IdentityHoprdSecret error: {0}")]
+    IdentityHoprdSecretError(String),
+
     /// The hoprd is in an Unknown status
     #[error("Invalid Hoprd status: {0}")]
     HoprdStatusError(String),
@@ -46,4 +49,16 @@ pub enum Error {
     /// The Job execution did not complete successfully
     #[error("There is an issue with the identity: {0}")]
     IdentityIssue(String),
+
+    /// The image registry could not resolve an image
+    #[error("Image registry error: {0}")]
+    ImageRegistryError(String),
+
+    /// The hoprd node API could not be queried
+    #[error("Hoprd API error: {0}")]
+    HoprdApiError(String),
+
+    /// The RPC provider of a network could not be queried
+    #[error("RPC provider error: {0}")]
+    RpcError(String),
 }
@@ -1,6 +1,9 @@
+use k8s_openapi::api::core::v1::LocalObjectReference;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
+use crate::constants;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash)]
 pub struct OperatorConfig {
     pub instance: OperatorInstance,
@@ -9,6 +12,13 @@ pub struct OperatorConfig {
     pub hopli_rpc_provider_url: String,
     pub persistence: PersistenceConfig,
     pub webhook: WebhookConfig,
+    #[serde(default)]
+    pub images: ImagesConfig,
+    #[serde(default)]
+    pub health: HealthConfig,
+    /// On-chain settings of each network, by network name. Identities of networks not listed here are not validated on-chain
+    #[serde(default)]
+    pub networks: BTreeMap<String, NetworkConfig>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash)]
@@ -39,4 +49,140 @@ pub struct PersistenceConfig {
 pub struct WebhookConfig {
     pub crt_file: String,
     pub key_file: String,
+    #[serde(default = "WebhookConfig::default_bind_address")]
+    pub bind_address: String,
+    #[serde(default = "WebhookConfig::default_port")]
+    pub port: u16,
+    /// Port of the health endpoint, served in every operator mode
+    #[serde(default = "WebhookConfig::default_health_port")]
+    pub health_port: u16,
+    /// Seconds between checks of the certificate files for changes
+    #[serde(default = "WebhookConfig::default_reload_interval")]
+    pub reload_interval: u64,
+}
+
+impl WebhookConfig {
+    fn default_bind_address() -> String {
+        "0.0.0.0".to_owned()
+    }
+
+    fn default_port() -> u16 {
+        8443
+    }
+
+    fn default_health_port() -> u16 {
+        8080
+    }
+
+    fn default_reload_interval() -> u64 {
+        30
+    }
+}
+
+/// Images of the containers created by the operator, allowing to pull them from a mirrored registry
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Hash)]
+#[serde(default)]
+pub struct ImagesConfig {
+    /// Repository of the hoprd image. The tag is taken from the version of each node
+    pub hoprd: String,
+    pub metrics: String,
+    pub init: String,
+    /// Image downloading and verifying the logs snapshot of the nodes
+    pub snapshot: String,
+    pub delete_database: String,
+    pub kubectl: String,
+    /// Pull policy of the containers created by the operator. The hoprd container is always pulled if not set
+    pub pull_policy: Option<String>,
+    /// Names of the secrets used to pull the images
+    pub pull_secrets: Vec<String>,
+    /// Whether the hoprd image tags are resolved into digests through the registry API when rolling out a node
+    pub resolve_digests: bool,
+    /// Registries reached over plain HTTP when resolving digests
+    pub insecure_registries: Vec<String>,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        Self {
+            hoprd: format!("{}/{}", constants::HOPR_DOCKER_REGISTRY, constants::HOPR_DOCKER_IMAGE_NAME),
+            metrics: format!("{}/{}:{}", constants::HOPR_DOCKER_REGISTRY, constants::HOPR_DOCKER_METRICS_IMAGE_NAME, constants::OPERATOR_METRICS_CONTAINER_TAG),
+            init: "alpine".to_owned(),
+            snapshot: format!("{}/{}:{}", constants::HOPR_DOCKER_REGISTRY, constants::HOPR_DOCKER_SNAPSHOT_IMAGE_NAME, constants::OPERATOR_SNAPSHOT_CONTAINER_TAG),
+            delete_database: "debian:stable".to_owned(),
+            kubectl: "registry.hub.docker.com/bitnamilegacy/kubectl:1.33".to_owned(),
+            pull_policy: None,
+            pull_secrets: vec![],
+            resolve_digests: true,
+            insecure_registries: vec![],
+        }
+    }
+}
+
+/// Thresholds used to flag running nodes as degraded
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Hash)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Lowest network health (`Red`, `Orange`, `Yellow` or `Green`) considered healthy
+    pub min_network_health: String,
+    /// Seconds the network health must stay below the threshold, or unknown as the node API is unreachable, before the node is degraded
+    pub degraded_window: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            min_network_health: "Green".to_owned(),
+            degraded_window: 300,
+        }
+    }
+}
+
+/// Contracts and thresholds used to validate the identities of a network on-chain
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Hash, Default)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// RPC provider of the network. Defaults to `hopli_rpc_provider_url`
+    pub rpc_provider_url: Option<String>,
+    /// Address of the contract registering the safe of each node
+    pub node_safe_registry_address: String,
+    /// Address of the HOPR token contract
+    pub token_address: String,
+    /// Minimum native balance of the node, in ether
+    pub min_node_native_balance: Option<String>,
+    /// Minimum HOPR balance of the safe, in ether
+    pub min_safe_token_balance: Option<String>,
+}
+
+impl OperatorConfig {
+    /// RPC provider of the network, falling back to the one used by hopli
+    pub fn rpc_provider_url(&self, network: &str) -> String {
+        self.networks.get(network).and_then(|network| network.rpc_provider_url.clone()).unwrap_or(self.hopli_rpc_provider_url.to_owned())
+    }
+}
+
+impl ImagesConfig {
+    pub fn get_pull_secrets(&self) -> Option<Vec<LocalObjectReference>> {
+        if self.pull_secrets.is_empty() {
+            None
+        } else {
+            Some(self.pull_secrets.iter().map(|name| LocalObjectReference { name: name.to_owned() }).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_images_default_when_missing() {
+        let config: OperatorConfig = serde_yml::from_reader(std::fs::File::open("test-data/sample_config-staging.yaml").unwrap()).unwrap();
+        assert_eq!(config.images, ImagesConfig::default());
+        assert_eq!(config.images.get_pull_secrets(), None);
+
+        let images: ImagesConfig = serde_yml::from_str("hoprd: registry.local/hoprd\npull_secrets: [mirror]\n").unwrap();
+        assert_eq!(images.hoprd, "registry.local/hoprd");
+        assert_eq!(images.init, "alpine");
+        assert_eq!(images.get_pull_secrets().unwrap()[0].name, "mirror");
+    }
 }
@@ -0,0 +1,64 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use std::{collections::BTreeMap, net::SocketAddr, sync::{Arc, RwLock}};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+pub const COMPONENT_WEBHOOK: &str = "webhook";
+pub const COMPONENT_CONTROLLERS: &str = "controllers";
+
+/// Health of the components running within the operator process
+#[derive(Default, Debug)]
+pub struct OperatorHealth {
+    components: RwLock<BTreeMap<String, bool>>,
+}
+
+impl OperatorHealth {
+    pub fn set(&self, component: &str, healthy: bool) {
+        self.components.write().unwrap().insert(component.to_owned(), healthy);
+    }
+
+    /// The operator is healthy when at least one component is registered and all of them are healthy
+    pub fn is_healthy(&self) -> bool {
+        let components = self.components.read().unwrap();
+        !components.is_empty() && components.values().all(|healthy| *healthy)
+    }
+
+    pub fn report(&self) -> BTreeMap<String, bool> {
+        self.components.read().unwrap().clone()
+    }
+}
+
+async fn healthz(State(health): State<Arc<OperatorHealth>>) -> impl IntoResponse {
+    let status_code = if health.is_healthy() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status_code, Json(health.report()))
+}
+
+/// Serves the plain HTTP health endpoint used by the kubelet probes, whichever components run in the process
+pub async fn run_health_server(addr: SocketAddr, health: Arc<OperatorHealth>) {
+    let app = Router::new().route("/healthz", get(healthz)).with_state(health);
+    match TcpListener::bind(addr).await {
+        Ok(listener) => {
+            info!("Health server listening on {}", addr);
+            if let Err(error) = axum::serve(listener, app).await {
+                error!("Health server stopped with error: {}", error);
+            }
+        }
+        Err(error) => error!("Could not bind health server on {}: {}", addr, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_healthy_requires_all_components() {
+        let health = OperatorHealth::default();
+        assert!(!health.is_healthy());
+        health.set(COMPONENT_WEBHOOK, true);
+        health.set(COMPONENT_CONTROLLERS, false);
+        assert!(!health.is_healthy());
+        health.set(COMPONENT_CONTROLLERS, true);
+        assert!(health.is_healthy());
+    }
+}
@@ -6,7 +6,8 @@ use kube::{
     Api, ResourceExt,
 };
 use serde::de::DeserializeOwned;
-use serde_json::json;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use tracing::{debug, error};
 
@@ -51,3 +52,42 @@ pub async fn delete_finalizer<K: kube::Resource<Scope = NamespaceResourceScope,
     }
     Ok(())
 }
+
+/// Merge patch setting the given labels and removing the ones of the previous configuration that are no longer set
+fn labels_patch(labels: &BTreeMap<String, String>, previous_labels: &BTreeMap<String, String>) -> Value {
+    let mut patch_labels: Map<String, Value> = previous_labels.keys().filter(|key| !labels.contains_key(*key)).map(|key| (key.to_owned(), Value::Null)).collect();
+    patch_labels.extend(labels.iter().map(|(key, value)| (key.to_owned(), json!(value))));
+    json!({
+        "metadata": {
+            "labels": patch_labels
+        }
+    })
+}
+
+/// Merges the given labels into an existing resource, removing the previous ones that are no longer set. Skips the resource if it does not exist
+pub async fn update_labels<K: kube::Resource<Scope = NamespaceResourceScope, DynamicType = ()> + Clone + Debug + DeserializeOwned>(
+    client: Client,
+    name: &str,
+    namespace: &str,
+    labels: &BTreeMap<String, String>,
+    previous_labels: &BTreeMap<String, String>,
+) -> Result<(), kube::Error> {
+    let api: Api<K> = Api::namespaced(client, namespace);
+    if api.get_opt(name).await?.is_some() {
+        api.patch(name, &PatchParams::default(), &Patch::Merge(labels_patch(labels, previous_labels))).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_patch() {
+        let labels = BTreeMap::from([("team".to_owned(), "core".to_owned()), ("tier".to_owned(), "gold".to_owned())]);
+        let previous_labels = BTreeMap::from([("team".to_owned(), "apps".to_owned()), ("owner".to_owned(), "alice".to_owned())]);
+        assert_eq!(labels_patch(&labels, &previous_labels), json!({ "metadata": { "labels": { "team": "core", "tier": "gold", "owner": null } } }));
+        assert_eq!(labels_patch(&BTreeMap::new(), &BTreeMap::new()), json!({ "metadata": { "labels": {} } }));
+    }
+}
@@ -1,4 +1,6 @@
 use crate::constants;
+use chrono::Utc;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
 use std::collections::BTreeMap;
 
 pub fn common_lables(name: String, instance: Option<String>, component: Option<String>) -> BTreeMap<String, String> {
@@ -12,3 +14,38 @@ pub fn common_lables(name: String, instance: Option<String>, component: Option<S
     }
     labels
 }
+
+/// Keeps the labels defined by users, dropping the ones in the prefixes managed by the operator
+pub fn user_labels(labels: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    labels
+        .iter()
+        .filter(|(key, _)| !constants::LABEL_RESERVED_PREFIXES.iter().any(|prefix| key.starts_with(prefix)))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect()
+}
+
+/// Adds or replaces a condition by type, keeping its transition time while the status does not change
+pub fn set_condition(conditions: &mut Vec<Condition>, type_: &str, status: &str, reason: &str, message: String, observed_generation: Option<i64>) {
+    let last_transition_time = match conditions.iter().find(|condition| condition.type_ == type_) {
+        Some(condition) if condition.status == status => condition.last_transition_time.clone(),
+        _ => Time(Utc::now()),
+    };
+    conditions.retain(|condition| condition.type_ != type_);
+    conditions.push(Condition {
+        type_: type_.to_owned(),
+        status: status.to_owned(),
+        reason: reason.to_owned(),
+        message,
+        observed_generation,
+        last_transition_time,
+    });
+}
+
+/// Reads the schema of a CRD version from the Helm chart, skipping the templated lines
+#[cfg(test)]
+pub fn crd_schema_from_chart(chart_file: &str, version: &str) -> serde_json::Value {
+    let template = std::fs::read_to_string(chart_file).unwrap();
+    let crd = template.lines().filter(|line| !line.contains("{{")).collect::<Vec<&str>>().join("\n");
+    let crd: serde_json::Value = serde_yml::from_str(&crd).unwrap();
+    crd["spec"]["versions"].as_array().unwrap().iter().find(|crd_version| crd_version["name"] == version).unwrap()["schema"].clone()
+}
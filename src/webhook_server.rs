@@ -1,18 +1,26 @@
-use anyhow::Error;
+use anyhow::{Context, Error};
 use axum::{Json, Router, response::IntoResponse, routing::post};
-use axum_server::{tls_rustls::{RustlsConfig, bind_rustls}};
+use axum_server::{Handle, tls_rustls::{RustlsConfig, bind_rustls}};
 use rustls::{ServerConfig, pki_types::{CertificateDer, PrivateKeyDer}};
-use rustls_pemfile::{certs, pkcs8_private_keys};
-use std::{env, io::BufReader, net::SocketAddr};
-use std::collections::hash_map::DefaultHasher;
+use rustls_pemfile::{certs, private_key};
+use std::{env, io::BufReader, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, path::{Path, PathBuf}, sync::Arc};
+use std::collections::{hash_map::DefaultHasher, BTreeMap};
 use std::hash::{Hash, Hasher};
-use rustls::crypto::ring::default_provider;
 use tracing::{trace, error, info, warn};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{Value};
 use tokio::net::TcpStream;
 use tokio::time::{sleep, Duration};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::core::{admission::{AdmissionRequest, AdmissionResponse, AdmissionReview}, DynamicObject};
+use kube::CustomResourceExt;
+use crate::cluster::{cluster_hoprd::{ClusterHoprd, ClusterHoprdSpec}, cluster_hoprd_v1beta1};
+use crate::hoprd::{hoprd_deployment_spec::HoprdDeploymentSpec, hoprd_resource::{Hoprd, HoprdSpec}, hoprd_service::HoprdServiceSpec, hoprd_v1beta1};
+use crate::identity_hoprd::identity_hoprd_resource::IdentityHoprd;
+use crate::identity_pool::identity_pool_resource::IdentityPool;
+use crate::constants;
 use crate::operator_config::WebhookConfig;
+use crate::operator_health::{OperatorHealth, COMPONENT_WEBHOOK};
 
 #[derive(Deserialize, Serialize, Debug)]
 struct ConversionRequest {
@@ -49,8 +57,20 @@ struct StatusResult {
     message: Option<String>,
 }
 
-async fn wait_for_webhook_ready() -> Result<(), String> {
-    let addr = "127.0.0.1:8443";  // use localhost, not 0.0.0.0
+/// Address dialed to check the webhook server, which is the bound one unless the server listens on all the interfaces
+fn readiness_address(bound_addr: SocketAddr) -> SocketAddr {
+    match bound_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), bound_addr.port()),
+        IpAddr::V6(ip) if ip.is_unspecified() => SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), bound_addr.port()),
+        _ => bound_addr,
+    }
+}
+
+async fn wait_for_webhook_ready(handle: &Handle) -> Result<(), String> {
+    let Some(bound_addr) = handle.listening().await else {
+        return Err("Webhook server could not bind its address".to_owned());
+    };
+    let addr = readiness_address(bound_addr);
     info!("Waiting for webhook to be ready");
     for _ in 0..50 {
         if TcpStream::connect(addr).await.is_ok() {
@@ -62,63 +82,101 @@ async fn wait_for_webhook_ready() -> Result<(), String> {
     Err(format!("Webhook did not start on {}", addr))
 }
 
-fn load_rustls_config(cert_path: &str, key_path: &str) -> Result<ServerConfig, Error> {
-    // Install the ring crypto provider globally
-    default_provider().install_default().expect("Install ring provider");
+/// Absolute paths are used as they are, relative paths are resolved against the current directory
+fn resolve_path(path: &str) -> Result<PathBuf, Error> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(env::current_dir()?.join(path))
+    }
+}
+
+/// Reads the raw content of the certificate and key files, used to detect rotations
+fn read_tls_files(cert_path: &Path, key_path: &Path) -> Result<(Vec<u8>, Vec<u8>), Error> {
+    let cert = std::fs::read(cert_path).with_context(|| format!("Could not read cert file: {}", cert_path.display()))?;
+    let key = std::fs::read(key_path).with_context(|| format!("Could not read key file: {}", key_path.display()))?;
+    Ok((cert, key))
+}
 
+fn parse_rustls_config(cert_pem: &[u8], key_pem: &[u8]) -> Result<ServerConfig, Error> {
     // Load certificate chain
-    let dir = env::current_dir()?
-        .to_str()
-        .ok_or_else(|| anyhow::anyhow!("Invalid UTF-8 in current directory path"))?
-        .to_owned();
-    let cert_path = format!("{}/{}", dir.clone(), cert_path);
-    let cert_file = std::fs::File::open(&cert_path).expect(format!("Could not open cert file: {}", cert_path).as_str());
-    let mut cert_reader = BufReader::new(cert_file);
-    let cert_chain = certs(&mut cert_reader)
-        .collect::<Result<Vec<_>, _>>()?
-        .into_iter()
-        .map(CertificateDer::from)
-        .collect::<Vec<_>>();
-
-    // Load private key
-    let key_path = format!("{}/{}", dir.clone(), key_path);
-    let key_file = std::fs::File::open(&key_path).expect(format!("Could not open key file: {}", key_path).as_str());
-    let mut key_reader = BufReader::new(key_file);
-    let mut keys_raw = pkcs8_private_keys(&mut key_reader)
-        .collect::<Result<Vec<_>, _>>()?;
-    if keys_raw.is_empty() {
-        return Err(anyhow::anyhow!("No private keys found in {}", key_path));
-    }
-    let key = PrivateKeyDer::Pkcs8(keys_raw.remove(0).into());
+    let cert_chain = certs(&mut BufReader::new(cert_pem)).collect::<Result<Vec<CertificateDer>, _>>()?;
+    if cert_chain.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found"));
+    }
+
+    // Load private key, either PKCS#8, PKCS#1 (RSA) or SEC1 (EC)
+    let key: PrivateKeyDer = private_key(&mut BufReader::new(key_pem))?.ok_or_else(|| anyhow::anyhow!("No private keys found"))?;
 
     // Build server config (no client auth)
-    let mut cfg = ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(cert_chain, key)?;
+    let mut cfg = ServerConfig::builder().with_no_client_auth().with_single_cert(cert_chain, key)?;
 
     cfg.alpn_protocols = vec![b"http/1.1".to_vec()];
 
     Ok(cfg)
 }
 
-pub async fn run_webhook_server(webhook_config:WebhookConfig) {
+fn load_rustls_config(cert_path: &Path, key_path: &Path) -> Result<ServerConfig, Error> {
+    let (cert_pem, key_pem) = read_tls_files(cert_path, key_path)?;
+    parse_rustls_config(&cert_pem, &key_pem).with_context(|| format!("Invalid TLS files {} and {}", cert_path.display(), key_path.display()))
+}
+
+/// Periodically checks the certificate files and reloads the TLS configuration when their content changes
+async fn watch_tls_files(tls_config: RustlsConfig, cert_path: PathBuf, key_path: PathBuf, reload_interval: u64) {
+    let mut current_files = read_tls_files(&cert_path, &key_path).ok();
+    loop {
+        sleep(Duration::from_secs(reload_interval)).await;
+        let files = match read_tls_files(&cert_path, &key_path) {
+            Ok(files) => files,
+            Err(error) => {
+                warn!("Could not check TLS files for changes: {:#}", error);
+                continue;
+            }
+        };
+        if current_files.as_ref() == Some(&files) {
+            continue;
+        }
+        // Files might be read in the middle of a rotation, so they are retried on next iteration if invalid
+        match parse_rustls_config(&files.0, &files.1) {
+            Ok(server_config) => {
+                tls_config.reload_from_config(Arc::new(server_config));
+                current_files = Some(files);
+                info!("Webhook TLS certificate reloaded from {}", cert_path.display());
+            }
+            Err(error) => warn!("Could not reload webhook TLS certificate: {:#}", error),
+        }
+    }
+}
+
+pub async fn run_webhook_server(webhook_config: WebhookConfig, health: Arc<OperatorHealth>, handle: Handle) {
+    health.set(COMPONENT_WEBHOOK, false);
     // Define Axum app with routes
-    let app = Router::new().route("/convert", post(convert));
-    let addr = SocketAddr::from(([0, 0, 0, 0], 8443));
+    let app = Router::new().route("/convert", post(convert)).route("/mutate", post(mutate));
+    let bind_address: IpAddr = webhook_config.bind_address.parse().expect("Invalid webhook bind address");
+    let addr = SocketAddr::new(bind_address, webhook_config.port);
 
     info!("Starting webhook server with TLS");
-    let server_config: ServerConfig = load_rustls_config(webhook_config.crt_file.as_str(), webhook_config.key_file.as_str()).expect("Invalid TLS");
+    let cert_path = resolve_path(&webhook_config.crt_file).expect("Invalid cert file path");
+    let key_path = resolve_path(&webhook_config.key_file).expect("Invalid key file path");
+    let server_config: ServerConfig = load_rustls_config(&cert_path, &key_path).expect("Invalid TLS");
     let tls_config = RustlsConfig::from_config(server_config.into());
+    tokio::spawn(watch_tls_files(tls_config.clone(), cert_path, key_path, webhook_config.reload_interval));
     // Spawn the server in a background task
+    let webhook_handle = handle.clone();
     let server_handle = tokio::spawn(async move {
         bind_rustls(addr, tls_config)
+            .handle(webhook_handle)
             .serve(app.into_make_service())
             .await
     });
 
     // Wait for the server to be ready
-    match wait_for_webhook_ready().await {
-        Ok(_) => info!("Webhook server is ready and listening on {}", addr),
+    match wait_for_webhook_ready(&handle).await {
+        Ok(_) => {
+            health.set(COMPONENT_WEBHOOK, true);
+            info!("Webhook server is ready and listening on {}", addr)
+        }
         Err(e) => error!("Failed to start webhook server: {}", e),
     }
     
@@ -128,285 +186,377 @@ pub async fn run_webhook_server(webhook_config:WebhookConfig) {
         Ok(Err(e)) => error!("Webhook server stopped with error: {}", e),
         Err(e) => error!("Webhook server task panicked: {}", e),
     }
+    health.set(COMPONENT_WEBHOOK, false);
 }
 
-async fn add_observed_generation(resource: &mut Value) -> Result<(), String> {
-    let observed_generation = resource.get("metadata")
-        .and_then(|m| m.get("generation"))
-        .cloned()
-        .unwrap_or(Value::Number(0.into()));
-    trace!("Setting observedGeneration to {:?}", observed_generation);
-    let status = resource.get_mut("status").ok_or("Missing 'status' field in v3 object")?;
-    let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
-    trace!("Current status object before update: {:?}", status_obj);
-    status_obj.insert("observedGeneration".to_owned(), observed_generation);
-    status_obj.remove("checksum");
-    trace!("Updated status object after insert: {:?}", status_obj);
-    Ok(())
+/// Field that cannot be derived when converting back to the original version. A null value means that the field was not present in the original version.
+/// The reverse conversion derives `derived` for the field, so deriving a different value means that it was written since through another version
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+struct PreservedField {
+    value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    derived: Option<Value>,
 }
 
+/// Fields that cannot be derived when converting back to the original version, indexed by JSON pointer
+type PreservedFields = BTreeMap<String, PreservedField>;
 
-async fn add_status_checksum(resource: &mut Value) -> Result<(), String> {
-    // Clone the spec value before mutably borrowing resource
-    let spec = resource.get("spec").cloned().ok_or("Missing 'spec' field in object")?;
-    let status = resource.get_mut("status").ok_or("Missing 'status' field in object")?;
-    let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
-
-    let mut hasher: DefaultHasher = DefaultHasher::new();
-    spec.hash(&mut hasher);
-    let checksum = hasher.finish().to_string();
-
-    status_obj.insert("checksum".to_string(), Value::String(checksum.clone()));
-    status_obj.remove("observedGeneration");
+fn insert_preserved_field(preserved_fields: &mut PreservedFields, pointer: &str, value: Value, derived: Option<Value>) {
+    preserved_fields.insert(pointer.to_owned(), PreservedField { value, derived });
+}
 
-    Ok(())
+/// Spec and status fields of the schema of a served version
+fn schema_fields(crd: CustomResourceDefinition) -> (Vec<String>, Vec<String>) {
+    let properties = |section: &str| -> Vec<String> {
+        crd.spec
+            .versions
+            .first()
+            .and_then(|version| version.schema.as_ref())
+            .and_then(|schema| schema.open_api_v3_schema.as_ref())
+            .and_then(|schema| schema.properties.as_ref())
+            .and_then(|properties| properties.get(section))
+            .and_then(|section| section.properties.as_ref())
+            .map(|properties| properties.keys().cloned().collect())
+            .unwrap_or_default()
+    };
+    (properties("spec"), properties("status"))
 }
 
-// async fn add_new_updated_timestamp(resource: &mut Value) -> Result<(), String> {
-//     let status = resource.get_mut("status").ok_or("Missing 'status' field in object")?;
-//     let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
+/// Spec and status fields known by each served version of the resources. The fields of the storage version are read from its schema,
+/// while the ones of `v1alpha2`, which is no longer generated, are listed
+fn known_fields(kind: &str, api_version: &str) -> Result<(Vec<String>, Vec<String>), String> {
+    let listed = |spec: &[&str], status: &[&str]| Ok((spec.iter().map(|field| field.to_string()).collect(), status.iter().map(|field| field.to_string()).collect()));
+    match (kind, api_version) {
+        ("ClusterHoprd", "v1alpha2") => listed(
+            &["identityPoolName", "replicas", "config", "version", "enabled", "supportedRelease", "forceIdentityName", "portsAllocation", "service", "deployment", "profilingEnabled", "sourceNodeLogs"],
+            &["updateTimestamp", "checksum", "phase", "currentNodes"],
+        ),
+        ("ClusterHoprd", "v1alpha3") => Ok(schema_fields(ClusterHoprd::crd())),
+        ("Hoprd", "v1alpha2") => listed(
+            &["identityPoolName", "identityName", "version", "config", "enabled", "deleteDatabase", "supportedRelease", "portsAllocation", "service", "deployment", "profilingEnabled", "sourceNodeLogs"],
+            &["updateTimestamp", "checksum", "phase", "identityName"],
+        ),
+        ("Hoprd", "v1alpha3") => Ok(schema_fields(Hoprd::crd())),
+        ("IdentityHoprd", "v1alpha2") => listed(
+            &["identityPoolName", "identityFile", "peerId", "nativeAddress", "safeAddress", "moduleAddress"],
+            &["updateTimestamp", "checksum", "phase", "hoprdNodeName"],
+        ),
+        ("IdentityHoprd", "v1alpha3") => Ok(schema_fields(IdentityHoprd::crd())),
+        ("IdentityPool", "v1alpha2") => listed(&["network", "secretName", "funding"], &["updateTimestamp", "checksum", "phase", "size", "locked"]),
+        ("IdentityPool", "v1alpha3") => Ok(schema_fields(IdentityPool::crd())),
+        _ => Err(format!("Unsupported kind {} for version {}", kind, api_version)),
+    }
+}
 
-//     let timestamp = Utc::now().to_rfc3339();
+/// Removes the preserved fields annotation left by a previous conversion
+fn take_preserved_fields(resource: &mut Value) -> Result<PreservedFields, String> {
+    let Some(annotations) = resource.pointer_mut("/metadata/annotations").and_then(|a| a.as_object_mut()) else {
+        return Ok(PreservedFields::new());
+    };
+    let preserved_fields = match annotations.remove(constants::ANNOTATION_CONVERSION_PRESERVED_FIELDS) {
+        Some(Value::String(preserved_fields)) => serde_json::from_str(&preserved_fields).map_err(|e| format!("Invalid preserved fields annotation: {}", e))?,
+        _ => PreservedFields::new(),
+    };
+    if annotations.is_empty() {
+        resource["metadata"].as_object_mut().ok_or("Metadata is not a JSON object")?.remove("annotations");
+    }
+    Ok(preserved_fields)
+}
 
-//     status_obj.insert("updateTimestampNew".to_string(), Value::String(timestamp.clone()));
-//     status_obj.remove("updateTimestamp");
+/// Stores in an annotation the fields that would be lost by the conversion
+fn store_preserved_fields(resource: &mut Value, preserved_fields: PreservedFields) -> Result<(), String> {
+    if preserved_fields.is_empty() {
+        return Ok(());
+    }
+    let preserved_fields = serde_json::to_string(&preserved_fields).map_err(|e| e.to_string())?;
+    let metadata = resource.get_mut("metadata").and_then(|m| m.as_object_mut()).ok_or("Missing 'metadata' field in object")?;
+    let annotations = metadata.entry("annotations").or_insert_with(|| Value::Object(serde_json::Map::new()));
+    annotations
+        .as_object_mut()
+        .ok_or("Annotations is not a JSON object")?
+        .insert(constants::ANNOTATION_CONVERSION_PRESERVED_FIELDS.to_owned(), Value::String(preserved_fields));
+    Ok(())
+}
 
-//     Ok(())
-// }
+/// Restores the fields preserved by a previous conversion, overriding the derived values. Fields written since through another version keep the written value
+fn restore_preserved_fields(resource: &mut Value, preserved_fields: PreservedFields) {
+    for (pointer, PreservedField { value, derived }) in preserved_fields {
+        let Some((parent_pointer, field)) = pointer.rsplit_once('/') else {
+            warn!("Ignoring invalid preserved field {}", pointer);
+            continue;
+        };
+        if resource.pointer(&pointer).is_some_and(|current| Some(current) != derived.as_ref()) {
+            trace!("Discarding preserved field {} as it was written since", pointer);
+            continue;
+        }
+        match resource.pointer_mut(parent_pointer).and_then(|parent| parent.as_object_mut()) {
+            Some(parent) if value.is_null() => {
+                parent.remove(field);
+            }
+            Some(parent) => {
+                parent.insert(field.to_owned(), value);
+            }
+            None => warn!("Ignoring preserved field {} as its parent is missing", pointer),
+        }
+    }
+}
 
-// async fn add_old_updated_timestamp(resource: &mut Value) -> Result<(), String> {
-//     let status = resource.get_mut("status").ok_or("Missing 'status' field in object")?;
-//     let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
+/// Removes a field that does not exist in the target version, preserving it unless the reverse conversion derives the same value
+fn preserve_field(resource: &mut Value, pointer: &str, derived_value: Option<Value>, preserved_fields: &mut PreservedFields) {
+    let value = pointer
+        .rsplit_once('/')
+        .and_then(|(parent_pointer, field)| resource.pointer_mut(parent_pointer).and_then(|parent| parent.as_object_mut()).and_then(|parent| parent.remove(field)));
+    if value != derived_value {
+        insert_preserved_field(preserved_fields, pointer, value.unwrap_or(Value::Null), derived_value);
+    }
+}
 
-//     status_obj.remove("updateTimestampNew");
-//     let timestamp = Utc::now().to_rfc3339();
-//     status_obj.insert("updateTimestamp".to_string(), Value::String(timestamp.clone()));
+/// Preserves the fields of a section (spec or status) that are unknown by the target version
+fn preserve_unknown_fields(resource: &mut Value, section: &str, known_fields: &[String], preserved_fields: &mut PreservedFields) {
+    let unknown_fields: Vec<String> = match resource.get(section).and_then(|s| s.as_object()) {
+        Some(section_obj) => section_obj.keys().filter(|field| !known_fields.contains(field)).cloned().collect(),
+        None => return,
+    };
+    for field in unknown_fields {
+        preserve_field(resource, &format!("/{}/{}", section, field), None, preserved_fields);
+    }
+}
 
-//     Ok(())
-// }
+fn generation(resource: &Value) -> Value {
+    resource.pointer("/metadata/generation").cloned().unwrap_or(Value::Number(0.into()))
+}
 
-async fn convert_cluster_hoprd_v2_to_v3(resource: &mut Value) -> Result<(), String> {
-    trace!("Convert clusterHoprd: v1alpha2 -> v1alpha3");
+fn spec_checksum(resource: &Value) -> Result<Value, String> {
+    let spec = resource.get("spec").ok_or("Missing 'spec' field in object")?;
+    let mut hasher: DefaultHasher = DefaultHasher::new();
+    spec.hash(&mut hasher);
+    Ok(Value::String(hasher.finish().to_string()))
+}
 
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in ClusterHoprd v3 object")?;
+fn add_observed_generation(resource: &mut Value) -> Result<(), String> {
+    let observed_generation = generation(resource);
+    trace!("Setting observedGeneration to {:?}", observed_generation);
+    let Some(status) = resource.get_mut("status") else {
+        return Ok(());
+    };
+    let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
+    status_obj.entry("observedGeneration").or_insert(observed_generation);
+    Ok(())
+}
 
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
+fn add_status_checksum(resource: &mut Value) -> Result<(), String> {
+    let checksum = spec_checksum(resource)?;
+    let Some(status) = resource.get_mut("status") else {
+        return Ok(());
+    };
+    let status_obj = status.as_object_mut().ok_or("Status is not a JSON object")?;
+    status_obj.entry("checksum").or_insert(checksum);
+    Ok(())
+}
 
+/// Converts between v1alpha2 and v1alpha3, delegating the kind specific changes and preserving the fields the target version cannot hold.
+/// The checksum of v1alpha2 and the observedGeneration of v1alpha3 are only preserved if they cannot be computed back.
+async fn convert_between_v2_and_v3(resource: &mut Value, target_version: &str) -> Result<(), String> {
+    let kind = resource.get("kind").and_then(|k| k.as_str()).ok_or("Missing 'kind' field")?.to_owned();
+    let (known_spec_fields, known_status_fields) = known_fields(&kind, target_version)?;
+    let previously_preserved_fields = take_preserved_fields(resource)?;
+    let mut preserved_fields = PreservedFields::new();
+    let has_status = resource.get("status").is_some();
+    if target_version == "v1alpha3" {
+        let checksum = if has_status { Some(spec_checksum(resource)?) } else { None };
+        preserve_field(resource, "/status/checksum", checksum, &mut preserved_fields);
+        match kind.as_str() {
+            "ClusterHoprd" => convert_cluster_hoprd_v2_to_v3(resource, &mut preserved_fields).await?,
+            "Hoprd" => convert_hoprd_v2_to_v3(resource, &mut preserved_fields).await?,
+            "IdentityHoprd" => convert_identity_hoprd_v2_to_v3(resource, &mut preserved_fields).await?,
+            _ => trace!("Convert {}: v1alpha2 -> v1alpha3", kind),
+        }
+    } else {
+        let observed_generation = if has_status { Some(generation(resource)) } else { None };
+        preserve_field(resource, "/status/observedGeneration", observed_generation, &mut preserved_fields);
+        match kind.as_str() {
+            "ClusterHoprd" => convert_cluster_hoprd_v3_to_v2(resource, &mut preserved_fields).await?,
+            "Hoprd" => convert_hoprd_v3_to_v2(resource, &mut preserved_fields).await?,
+            "IdentityHoprd" => convert_identity_hoprd_v3_to_v2(resource, &mut preserved_fields).await?,
+            _ => trace!("Convert {}: v1alpha3 -> v1alpha2", kind),
+        }
+    }
+    preserve_unknown_fields(resource, "spec", &known_spec_fields, &mut preserved_fields);
+    preserve_unknown_fields(resource, "status", &known_status_fields, &mut preserved_fields);
+    restore_preserved_fields(resource, previously_preserved_fields);
+    if target_version == "v1alpha3" {
+        add_observed_generation(resource)?;
+    } else {
+        add_status_checksum(resource)?;
+    }
+    store_preserved_fields(resource, preserved_fields)
+}
 
-    // Move spec.portsAllocation to spec.service.portsAllocation
-    // First, remove portsAllocation from spec_obj to avoid double mutable borrow
+/// Moves spec.portsAllocation to spec.service.portsAllocation
+fn move_ports_allocation_v2_to_v3(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v2 object")?;
     let ports_allocation_value = spec_obj.remove("portsAllocation");
-    if let Some(service) = spec_obj.get_mut("service").and_then(|p| p.as_object_mut()) {
-        if let Some(ports_allocation_value) = ports_allocation_value {
-            service.insert("portsAllocation".to_string(), ports_allocation_value);
-        } else {
-            warn!("spec.portsAllocation missing; adding default portsAllocation");
-            service.insert("portsAllocation".to_string(), Value::Number(10.into()));
+    match spec_obj.get_mut("service").and_then(|p| p.as_object_mut()) {
+        Some(service) => {
+            if let Some(ports_allocation_value) = ports_allocation_value {
+                service.insert("portsAllocation".to_string(), ports_allocation_value);
+            } else {
+                warn!("spec.portsAllocation missing; adding default portsAllocation");
+                service.insert("portsAllocation".to_string(), Value::Number(constants::HOPRD_PORTS_ALLOCATION.into()));
+                insert_preserved_field(preserved_fields, "/spec/portsAllocation", Value::Null, Some(Value::Number(constants::HOPRD_PORTS_ALLOCATION.into())));
+            }
+        }
+        None => {
+            if let Some(ports_allocation_value) = ports_allocation_value {
+                insert_preserved_field(preserved_fields, "/spec/portsAllocation", ports_allocation_value, None);
+            }
         }
     }
-
-    // Remove spec.supportedRelease
-    spec_obj.remove("supportedRelease");
-    // Remove spec.forceIdentityName
-    spec_obj.remove("forceIdentityName");
-
-    add_observed_generation(resource).await?;
-
     Ok(())
-
 }
 
-async fn convert_cluster_hoprd_v3_to_v2(resource: &mut Value) -> Result<(), String> {
-    trace!("Convert clusterHoprd: v1alpha3 -> v1alpha2");
-
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in ClusterHoprd v3 object")?;
-
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
-
-    // Move spec.service.portsAllocation to spec.portsAllocation
+/// Moves spec.service.portsAllocation to spec.portsAllocation
+fn move_ports_allocation_v3_to_v2(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v3 object")?;
     if let Some(service) = spec_obj.get_mut("service").and_then(|p| p.as_object_mut()) {
         if let Some(ports_allocation_value) = service.remove("portsAllocation") {
             spec_obj.insert("portsAllocation".to_string(), ports_allocation_value);
         } else {
             warn!("spec.service.portsAllocation missing; adding default portsAllocation");
-            spec_obj.insert("portsAllocation".to_string(), Value::Number(10.into()));
+            spec_obj.insert("portsAllocation".to_string(), Value::Number(constants::HOPRD_PORTS_ALLOCATION.into()));
+            insert_preserved_field(preserved_fields, "/spec/service/portsAllocation", Value::Null, Some(Value::Number(constants::HOPRD_PORTS_ALLOCATION.into())));
         }
     }
+    Ok(())
+}
+
+async fn convert_cluster_hoprd_v2_to_v3(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
+    trace!("Convert clusterHoprd: v1alpha2 -> v1alpha3");
+    move_ports_allocation_v2_to_v3(resource, preserved_fields)?;
+    preserve_field(resource, "/spec/supportedRelease", Some(Value::String("kaunas".to_string())), preserved_fields);
+    preserve_field(resource, "/spec/forceIdentityName", Some(Value::Bool(true)), preserved_fields);
+    Ok(())
+}
 
+async fn convert_cluster_hoprd_v3_to_v2(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
+    trace!("Convert clusterHoprd: v1alpha3 -> v1alpha2");
+    move_ports_allocation_v3_to_v2(resource, preserved_fields)?;
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v3 object")?;
     // Insert spec.supportedRelease with "kaunas" value
     spec_obj.insert("supportedRelease".to_string(), Value::String("kaunas".to_string()));
-
     // Insert spec.forceIdentityName with true value
     spec_obj.insert("forceIdentityName".to_string(), Value::Bool(true));
-
-    add_status_checksum(resource).await?;
-
     Ok(())
-
 }
 
-async fn convert_hoprd_v2_to_v3(resource: &mut Value) -> Result<(), String> {
+async fn convert_hoprd_v2_to_v3(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
     trace!("Convert hoprd: v1alpha2 -> v1alpha3");
-
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in Hoprd v3 object")?;
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
-
-    // Move spec.portsAllocation to spec.service.portsAllocation
-    // First, remove portsAllocation from spec_obj to avoid double mutable borrow
-    let ports_allocation_value = spec_obj.remove("portsAllocation");
-    if let Some(service) = spec_obj.get_mut("service").and_then(|p| p.as_object_mut()) {
-        if let Some(ports_allocation_value) = ports_allocation_value {
-            service.insert("portsAllocation".to_string(), ports_allocation_value);
-        } else {
-            warn!("spec.portsAllocation missing; adding default portsAllocation");
-            service.insert("portsAllocation".to_string(), Value::Number(10.into()));
-        }
-    }
-    // Remove spec.supportedRelease
-    spec_obj.remove("supportedRelease");
-
-    add_observed_generation(resource).await?;
-
+    move_ports_allocation_v2_to_v3(resource, preserved_fields)?;
+    preserve_field(resource, "/spec/supportedRelease", Some(Value::String("kaunas".to_string())), preserved_fields);
     Ok(())
-
-
 }
 
-async fn convert_hoprd_v3_to_v2(resource: &mut Value) -> Result<(), String> {
+async fn convert_hoprd_v3_to_v2(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
     trace!("Convert hoprd: v1alpha3 -> v1alpha2");
-
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in Hoprd v3 object")?;
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
-
-    // Move spec.service.portsAllocation to spec.portsAllocation
-    if let Some(service) = spec_obj.get_mut("service").and_then(|p| p.as_object_mut()) {
-        if let Some(ports_allocation_value) = service.remove("portsAllocation") {
-            spec_obj.insert("portsAllocation".to_string(), ports_allocation_value);
-        } else {
-            warn!("spec.service.portsAllocation missing; adding default portsAllocation");
-            spec_obj.insert("portsAllocation".to_string(), Value::Number(10.into()));
-        }
-    }
-
+    move_ports_allocation_v3_to_v2(resource, preserved_fields)?;
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v3 object")?;
     // Insert spec.supportedRelease with "kaunas" value
     spec_obj.insert("supportedRelease".to_string(), Value::String("kaunas".to_string()));
-
-    add_status_checksum(resource).await?;
-
     Ok(())
 }
 
-async fn convert_identity_hoprd_v2_to_v3(resource: &mut Value) -> Result<(), String> {
+async fn convert_identity_hoprd_v2_to_v3(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
     trace!("Convert identityHoprd: v1alpha2 -> v1alpha3");
-
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in IdentityHoprd v3 object")?;
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
-
+    preserve_field(resource, "/spec/peerId", Some(Value::String("unknown".into())), preserved_fields);
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v2 object")?;
     // Rename spec.nativeAddress to spec.nodeAddress
     if let Some(native_address) = spec_obj.remove("nativeAddress") {
         trace!("Renaming spec.nativeAddress to spec.nodeAddress");
         spec_obj.insert("nodeAddress".to_string(), native_address);
     } else {
-        warn!("spec.nativeAddress missing; adding placeholder nativeAddress");
+        warn!("spec.nativeAddress missing; adding placeholder nodeAddress");
         spec_obj.insert("nodeAddress".to_string(), Value::String("lost".into()));
+        insert_preserved_field(preserved_fields, "/spec/nativeAddress", Value::Null, Some(Value::String("lost".into())));
     }
-
-    // Remove spec.peerId if present
-    trace!("Removing spec.peerId if present");
-    spec_obj.remove("peerId");
-
-    add_observed_generation(resource).await?;
-
     Ok(())
-
 }
 
-async fn convert_identity_hoprd_v3_to_v2(resource: &mut Value) -> Result<(), String> {
+async fn convert_identity_hoprd_v3_to_v2(resource: &mut Value, preserved_fields: &mut PreservedFields) -> Result<(), String> {
     trace!("Convert identityHoprd: v1alpha3 -> v1alpha2");
-
-    let spec = resource
-        .get_mut("spec")
-        .ok_or("Missing 'spec' field in IdentityHoprd v3 object")?;
-    let spec_obj = spec
-        .as_object_mut()
-        .ok_or("Spec is not a JSON object")?;
-
+    let spec_obj = resource.get_mut("spec").and_then(|s| s.as_object_mut()).ok_or("Missing 'spec' field in v3 object")?;
     // Rename spec.nodeAddress to spec.nativeAddress
-    if let Some(native_address) = spec_obj.remove("nodeAddress") {
-        spec_obj.insert("nativeAddress".to_string(), native_address);
+    if let Some(node_address) = spec_obj.remove("nodeAddress") {
+        spec_obj.insert("nativeAddress".to_string(), node_address);
     } else {
         warn!("spec.nodeAddress missing; adding placeholder nativeAddress");
         spec_obj.insert("nativeAddress".to_string(), Value::String("unknown".into()));
+        insert_preserved_field(preserved_fields, "/spec/nodeAddress", Value::Null, Some(Value::String("unknown".into())));
     }
-
-    // Insert spec.peerId with unknown placeholder
+    // Insert spec.peerId with unknown placeholder, restored afterwards if it was preserved
     spec_obj.insert("peerId".to_string(), Value::String("unknown".into()));
-
-    add_status_checksum(resource).await?;
-
     Ok(())
-
 }
 
-async fn convert_identity_pool_v2_to_v3(resource: &mut Value) -> Result<(), String> {
-    trace!("Convert identityPool: v1alpha2 -> v1alpha3");
-    add_observed_generation(resource).await?;
-    // add_new_updated_timestamp(resource).await?;
-    trace!("Converted identityPool v2 to v3: {:?}", resource);
+/// Converts the `spec` of the resource between two of its typed versions
+fn convert_typed_spec<S, T>(resource: &mut Value) -> Result<(), String>
+where
+    S: DeserializeOwned,
+    T: Serialize + TryFrom<S, Error = crate::model::Error>,
+{
+    let spec = resource.get_mut("spec").ok_or("Missing 'spec' field in object")?;
+    let source_spec: S = serde_json::from_value(spec.clone()).map_err(|e| format!("Invalid 'spec' field: {}", e))?;
+    let target_spec = T::try_from(source_spec).map_err(|e| format!("Invalid 'spec' field: {}", e))?;
+    let mut target_spec = serde_json::to_value(target_spec).map_err(|e| e.to_string())?;
+    remove_null_fields(&mut target_spec);
+    *spec = target_spec;
     Ok(())
 }
 
-async fn convert_identity_pool_v3_to_v2(resource: &mut Value) -> Result<(), String> {
-    trace!("Convert identityPool: v1alpha3 -> v1alpha2");
-    add_status_checksum(resource).await?;
-    // add_old_updated_timestamp(resource).await?;
-    trace!("Converted identityPool v3 to v2: {:?}", resource);
-    Ok(())
+/// Unset optional fields are omitted instead of being sent as `null`
+fn remove_null_fields(value: &mut Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.retain(|_, field| !field.is_null());
+        object.values_mut().for_each(remove_null_fields);
+    }
 }
 
-async fn convert_v2_to_v3(resource: &mut Value) -> Result<(), String> {
+async fn convert_v3_to_beta1(resource: &mut Value) -> Result<(), String> {
     let kind = resource.get("kind").and_then(|k| k.as_str()).ok_or("Missing 'kind' field")?;
+    trace!("Convert {}: v1alpha3 -> v1beta1", kind);
     match kind {
-        "ClusterHoprd" => convert_cluster_hoprd_v2_to_v3(resource).await,
-        "Hoprd" => convert_hoprd_v2_to_v3(resource).await,
-        "IdentityHoprd" => convert_identity_hoprd_v2_to_v3(resource).await,
-        "IdentityPool" => convert_identity_pool_v2_to_v3(resource).await,
-        _ => {
-            Err(format!("Unsupported kind for conversion from v2 to v3: {}", kind))
-        }
+        "ClusterHoprd" => convert_typed_spec::<ClusterHoprdSpec, cluster_hoprd_v1beta1::ClusterHoprdSpec>(resource),
+        "Hoprd" => convert_typed_spec::<HoprdSpec, hoprd_v1beta1::HoprdSpec>(resource),
+        _ => Err(format!("Unsupported kind for conversion from v3 to v1beta1: {}", kind)),
     }
 }
 
-async fn convert_v3_to_v2(resource: &mut Value) -> Result<(), String> {
+async fn convert_beta1_to_v3(resource: &mut Value) -> Result<(), String> {
     let kind = resource.get("kind").and_then(|k| k.as_str()).ok_or("Missing 'kind' field")?;
+    trace!("Convert {}: v1beta1 -> v1alpha3", kind);
     match kind {
-        "ClusterHoprd" => convert_cluster_hoprd_v3_to_v2(resource).await,
-        "Hoprd" => convert_hoprd_v3_to_v2(resource).await,
-        "IdentityHoprd" => convert_identity_hoprd_v3_to_v2(resource).await,
-        "IdentityPool" => convert_identity_pool_v3_to_v2(resource).await,
-        _ => {
-            Err(format!("Unsupported kind for conversion from v3 to v2: {}", kind))
-        }
+        "ClusterHoprd" => convert_typed_spec::<cluster_hoprd_v1beta1::ClusterHoprdSpec, ClusterHoprdSpec>(resource),
+        "Hoprd" => convert_typed_spec::<hoprd_v1beta1::HoprdSpec, HoprdSpec>(resource),
+        _ => Err(format!("Unsupported kind for conversion from v1beta1 to v3: {}", kind)),
+    }
+}
+
+/// Converts the resource to the desired version. Conversions are chained through the storage version `v1alpha3`
+async fn convert_resource(resource: &mut Value, desired_api_version: &str) -> Result<(), String> {
+    let api_version = resource.get("apiVersion").and_then(|v| v.as_str()).ok_or("Missing 'apiVersion' field")?.to_owned();
+    if api_version == desired_api_version {
+        return Ok(());
+    }
+    match api_version.as_str() {
+        "hoprnet.org/v1alpha2" => convert_between_v2_and_v3(resource, "v1alpha3").await?,
+        "hoprnet.org/v1alpha3" => {}
+        "hoprnet.org/v1beta1" => convert_beta1_to_v3(resource).await?,
+        _ => return Err(format!("Unsupported API version: {}", api_version)),
+    }
+    match desired_api_version {
+        "hoprnet.org/v1alpha2" => convert_between_v2_and_v3(resource, "v1alpha2").await,
+        "hoprnet.org/v1alpha3" => Ok(()),
+        "hoprnet.org/v1beta1" => convert_v3_to_beta1(resource).await,
+        _ => Err(format!("Unsupported desired API version: {}", desired_api_version)),
     }
 }
 
@@ -426,16 +576,7 @@ async fn convert(Json(request): Json<ConversionRequest>) -> impl IntoResponse {
         let mut resource = obj.clone();
         trace!("Converting resource: {:?}", resource);
 
-        let conversion_result = match request.request.desired_apiversion.as_str() {
-            "hoprnet.org/v1alpha2" => Ok(convert_v3_to_v2(&mut resource).await),
-            "hoprnet.org/v1alpha3" => Ok(convert_v2_to_v3(&mut resource).await),
-            _ => {
-                let msg = format!("Unsupported desired API version: {}", request.request.desired_apiversion.as_str());
-                Err(msg)
-            }
-        };
-
-        if let Err(err_msg) = conversion_result {
+        if let Err(err_msg) = convert_resource(&mut resource, request.request.desired_apiversion.as_str()).await {
             response_inner.result.status = "Failure".to_string();
             response_inner.result.message = Some(err_msg.clone());
             error!("{}: {}", request.request.desired_apiversion.as_str(), err_msg);
@@ -451,4 +592,277 @@ async fn convert(Json(request): Json<ConversionRequest>) -> impl IntoResponse {
         kind: "ConversionReview".to_string(),
         response: response_inner,
     })
-}
\ No newline at end of file
+}
+/// Fills the service defaults that would be otherwise computed by the operator
+fn add_service_defaults(spec_obj: &mut serde_json::Map<String, Value>) -> Result<(), String> {
+    let default_service = serde_json::to_value(HoprdServiceSpec::default()).map_err(|e| e.to_string())?;
+    let service = spec_obj.entry("service").or_insert_with(|| Value::Object(serde_json::Map::new()));
+    let service_obj = service.as_object_mut().ok_or("Service is not a JSON object")?;
+    for (key, value) in default_service.as_object().ok_or("Default service is not a JSON object")? {
+        service_obj.entry(key.to_owned()).or_insert_with(|| value.clone());
+    }
+    Ok(())
+}
+
+/// Fills the deployment defaults that would be otherwise computed by the operator
+fn add_deployment_defaults(spec_obj: &mut serde_json::Map<String, Value>, source_node_logs: Option<bool>) -> Result<(), String> {
+    let deployment: Option<HoprdDeploymentSpec> = match spec_obj.get("deployment") {
+        Some(deployment) => serde_json::from_value(deployment.clone()).map_err(|e| format!("Invalid 'spec.deployment' field: {}", e))?,
+        None => None,
+    };
+    let deployment = HoprdDeploymentSpec::with_defaults(deployment, source_node_logs);
+    let mut deployment_value = serde_json::to_value(deployment).map_err(|e| e.to_string())?;
+    if let Some(deployment_obj) = deployment_value.as_object_mut() {
+        deployment_obj.retain(|_, value| !value.is_null());
+    }
+    spec_obj.insert("deployment".to_string(), deployment_value);
+    Ok(())
+}
+
+fn add_hoprd_defaults(resource: &mut Value) -> Result<(), String> {
+    trace!("Adding defaults to hoprd");
+    let spec = resource.get_mut("spec").ok_or("Missing 'spec' field in Hoprd object")?;
+    let spec_obj = spec.as_object_mut().ok_or("Spec is not a JSON object")?;
+    spec_obj.entry("enabled").or_insert(Value::Bool(true));
+    spec_obj.entry("deleteDatabase").or_insert(Value::Bool(false));
+    spec_obj.entry("profilingEnabled").or_insert(Value::Bool(false));
+    let source_node_logs = spec_obj.entry("sourceNodeLogs").or_insert(Value::Bool(false)).as_bool().unwrap_or(false);
+    add_service_defaults(spec_obj)?;
+    add_deployment_defaults(spec_obj, Some(source_node_logs))?;
+    Ok(())
+}
+
+fn add_cluster_hoprd_defaults(resource: &mut Value) -> Result<(), String> {
+    trace!("Adding defaults to clusterHoprd");
+    let spec = resource.get_mut("spec").ok_or("Missing 'spec' field in ClusterHoprd object")?;
+    let spec_obj = spec.as_object_mut().ok_or("Spec is not a JSON object")?;
+    spec_obj.entry("enabled").or_insert(Value::Bool(true));
+    spec_obj.entry("profilingEnabled").or_insert(Value::Bool(false));
+    add_service_defaults(spec_obj)?;
+    // Probes are defaulted on each Hoprd node, as they depend on which node is sourcing the logs
+    add_deployment_defaults(spec_obj, None)?;
+    Ok(())
+}
+
+fn add_defaults(kind: &str, resource: &mut Value) -> Result<(), String> {
+    match kind {
+        "Hoprd" => add_hoprd_defaults(resource),
+        "ClusterHoprd" => add_cluster_hoprd_defaults(resource),
+        _ => Err(format!("Unsupported kind for mutation: {}", kind)),
+    }
+}
+
+// Mutation handler
+async fn mutate(Json(review): Json<AdmissionReview<DynamicObject>>) -> impl IntoResponse {
+    trace!("Received admission review: {:?}", review);
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(error) => {
+            error!("Invalid admission request: {}", error);
+            return Json(AdmissionResponse::invalid(error.to_string()).into_review());
+        }
+    };
+    let response = AdmissionResponse::from(&request);
+    let Some(object) = request.object.as_ref() else {
+        return Json(response.into_review());
+    };
+    let original = match serde_json::to_value(object) {
+        Ok(original) => original,
+        Err(error) => return Json(response.deny(error.to_string()).into_review()),
+    };
+    let mut resource = original.clone();
+    if let Err(err_msg) = add_defaults(&request.kind.kind, &mut resource) {
+        error!("{}/{}: {}", request.kind.kind, request.name, err_msg);
+        return Json(response.deny(err_msg).into_review());
+    }
+    let patch = json_patch::diff(&original, &resource);
+    trace!("Mutation of {}/{} completed with {:?}", request.kind.kind, request.name, patch);
+    match response.clone().with_patch(patch) {
+        Ok(response) => Json(response.into_review()),
+        Err(error) => Json(response.deny(error.to_string()).into_review()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+    use rustls::crypto::ring::default_provider;
+    use serde_json::json;
+
+    #[test]
+    fn test_readiness_address() {
+        let address = |addr: &str| addr.parse::<SocketAddr>().unwrap();
+        assert_eq!(readiness_address(address("0.0.0.0:8443")), address("127.0.0.1:8443"));
+        assert_eq!(readiness_address(address("[::]:8443")), address("[::1]:8443"));
+        assert_eq!(readiness_address(address("10.0.0.5:8443")), address("10.0.0.5:8443"));
+    }
+
+    fn load_test_tls_config(cert_file: &str, key_file: &str) -> Result<ServerConfig, Error> {
+        let _ = default_provider().install_default();
+        load_rustls_config(&resolve_path(cert_file)?, &resolve_path(key_file)?)
+    }
+
+    #[test]
+    fn test_load_rustls_config_pkcs1_rsa_key() {
+        assert!(load_test_tls_config("test-data/webhook/rsa.crt", "test-data/webhook/rsa-pkcs1.key").is_ok());
+    }
+
+    #[test]
+    fn test_load_rustls_config_sec1_ec_key() {
+        assert!(load_test_tls_config("test-data/webhook/ec.crt", "test-data/webhook/ec-sec1.key").is_ok());
+    }
+
+    #[test]
+    fn test_load_rustls_config_mismatched_key() {
+        assert!(load_test_tls_config("test-data/webhook/rsa.crt", "test-data/webhook/ec-sec1.key").is_err());
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(resolve_path("/tls/tls.crt").unwrap(), PathBuf::from("/tls/tls.crt"));
+        assert_eq!(resolve_path("test-data/tls.crt").unwrap(), env::current_dir().unwrap().join("test-data/tls.crt"));
+    }
+
+    #[tokio::test]
+    async fn test_convert_round_trip_is_lossless() {
+        let fixtures = [
+            ("cluster-hoprd-v1alpha2.yaml", "hoprnet.org/v1alpha3"),
+            ("cluster-hoprd-v1alpha3.yaml", "hoprnet.org/v1alpha2"),
+            ("hoprd-v1alpha2.yaml", "hoprnet.org/v1alpha3"),
+            ("hoprd-v1alpha3.yaml", "hoprnet.org/v1alpha2"),
+            ("identity-hoprd-v1alpha2.yaml", "hoprnet.org/v1alpha3"),
+            ("identity-hoprd-v1alpha2-without-native-address.yaml", "hoprnet.org/v1alpha3"),
+            ("identity-hoprd-v1alpha3.yaml", "hoprnet.org/v1alpha2"),
+            ("identity-pool-v1alpha2.yaml", "hoprnet.org/v1alpha3"),
+            ("identity-pool-v1alpha3.yaml", "hoprnet.org/v1alpha2"),
+        ];
+        for (fixture, intermediate_api_version) in fixtures {
+            let original: Value = serde_yml::from_str(&std::fs::read_to_string(format!("test-data/conversion/{}", fixture)).unwrap()).unwrap();
+            let original_api_version = original["apiVersion"].as_str().unwrap().to_owned();
+            let mut resource = original.clone();
+            convert_resource(&mut resource, intermediate_api_version).await.unwrap();
+            resource["apiVersion"] = json!(intermediate_api_version);
+            let (known_spec_fields, known_status_fields) = known_fields(original["kind"].as_str().unwrap(), intermediate_api_version.trim_start_matches("hoprnet.org/")).unwrap();
+            assert!(resource["spec"].as_object().unwrap().keys().all(|field| known_spec_fields.contains(field)), "{}: unknown spec fields", fixture);
+            assert!(resource["status"].as_object().is_none_or(|status| status.keys().all(|field| known_status_fields.contains(field))), "{}: unknown status fields", fixture);
+            convert_resource(&mut resource, &original_api_version).await.unwrap();
+            resource["apiVersion"] = json!(original_api_version);
+            assert_eq!(resource, original, "{} is not preserved after converting to {}", fixture, intermediate_api_version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_without_lost_fields_has_no_annotation() {
+        let mut hoprd: Value = serde_yml::from_str(&std::fs::read_to_string("test-data/conversion/hoprd-v1alpha3.yaml").unwrap()).unwrap();
+        hoprd["spec"].as_object_mut().unwrap().remove("dnsName");
+        convert_resource(&mut hoprd, "hoprnet.org/v1alpha2").await.unwrap();
+        assert!(hoprd.pointer("/metadata/annotations").is_none());
+        assert_eq!(hoprd["spec"]["supportedRelease"], json!("kaunas"));
+        assert_eq!(hoprd["spec"]["portsAllocation"], json!(4));
+    }
+
+    #[test]
+    fn test_known_fields_match_chart_schema() {
+        let charts = [("ClusterHoprd", "cluster-hoprd"), ("Hoprd", "hoprd"), ("IdentityHoprd", "identity-hoprd"), ("IdentityPool", "identity-pool")];
+        for (kind, chart) in charts {
+            for version in ["v1alpha2", "v1alpha3"] {
+                let schema = utils::crd_schema_from_chart(&format!("charts/hoprd-crds/templates/{}.yaml", chart), version);
+                let chart_fields = |section: &str| {
+                    let mut fields: Vec<String> = schema["openAPIV3Schema"]["properties"][section]["properties"].as_object().unwrap().keys().cloned().collect();
+                    fields.sort();
+                    fields
+                };
+                let (mut spec_fields, mut status_fields) = known_fields(kind, version).unwrap();
+                spec_fields.sort();
+                status_fields.sort();
+                assert_eq!(spec_fields, chart_fields("spec"), "{} {} spec fields", kind, version);
+                assert_eq!(status_fields, chart_fields("status"), "{} {} status fields", kind, version);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_convert_discards_preserved_fields_written_since() {
+        let mut hoprd: Value = serde_yml::from_str(&std::fs::read_to_string("test-data/conversion/hoprd-v1alpha3.yaml").unwrap()).unwrap();
+        hoprd["spec"]["service"].as_object_mut().unwrap().remove("portsAllocation");
+        convert_resource(&mut hoprd, "hoprnet.org/v1alpha2").await.unwrap();
+        hoprd["apiVersion"] = json!("hoprnet.org/v1alpha2");
+        let mut unchanged = hoprd.clone();
+        convert_resource(&mut unchanged, "hoprnet.org/v1alpha3").await.unwrap();
+        assert!(unchanged.pointer("/spec/service/portsAllocation").is_none());
+        hoprd["spec"]["portsAllocation"] = json!(8);
+        convert_resource(&mut hoprd, "hoprnet.org/v1alpha3").await.unwrap();
+        assert_eq!(hoprd["spec"]["service"]["portsAllocation"], json!(8));
+    }
+
+    #[tokio::test]
+    async fn test_convert_without_ports_allocation_uses_default() {
+        let mut hoprd: Value = serde_yml::from_str(&std::fs::read_to_string("test-data/conversion/hoprd-v1alpha2.yaml").unwrap()).unwrap();
+        hoprd["spec"].as_object_mut().unwrap().remove("portsAllocation");
+        hoprd["spec"]["service"] = json!({ "type": "ClusterIP" });
+        convert_resource(&mut hoprd, "hoprnet.org/v1alpha3").await.unwrap();
+        assert_eq!(hoprd["spec"]["service"]["portsAllocation"], json!(HoprdServiceSpec::default().ports_allocation));
+    }
+
+    #[tokio::test]
+    async fn test_convert_hoprd_v3_to_beta1() {
+        let mut hoprd = json!({
+            "apiVersion": "hoprnet.org/v1alpha3",
+            "kind": "Hoprd",
+            "spec": {
+                "version": "latest", "identityPoolName": "pool", "identityName": "pool-1", "config": "",
+                "service": { "type": "ClusterIP", "portsAllocation": 4 },
+                "deployment": {
+                    "env": "- name: RUST_LOG\n  value: debug\n- name: SECRET\n  value_from:\n    secret_key_ref:\n      key: key\n      name: secret\n",
+                    "resources": "limits:\n  cpu: 100m\n"
+                }
+            }
+        });
+        convert_resource(&mut hoprd, "hoprnet.org/v1beta1").await.unwrap();
+        let deployment = &hoprd["spec"]["deployment"];
+        assert_eq!(deployment["env"][0], json!({ "name": "RUST_LOG", "value": "debug" }));
+        assert_eq!(deployment["env"][1], json!({ "name": "SECRET", "valueFrom": { "secretKeyRef": { "key": "key", "name": "secret" } } }));
+        assert_eq!(deployment["resources"], json!({ "limits": { "cpu": "100m" } }));
+        assert!(deployment.get("startupProbe").is_none());
+
+        hoprd["apiVersion"] = json!("hoprnet.org/v1beta1");
+        convert_resource(&mut hoprd, "hoprnet.org/v1alpha3").await.unwrap();
+        let deployment: HoprdDeploymentSpec = serde_json::from_value(hoprd["spec"]["deployment"].clone()).unwrap();
+        assert_eq!(HoprdDeploymentSpec::get_resource_requirements(Some(deployment.clone())).limits.unwrap()["cpu"].0, "100m");
+        let env = HoprdDeploymentSpec::get_environment_variables(Some(deployment)).unwrap();
+        assert!(env.iter().any(|env_var| env_var.name == "SECRET" && env_var.value_from.is_some()));
+    }
+
+    #[test]
+    fn test_add_hoprd_defaults() {
+        let mut hoprd = json!({
+            "apiVersion": "hoprnet.org/v1alpha3",
+            "kind": "Hoprd",
+            "spec": { "version": "latest", "identityPoolName": "pool", "identityName": "pool-1", "config": "", "sourceNodeLogs": true }
+        });
+        add_defaults("Hoprd", &mut hoprd).unwrap();
+        let spec = &hoprd["spec"];
+        assert_eq!(spec["enabled"], json!(true));
+        assert_eq!(spec["deleteDatabase"], json!(false));
+        assert_eq!(spec["service"], json!({ "type": "ClusterIP", "portsAllocation": 4 }));
+        let deployment: HoprdDeploymentSpec = serde_json::from_value(spec["deployment"].clone()).unwrap();
+        assert_eq!(HoprdDeploymentSpec::get_startup_probe(Some(deployment), false), Some(HoprdDeploymentSpec::default_startup_probe(true)));
+    }
+
+    #[test]
+    fn test_add_cluster_hoprd_defaults_keeps_user_values() {
+        let mut cluster_hoprd = json!({
+            "apiVersion": "hoprnet.org/v1alpha3",
+            "kind": "ClusterHoprd",
+            "spec": { "enabled": false, "service": { "type": "LoadBalancer" }, "deployment": { "resources": "limits: {}" } }
+        });
+        add_defaults("ClusterHoprd", &mut cluster_hoprd).unwrap();
+        let spec = &cluster_hoprd["spec"];
+        assert_eq!(spec["enabled"], json!(false));
+        assert_eq!(spec["service"], json!({ "type": "LoadBalancer", "portsAllocation": 4 }));
+        assert_eq!(spec["deployment"]["resources"], json!("limits: {}"));
+        assert!(spec["deployment"].get("startupProbe").is_none());
+        assert!(spec["deployment"].get("env").is_some());
+    }
+}